@@ -0,0 +1,101 @@
+use std::ffi;
+use std::io;
+use std::os::raw::{c_char, c_int};
+
+use log;
+
+use api;
+use errors::LogError;
+use loggers;
+
+const ANDROID_LOG_VERBOSE: c_int = 2;
+const ANDROID_LOG_DEBUG: c_int = 3;
+const ANDROID_LOG_INFO: c_int = 4;
+const ANDROID_LOG_WARN: c_int = 5;
+const ANDROID_LOG_ERROR: c_int = 6;
+
+#[link(name = "log")]
+extern "C" {
+    fn __android_log_write(prio: c_int, tag: *const c_char, text: *const c_char) -> c_int;
+}
+
+/// The signature of `__android_log_write()`, which `AndroidLogger` sends messages through: the
+/// priority, tag and message, returning a negative number on failure.
+pub type AndroidLogWrite = unsafe extern "C" fn(c_int, *const c_char, *const c_char) -> c_int;
+
+/// A logger sending messages to Android's logcat, using each message's target as the tag.
+///
+/// Levels are mapped to Android priorities: `Error` to `ANDROID_LOG_ERROR`, `Warn` to
+/// `ANDROID_LOG_WARN`, `Info` to `ANDROID_LOG_INFO`, `Debug` to `ANDROID_LOG_DEBUG` and `Trace`
+/// to `ANDROID_LOG_VERBOSE`.
+///
+/// This is only available on Android, with the `android` feature enabled.
+pub struct AndroidLogger {
+    write: AndroidLogWrite,
+}
+
+impl AndroidLogger {
+    /// Creates a logger writing to logcat with `__android_log_write()`.
+    pub fn new() -> AndroidLogger {
+        return AndroidLogger::with_write_fn(__android_log_write);
+    }
+
+    /// Creates a logger which calls the given function instead of `__android_log_write()`, such
+    /// as a stub recording messages in tests.
+    pub fn with_write_fn(write: AndroidLogWrite) -> AndroidLogger {
+        return AndroidLogger {
+            write: write,
+        };
+    }
+}
+
+/// Converts a message into a C string, replacing any nul bytes, which can't be represented.
+fn to_c_string(msg: &str) -> ffi::CString {
+    return match ffi::CString::new(msg) {
+        Ok(s) => s,
+        Err(_) => ffi::CString::new(msg.replace('\0', "\\0")).unwrap(),
+    };
+}
+
+fn priority(level: &log::LogLevel) -> c_int {
+    return match *level {
+        log::LogLevel::Error => ANDROID_LOG_ERROR,
+        log::LogLevel::Warn => ANDROID_LOG_WARN,
+        log::LogLevel::Info => ANDROID_LOG_INFO,
+        log::LogLevel::Debug => ANDROID_LOG_DEBUG,
+        log::LogLevel::Trace => ANDROID_LOG_VERBOSE,
+    };
+}
+
+impl api::Logger for AndroidLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, _location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        let c_tag = to_c_string(target);
+        let c_msg = to_c_string(msg);
+        // liblog reports failures as a negative errno
+        let result = unsafe { (self.write)(priority(level), c_tag.as_ptr(), c_msg.as_ptr()) };
+        if result < 0 {
+            return Err(LogError::from(io::Error::from_raw_os_error(-result)));
+        }
+        return Ok(());
+    }
+
+    fn describe(&self) -> String {
+        return "logcat".to_string();
+    }
+}
+
+impl log::Log for AndroidLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        loggers::log_with_fern_logger(self, record);
+    }
+}
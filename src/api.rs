@@ -1,3 +1,5 @@
+use std::sync;
+
 use log;
 
 use errors::LogError;
@@ -8,6 +10,59 @@ pub trait Logger: Sync + Send {
     /// Logs a given message in this logger.
     fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
             -> Result<(), LogError>;
+
+    /// Logs a given message in this logger, along with the target it was logged to. The target is
+    /// the module path unless given explicitly, as in `info!(target: "audit", ...)`.
+    ///
+    /// The default implementation ignores the target and calls `log()`. Loggers which pass
+    /// messages on to other loggers should override this to pass the target along.
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            _target: &str) -> Result<(), LogError> {
+        self.log(msg, level, location)
+    }
+
+    /// Logs a raw byte payload in this logger, such as a binary protocol dump which may not be
+    /// valid UTF-8.
+    ///
+    /// The default implementation converts the bytes to a string, replacing any invalid UTF-8
+    /// sequences with `U+FFFD REPLACEMENT CHARACTER`, and calls `log()`. Loggers writing to byte
+    /// oriented outputs can override this to write the bytes unchanged.
+    fn log_bytes(&self, bytes: &[u8], level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        self.log(&String::from_utf8_lossy(bytes), level, location)
+    }
+
+    /// Writes out any messages this logger has buffered.
+    ///
+    /// The default implementation does nothing, which is correct for loggers that don't buffer.
+    fn flush(&self) -> Result<(), LogError> {
+        return Ok(());
+    }
+
+    /// Returns whether this logger may buffer messages, and so needs `flush()` to be called.
+    /// Loggers which pass messages on to others use this to skip flushing outputs which don't
+    /// buffer, avoiding pointless work such as taking locks.
+    ///
+    /// The default implementation returns true, which is always safe.
+    fn needs_flush(&self) -> bool {
+        return true;
+    }
+
+    /// Returns a short human readable description of this logger, such as `stdout` or
+    /// `file (output.log)`. This is used for introspection, for instance to show the current
+    /// logging configuration - it isn't used when logging.
+    fn describe(&self) -> String {
+        return "custom".to_string();
+    }
+
+    /// Checks this logger's configuration without logging anything, returning a description of
+    /// each problem found, such as a host name which doesn't resolve. This is used by
+    /// `fern::validate()` for custom outputs.
+    ///
+    /// The default implementation finds no problems.
+    fn validate(&self) -> Vec<String> {
+        return Vec::new();
+    }
 }
 
 impl Logger for Box<Logger> {
@@ -15,4 +70,63 @@ impl Logger for Box<Logger> {
             -> Result<(), LogError> {
         (**self).log(msg, level, location)
     }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        (**self).log_with_target(msg, level, location, target)
+    }
+
+    fn log_bytes(&self, bytes: &[u8], level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        (**self).log_bytes(bytes, level, location)
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        (**self).flush()
+    }
+
+    fn needs_flush(&self) -> bool {
+        (**self).needs_flush()
+    }
+
+    fn describe(&self) -> String {
+        (**self).describe()
+    }
+
+    fn validate(&self) -> Vec<String> {
+        (**self).validate()
+    }
+}
+
+impl <T: Logger + ?Sized> Logger for sync::Arc<T> {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        (**self).log(msg, level, location)
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        (**self).log_with_target(msg, level, location, target)
+    }
+
+    fn log_bytes(&self, bytes: &[u8], level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        (**self).log_bytes(bytes, level, location)
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        (**self).flush()
+    }
+
+    fn needs_flush(&self) -> bool {
+        (**self).needs_flush()
+    }
+
+    fn describe(&self) -> String {
+        (**self).describe()
+    }
+
+    fn validate(&self) -> Vec<String> {
+        (**self).validate()
+    }
 }
@@ -0,0 +1,144 @@
+use std::fs;
+use std::io::Write;
+use std::io;
+use std::path;
+use std::process;
+use std::sync::atomic;
+
+use log;
+
+use api;
+use errors::LogError;
+
+/// Stores binary artifacts belonging to log messages, such as a captured packet or a
+/// screenshot, as files named by the SHA-256 hash of their contents, and logs messages
+/// referring to them:
+///
+/// ```rust,no_run
+/// # extern crate fern;
+/// # extern crate log;
+/// # fn setup(logger: &fern::Logger, packet: &[u8]) -> Result<(), fern::LogError> {
+/// static LOCATION: log::LogLocation = log::LogLocation {
+///     __module_path: module_path!(),
+///     __file: file!(),
+///     __line: line!(),
+/// };
+/// let blobs = try!(fern::BlobStore::new("logs/blobs"));
+/// try!(blobs.log(logger, "malformed packet", packet, &log::LogLevel::Warn, &LOCATION));
+/// // Logs "malformed packet [blob logs/blobs/9f86d081...]"
+/// # Ok(())
+/// # }
+/// # fn main() {}
+/// ```
+///
+/// Storing the same bytes twice only keeps one file. Files are written to a temporary name
+/// and then renamed, so a file with a hash as its name always has all of its contents.
+pub struct BlobStore {
+    dir: path::PathBuf,
+    // Numbers the temporary files, so that threads storing the same blob don't collide
+    next_temp: atomic::AtomicUsize,
+}
+
+impl BlobStore {
+    /// Creates a store keeping its files in `dir`, which is created if it doesn't exist.
+    pub fn new<P: AsRef<path::Path>>(dir: P) -> io::Result<BlobStore> {
+        try!(fs::create_dir_all(dir.as_ref()));
+        return Ok(BlobStore {
+            dir: dir.as_ref().to_path_buf(),
+            next_temp: atomic::AtomicUsize::new(0),
+        });
+    }
+
+    /// Stores `blob`, unless the same bytes are already stored, and returns the path of its
+    /// file.
+    pub fn store(&self, blob: &[u8]) -> io::Result<path::PathBuf> {
+        let path = self.dir.join(to_hex(&sha256(blob)));
+        if path.exists() {
+            return Ok(path);
+        }
+        let temp = self.dir.join(format!(".tmp-{}-{}", process::id(),
+                                         self.next_temp.fetch_add(1, atomic::Ordering::SeqCst)));
+        let written = fs::File::create(&temp).and_then(|mut file| {
+            try!(file.write_all(blob));
+            file.sync_all()
+        });
+        if let Err(e) = written.and_then(|()| fs::rename(&temp, &path)) {
+            let _ = fs::remove_file(&temp);
+            return Err(e);
+        }
+        return Ok(path);
+    }
+
+    /// Stores `blob`, and then logs `msg` to `logger` followed by the blob's path, as in
+    /// `message [blob <path>]`. Nothing is logged if the blob can't be stored.
+    pub fn log(&self, logger: &api::Logger, msg: &str, blob: &[u8], level: &log::LogLevel,
+               location: &log::LogLocation) -> Result<(), LogError> {
+        let path = try!(self.store(blob));
+        return logger.log(&format!("{} [blob {}]", msg, path.display()), level, location);
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    return bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+}
+
+const SHA256_ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Returns the SHA-256 hash of `data`, as specified in FIPS 180-4.
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut state: [u32; 8] = [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f,
+                               0x9b05688c, 0x1f83d9ab, 0x5be0cd19];
+    // The data is followed by a one bit, zeros up to 8 bytes before the end of a block, and the
+    // length in bits
+    let mut padded = data.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in padded.chunks(64) {
+        let mut schedule = [0u32; 64];
+        for i in 0..16 {
+            schedule[i] = u32::from_be_bytes([block[i * 4], block[i * 4 + 1], block[i * 4 + 2],
+                                              block[i * 4 + 3]]);
+        }
+        for i in 16..64 {
+            let s0 = schedule[i - 15].rotate_right(7) ^ schedule[i - 15].rotate_right(18)
+                     ^ (schedule[i - 15] >> 3);
+            let s1 = schedule[i - 2].rotate_right(17) ^ schedule[i - 2].rotate_right(19)
+                     ^ (schedule[i - 2] >> 10);
+            schedule[i] = schedule[i - 16].wrapping_add(s0).wrapping_add(schedule[i - 7])
+                                          .wrapping_add(s1);
+        }
+        let mut v = state;
+        for i in 0..64 {
+            let s1 = v[4].rotate_right(6) ^ v[4].rotate_right(11) ^ v[4].rotate_right(25);
+            let choice = (v[4] & v[5]) ^ (!v[4] & v[6]);
+            let t1 = v[7].wrapping_add(s1).wrapping_add(choice)
+                         .wrapping_add(SHA256_ROUND_CONSTANTS[i]).wrapping_add(schedule[i]);
+            let s0 = v[0].rotate_right(2) ^ v[0].rotate_right(13) ^ v[0].rotate_right(22);
+            let majority = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+            let t2 = s0.wrapping_add(majority);
+            v = [t1.wrapping_add(t2), v[0], v[1], v[2], v[3].wrapping_add(t1), v[4], v[5], v[6]];
+        }
+        for i in 0..8 {
+            state[i] = state[i].wrapping_add(v[i]);
+        }
+    }
+
+    let mut hash = [0; 32];
+    for (i, word) in state.iter().enumerate() {
+        hash[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    return hash;
+}
@@ -0,0 +1,73 @@
+use std::cell;
+use std::thread;
+
+thread_local! {
+    // Messages captured by each active `capture()` call on this thread, innermost last
+    static CAPTURES: cell::RefCell<Vec<Vec<String>>> = cell::RefCell::new(Vec::new());
+}
+
+/// Calls `f`, and returns every message logged by fern loggers on the current thread while it
+/// runs, instead of sending them to the loggers' outputs. This is meant for tests checking
+/// what a library logs:
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate log;
+/// extern crate fern;
+/// # fn main() {
+/// # if cfg!(feature = "static_max_level") { return; }
+/// # fern::init_global_logger(fern::DispatchConfig {
+/// #     format: Box::new(|msg, _level, _location| msg.to_string()),
+/// #     output: vec![fern::OutputConfig::null()],
+/// #     level: log::LogLevelFilter::Info,
+/// #     directives: vec![],
+/// # }, log::LogLevelFilter::Info).unwrap();
+/// let lines = fern::capture(|| {
+///     info!("connecting");
+///     debug!("below the level");
+/// });
+/// assert_eq!(lines, vec!["connecting"]);
+/// # }
+/// ```
+///
+/// Messages are captured by the first `DispatchLogger` they reach, such as the global logger,
+/// formatted as they would be for its outputs, and only if they pass its level and directives.
+/// Messages logged on other threads, including those an output logs on a background thread,
+/// aren't captured. With nested calls, only the innermost one captures each message.
+pub fn capture<F>(f: F) -> Vec<String> where F: FnOnce() {
+    CAPTURES.with(|captures| captures.borrow_mut().push(Vec::new()));
+    // Removes this call's messages even if `f` panics
+    let guard = CaptureGuard;
+    f();
+    drop(guard);
+    return CAPTURES.with(|captures| captures.borrow_mut().pop().unwrap_or_default());
+}
+
+struct CaptureGuard;
+
+impl Drop for CaptureGuard {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            let _ = CAPTURES.try_with(|captures| captures.borrow_mut().pop());
+        }
+    }
+}
+
+/// Returns whether messages logged on this thread are being captured.
+pub fn is_capturing() -> bool {
+    return CAPTURES.try_with(|captures| !captures.borrow().is_empty()).unwrap_or(false);
+}
+
+/// Adds a message to the innermost active capture on this thread. Returns false if there isn't
+/// one.
+pub fn push(msg: &str) -> bool {
+    return CAPTURES.try_with(|captures| {
+        match captures.borrow_mut().last_mut() {
+            Some(capture) => {
+                capture.push(msg.to_string());
+                true
+            },
+            None => false,
+        }
+    }).unwrap_or(false);
+}
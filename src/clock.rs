@@ -0,0 +1,114 @@
+//! The clock fern's presets and formatters read timestamps from, which can cache the time to
+//! avoid reading the system clock for every message:
+//!
+//! ```rust
+//! # extern crate fern;
+//! # fn main() {
+//! use std::time::Duration;
+//! fern::clock::cache_clock(Some(Duration::from_millis(5)));
+//! let now = fern::clock::now_utc();
+//! # let _ = now;
+//! # fern::clock::cache_clock(None);
+//! # }
+//! ```
+
+use std::sync;
+use std::sync::atomic;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use time;
+
+/// Time last read by the refresh thread, as nanoseconds since the Unix epoch.
+static CACHED_NANOS: atomic::AtomicI64 = atomic::AtomicI64::new(0);
+
+/// Whether `get_time()` returns the cached time.
+static CACHING: atomic::AtomicBool = atomic::AtomicBool::new(false);
+
+/// Whether reads of the system clock are counted in `READS`, which is only turned on by tests.
+static COUNTING: atomic::AtomicBool = atomic::AtomicBool::new(false);
+
+/// Number of times the system clock has been read since counting was turned on.
+static READS: atomic::AtomicU64 = atomic::AtomicU64::new(0);
+
+/// Stops the refresh thread when dropped.
+static REFRESH: sync::Mutex<Option<mpsc::Sender<()>>> = sync::Mutex::new(None);
+
+fn read_clock() -> time::Timespec {
+    // Relaxed, as this is on every message's path and only needs to be counted eventually
+    if COUNTING.load(atomic::Ordering::Relaxed) {
+        READS.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+    return time::get_time();
+}
+
+fn store_cached(now: time::Timespec) {
+    CACHED_NANOS.store(now.sec * 1_000_000_000 + now.nsec as i64, atomic::Ordering::SeqCst);
+}
+
+/// Sets whether `get_time()`, `now()` and `now_utc()` return a cached time,
+/// refreshed by a background thread every `refresh`, instead of reading the system clock each
+/// time. At high volume, reading the clock for every message can take a good part of the time
+/// spent logging, so this trades the precision of timestamps for throughput: times may be up to
+/// `refresh` behind, and messages logged within one refresh share the same time.
+///
+/// The presets and formatters fern provides, such as `Preset::Detailed` and `json_formatter()`,
+/// get their timestamps from this clock, so they use the cached time too. By default, nothing is
+/// cached. Calling this again changes the interval, and `None` goes back to reading the clock
+/// each time, stopping the thread.
+pub fn cache_clock(refresh: Option<Duration>) {
+    let mut thread = match REFRESH.lock() {
+        Ok(thread) => thread,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    // Dropping the sender stops the previous thread
+    *thread = None;
+    let refresh = match refresh {
+        Some(refresh) => refresh,
+        None => {
+            CACHING.store(false, atomic::Ordering::SeqCst);
+            return;
+        },
+    };
+    store_cached(read_clock());
+    CACHING.store(true, atomic::Ordering::SeqCst);
+    let (stop, stopped) = mpsc::channel::<()>();
+    thread::spawn(move || {
+        // Nothing is ever sent, so this only returns once the sender has been dropped
+        while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(refresh) {
+            store_cached(read_clock());
+        }
+    });
+    *thread = Some(stop);
+}
+
+/// Returns the current time, like `time::get_time()`, or the cached time if caching has been
+/// turned on with `cache_clock()`.
+pub fn get_time() -> time::Timespec {
+    if !CACHING.load(atomic::Ordering::SeqCst) {
+        return read_clock();
+    }
+    let nanos = CACHED_NANOS.load(atomic::Ordering::SeqCst);
+    return time::Timespec::new(nanos.div_euclid(1_000_000_000),
+                               nanos.rem_euclid(1_000_000_000) as i32);
+}
+
+/// Returns the current local time, like `time::now()`, from `get_time()`.
+pub fn now() -> time::Tm {
+    return time::at(get_time());
+}
+
+/// Returns the current UTC time, like `time::now_utc()`, from `get_time()`.
+pub fn now_utc() -> time::Tm {
+    return time::at_utc(get_time());
+}
+
+/// Turns on counting reads of the system clock, and returns how many there have been since,
+/// including the reads of the thread refreshing the cached time. This is only meant for fern's
+/// tests, to check how much caching saves.
+#[doc(hidden)]
+pub fn counted_reads() -> u64 {
+    COUNTING.store(true, atomic::Ordering::SeqCst);
+    return READS.load(atomic::Ordering::SeqCst);
+}
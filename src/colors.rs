@@ -0,0 +1,121 @@
+use std::env;
+
+use log;
+
+use config;
+
+/// An RGB color for `LevelColors`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+/// Which ANSI escape sequences `LevelColors` uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorMode {
+    /// The 8 basic colors, which every color terminal supports. Each color is shown as the
+    /// closest basic one.
+    Basic,
+    /// The 256-color palette, with each color shown as the closest one in its 6x6x6 color
+    /// cube.
+    Ansi256,
+    /// 24-bit colors, shown exactly. This is only used if the `COLORTERM` environment variable
+    /// is `truecolor` or `24bit`, as set by terminals which support it, and `Basic` is used
+    /// otherwise.
+    TrueColor,
+}
+
+/// Colors for each level, for formatters which color the level or the whole message on
+/// terminals:
+///
+/// ```rust
+/// # extern crate fern;
+/// # extern crate log;
+/// # fn main() {
+/// let colors = fern::LevelColors::new()
+///     .level(log::LogLevel::Info, fern::Rgb(95, 175, 255))
+///     .mode(fern::ColorMode::TrueColor);
+/// let format = Box::new(move |msg: &str, level: &log::LogLevel, _location: &log::LogLocation| {
+///     format!("[{}] {}", colors.paint_level(level), msg)
+/// });
+/// # let _ = format;
+/// # }
+/// ```
+///
+/// The default colors are red for `Error`, yellow for `Warn`, green for `Info`, blue for `Debug`
+/// and white for `Trace`, shown with `ColorMode::Basic`.
+#[derive(Clone, Debug)]
+pub struct LevelColors {
+    colors: [Rgb; 5],
+    mode: ColorMode,
+}
+
+impl LevelColors {
+    /// Creates the default colors.
+    pub fn new() -> LevelColors {
+        return LevelColors {
+            colors: [Rgb(205, 0, 0), Rgb(205, 205, 0), Rgb(0, 205, 0), Rgb(0, 0, 238),
+                     Rgb(229, 229, 229)],
+            mode: ColorMode::Basic,
+        };
+    }
+
+    /// Sets the color for `level`.
+    pub fn level(mut self, level: log::LogLevel, color: Rgb) -> LevelColors {
+        self.colors[level as usize - 1] = color;
+        return self;
+    }
+
+    /// Sets which escape sequences are used. `ColorMode::TrueColor` falls back to
+    /// `ColorMode::Basic` if `COLORTERM` says the terminal doesn't support it, which is checked
+    /// here rather than for every message.
+    pub fn mode(mut self, mode: ColorMode) -> LevelColors {
+        self.mode = match mode {
+            ColorMode::TrueColor if !truecolor_supported() => ColorMode::Basic,
+            mode => mode,
+        };
+        return self;
+    }
+
+    /// Returns the mode in use, after any fallback.
+    pub fn current_mode(&self) -> ColorMode {
+        return self.mode;
+    }
+
+    /// Returns the escape sequence setting the foreground color to the color for `level`.
+    pub fn escape(&self, level: &log::LogLevel) -> String {
+        let Rgb(r, g, b) = self.colors[*level as usize - 1];
+        return match self.mode {
+            ColorMode::Basic => {
+                let code = (r > 127) as u8 | ((g > 127) as u8) << 1 | ((b > 127) as u8) << 2;
+                format!("\x1b[{}m", 30 + code)
+            },
+            ColorMode::Ansi256 => {
+                // The cube's levels are 0, 95, 135, 175, 215 and 255
+                let cube = |c: u8| match c {
+                    0..=47 => 0,
+                    48..=114 => 1,
+                    c => (c as u16 - 35) / 40,
+                };
+                format!("\x1b[38;5;{}m", 16 + 36 * cube(r) + 6 * cube(g) + cube(b))
+            },
+            ColorMode::TrueColor => format!("\x1b[38;2;{};{};{}m", r, g, b),
+        };
+    }
+
+    /// Returns `text` in the color for `level`, followed by a reset.
+    pub fn paint(&self, level: &log::LogLevel, text: &str) -> String {
+        return format!("{}{}\x1b[0m", self.escape(level), text);
+    }
+
+    /// Returns the name of `level`, as given by `fern::level_str()`, in its color.
+    pub fn paint_level(&self, level: &log::LogLevel) -> String {
+        return self.paint(level, config::level_str(level));
+    }
+}
+
+/// Returns whether `COLORTERM` says the terminal supports 24-bit colors.
+fn truecolor_supported() -> bool {
+    return match env::var("COLORTERM") {
+        Ok(value) => value == "truecolor" || value == "24bit",
+        Err(_) => false,
+    };
+}
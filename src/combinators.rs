@@ -0,0 +1,215 @@
+use std::sync;
+use std::time::Duration;
+
+use log;
+
+use api;
+use errors::LogError;
+use loggers;
+
+/// Methods for wrapping any logger in the usual wrapper loggers, so that a chain of them reads
+/// fluently instead of as nested constructor calls:
+///
+/// ```rust
+/// # extern crate fern;
+/// # extern crate log;
+/// # fn main() {
+/// use std::time::Duration;
+/// use fern::LoggerExt;
+///
+/// let stderr = fern::WriterLogger::<std::io::Stderr>::with_stderr();
+/// let logger = stderr.truncated(1000)
+///                    .deduplicated()
+///                    .rate_limited(Duration::from_secs(1));
+/// # let _ = logger;
+/// # }
+/// ```
+///
+/// Each method wraps the logger built so far, so the last one in the chain sees a message
+/// first: in the example above, messages are rate limited, then repeats are dropped, and then
+/// what's left is truncated before being written to stderr.
+///
+/// This is implemented for every logger, including `Box<Logger>`.
+pub trait LoggerExt: api::Logger + Sized + 'static {
+    /// Passes on at most one message from each call site per `period`, as with
+    /// `OnceLogger::throttled()`.
+    fn rate_limited(self, period: Duration) -> Box<api::Logger> {
+        return Box::new(loggers::OnceLogger::throttled(Box::new(self), period));
+    }
+
+    /// Drops messages which are the same, at the same level, as the message passed on right
+    /// before them, so that a message repeated many times in a row is only logged once.
+    fn deduplicated(self) -> Box<api::Logger> {
+        return Box::new(DeduplicatedLogger {
+            output: Box::new(self),
+            last: sync::Mutex::new(None),
+        });
+    }
+
+    /// Cuts messages longer than `max_chars` characters short.
+    fn truncated(self, max_chars: usize) -> Box<api::Logger> {
+        return Box::new(TruncatedLogger {
+            output: Box::new(self),
+            max_chars: max_chars,
+        });
+    }
+
+    /// Sends every message to `other` as well. As with `DispatchLogger`, if one of the two
+    /// fails, the message is still sent to the other, and the first error is returned.
+    fn tee(self, other: Box<api::Logger>) -> Box<api::Logger> {
+        return Box::new(TeeLogger {
+            outputs: [Box::new(self), other],
+        });
+    }
+
+    /// Only passes on messages `predicate` returns true for.
+    fn filtered(self, predicate: Box<Fn(&str) -> bool + Sync + Send>) -> Box<api::Logger> {
+        return Box::new(FilteredLogger {
+            output: Box::new(self),
+            predicate: predicate,
+        });
+    }
+}
+
+impl <T: api::Logger + 'static> LoggerExt for T {}
+
+struct DeduplicatedLogger {
+    output: Box<api::Logger>,
+    last: sync::Mutex<Option<(log::LogLevel, String)>>,
+}
+
+impl api::Logger for DeduplicatedLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        {
+            let mut last = try!(self.last.lock());
+            match *last {
+                Some((ref last_level, ref last_msg)) if last_level == level && last_msg == msg => {
+                    return Ok(());
+                },
+                _ => {},
+            }
+            *last = Some((*level, msg.to_string()));
+        }
+        return self.output.log_with_target(msg, level, location, target);
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        return self.output.flush();
+    }
+
+    fn needs_flush(&self) -> bool {
+        return self.output.needs_flush();
+    }
+
+    fn describe(&self) -> String {
+        return format!("deduplicated ({})", self.output.describe());
+    }
+}
+
+struct TruncatedLogger {
+    output: Box<api::Logger>,
+    max_chars: usize,
+}
+
+impl api::Logger for TruncatedLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        let msg = match msg.char_indices().nth(self.max_chars) {
+            Some((end, _)) => &msg[..end],
+            None => msg,
+        };
+        return self.output.log_with_target(msg, level, location, target);
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        return self.output.flush();
+    }
+
+    fn needs_flush(&self) -> bool {
+        return self.output.needs_flush();
+    }
+
+    fn describe(&self) -> String {
+        return format!("truncated ({} chars, {})", self.max_chars, self.output.describe());
+    }
+}
+
+struct TeeLogger {
+    outputs: [Box<api::Logger>; 2],
+}
+
+impl api::Logger for TeeLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        let first = self.outputs[0].log_with_target(msg, level, location, target);
+        let second = self.outputs[1].log_with_target(msg, level, location, target);
+        return first.and(second);
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        let mut result = Ok(());
+        for output in self.outputs.iter().filter(|output| output.needs_flush()) {
+            let output_result = output.flush();
+            if result.is_ok() {
+                result = output_result;
+            }
+        }
+        return result;
+    }
+
+    fn needs_flush(&self) -> bool {
+        return self.outputs.iter().any(|output| output.needs_flush());
+    }
+
+    fn describe(&self) -> String {
+        return format!("tee ({}, {})", self.outputs[0].describe(), self.outputs[1].describe());
+    }
+}
+
+struct FilteredLogger {
+    output: Box<api::Logger>,
+    predicate: Box<Fn(&str) -> bool + Sync + Send>,
+}
+
+impl api::Logger for FilteredLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        if !(self.predicate)(msg) {
+            return Ok(());
+        }
+        return self.output.log_with_target(msg, level, location, target);
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        return self.output.flush();
+    }
+
+    fn needs_flush(&self) -> bool {
+        return self.output.needs_flush();
+    }
+
+    fn describe(&self) -> String {
+        return format!("filtered ({})", self.output.describe());
+    }
+}
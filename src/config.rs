@@ -1,30 +1,235 @@
+use std::cmp;
 use std::convert::AsRef;
+use std::env;
+#[cfg(unix)]
+use std::ffi::CString;
+use std::fmt;
+use std::io::Write;
 use std::io;
 use std::fs;
+use std::panic;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
 use std::path;
+use std::thread;
+use std::sync;
+use std::time::{Duration, Instant};
 
+#[cfg(unix)]
+use libc;
 use log;
+use time;
 
 use api;
+use clock;
+use colors;
+use context;
+#[cfg(target_os = "linux")]
+use journald;
 use loggers;
 use errors::InitError;
 
 /// From https://github.com/rust-lang/log/blob/63fee41a26bf0a6400dd1c952137c97b9ef5c645/env/src/lib.rs#L187
+///
+/// A directive applies to every module path starting with its name, and when several match, the one
+/// with the longest name is used. A directive created with `LogDirective::new()` only lets
+/// through messages more severe than its level, so a `Warn` directive passes `Error` messages but
+/// drops `Warn` ones. An inclusive directive, created with `LogDirective::inclusive()`, also lets
+/// through messages at its level, as `env_logger` does, and an exact directive, created with
+/// `LogDirective::exact()`, only lets through messages at exactly its level.
+///
+/// The `inclusive` and `exact` fields were added after the others, so code building a directive
+/// with a struct literal needs to set them, or use `LogDirective::new()` instead.
+///
+/// Directives only filter messages further - messages also need to pass the level of the logger
+/// the directives are given to.
 #[derive(Clone, Debug)]
 pub struct LogDirective {
     /// Module name
     pub name:  String,
     /// Log level filter for that module
     pub level: log::LogLevelFilter,
+    /// Whether messages at `level` pass too, rather than only those more severe
+    pub inclusive: bool,
+    /// Whether only messages at exactly `level` pass. This takes precedence over `inclusive`.
+    pub exact: bool,
 }
 impl LogDirective {
     /// Easier LogDirective creation
     pub fn new(name: String, level: log::LogLevelFilter) -> Self {
         LogDirective {
             name: name,
-            level: level
+            level: level,
+            inclusive: false,
+            exact: false,
+        }
+    }
+
+    /// Creates a directive which lets through messages at the given level and anything more
+    /// severe, as `env_logger` directives do.
+    pub fn inclusive(name: String, level: log::LogLevelFilter) -> Self {
+        LogDirective {
+            name: name,
+            level: level,
+            inclusive: true,
+            exact: false,
+        }
+    }
+
+    /// Creates a directive which only lets through messages at exactly the given level, and
+    /// not those which are more severe.
+    pub fn exact(name: String, level: log::LogLevelFilter) -> Self {
+        LogDirective {
+            name: name,
+            level: level,
+            inclusive: false,
+            exact: true,
         }
     }
+
+    /// Returns whether this directive lets through a message at the given level. This doesn't
+    /// check whether the directive applies to the message's module path.
+    pub fn allows(&self, level: &log::LogLevel) -> bool {
+        return match (self.exact, self.inclusive) {
+            (true, _) => *level == self.level,
+            (false, true) => *level <= self.level,
+            (false, false) => *level < self.level,
+        };
+    }
+}
+
+/// Creates a directive for each name and level, as a shorter way of writing them out with
+/// `LogDirective::new()`:
+///
+/// ```rust
+/// # extern crate fern;
+/// # extern crate log;
+/// # fn main() {
+/// use log::LogLevelFilter::{Trace, Warn};
+/// let directives = fern::directives(&[("hyper", Warn), ("my_app::db", Trace)]);
+/// assert_eq!(directives.len(), 2);
+/// # }
+/// ```
+///
+/// The order doesn't matter, as the longest matching name always applies.
+pub fn directives(directives: &[(&str, log::LogLevelFilter)]) -> Vec<LogDirective> {
+    return directives.iter().map(|&(name, level)| LogDirective::new(name.to_string(), level))
+                     .collect();
+}
+
+/// Returns whether `directives` let through a message at `level` from `module_path`, the same
+/// way `DispatchLogger` checks them, for testing a directive configuration without a logger:
+///
+/// ```rust
+/// # extern crate fern;
+/// # extern crate log;
+/// # fn main() {
+/// use log::LogLevelFilter::{Trace, Warn};
+/// let directives = fern::directives(&[("hyper", Warn), ("hyper::client", Trace)]);
+/// assert!(!fern::matches(&directives, &log::LogLevel::Info, "hyper::server"));
+/// assert!(fern::matches(&directives, &log::LogLevel::Info, "hyper::client::pool"));
+/// # }
+/// ```
+///
+/// The directive with the longest name which `module_path` starts with applies, and if several
+/// have that name, the last one. If none match, this returns true, leaving the message to the
+/// logger's level. The directives don't have to be sorted.
+pub fn matches(directives: &[LogDirective], level: &log::LogLevel, module_path: &str) -> bool {
+    return match directives.iter().filter(|directive| module_path.starts_with(&*directive.name))
+                           .max_by_key(|directive| directive.name.len()) {
+        Some(directive) => directive.allows(level),
+        None => true,
+    };
+}
+
+/// Parses a level filter, such as from a configuration file or environment variable, ignoring
+/// case: `error`, `warn`, `info`, `debug`, `trace`, and `off` or `none` for
+/// `LogLevelFilter::Off`. Returns `None` for anything else.
+pub fn parse_level(s: &str) -> Option<log::LogLevelFilter> {
+    return match &*s.to_ascii_lowercase() {
+        "off" | "none" => Some(log::LogLevelFilter::Off),
+        "error" => Some(log::LogLevelFilter::Error),
+        "warn" => Some(log::LogLevelFilter::Warn),
+        "info" => Some(log::LogLevelFilter::Info),
+        "debug" => Some(log::LogLevelFilter::Debug),
+        "trace" => Some(log::LogLevelFilter::Trace),
+        _ => None,
+    };
+}
+
+/// Parses a comma-separated list of directives in `env_logger` syntax, such as
+/// `warn,my_app=debug,my_app::net=trace`, returning the default level, if one is given, and the
+/// directives. An entry with just a level sets the default level, and one with just a name lets
+/// through every message under that name. Levels are parsed with `parse_level()`, and as in
+/// `env_logger`, the directives are inclusive: `my_app=debug` lets through `Debug` messages too.
+///
+/// Exact directives, see `LogDirective::exact()`, are written with `==` instead, as in
+/// `my_app::net==info`. This is the syntax `DispatchLogger::directives_string()` writes.
+///
+/// Returns `None` if any of the levels can't be parsed. Use `parse_directives_lenient()` to
+/// find out which entries couldn't be parsed.
+pub fn parse_directives(s: &str) -> Option<(Option<log::LogLevelFilter>, Vec<LogDirective>)> {
+    return match parse_directives_lenient(s) {
+        (level, directives, ref errors) if errors.is_empty() => Some((level, directives)),
+        _ => None,
+    };
+}
+
+/// Like `parse_directives()`, but skips entries which can't be parsed instead of failing, and
+/// returns a description of each one along with the rest, such as for a command line tool to
+/// warn about them:
+///
+/// ```rust
+/// # extern crate fern;
+/// # fn main() {
+/// let (level, directives, errors) = fern::parse_directives_lenient("info,my_app=loud,db=trace");
+/// assert_eq!(directives.len(), 1);
+/// assert_eq!(errors, vec!["invalid level `loud` in `my_app=loud`"]);
+/// # let _ = level;
+/// # }
+/// ```
+pub fn parse_directives_lenient(s: &str)
+        -> (Option<log::LogLevelFilter>, Vec<LogDirective>, Vec<String>) {
+    let mut default_level = None;
+    let mut directives = Vec::new();
+    let mut errors = Vec::new();
+    for entry in s.split(',').map(|entry| entry.trim()).filter(|entry| !entry.is_empty()) {
+        let (name, level, exact) = match entry.find('=') {
+            Some(i) if entry[i + 1..].starts_with('=') => (&entry[..i], &entry[i + 2..], true),
+            Some(i) => (&entry[..i], &entry[i + 1..], false),
+            None => match parse_level(entry) {
+                Some(level) => {
+                    default_level = Some(level);
+                    continue;
+                },
+                None => (entry, "trace", false),
+            },
+        };
+        let level = match parse_level(level.trim()) {
+            Some(level) => level,
+            None => {
+                errors.push(format!("invalid level `{}` in `{}`", level.trim(), entry));
+                continue;
+            },
+        };
+        directives.push(match exact {
+            true => LogDirective::exact(name.trim().to_string(), level),
+            false => LogDirective::inclusive(name.trim().to_string(), level),
+        });
+    }
+    return (default_level, directives, errors);
+}
+
+/// Returns the name of a level, such as `"WARN"` - the same text as its `Display`
+/// implementation, but without going through a formatter. The built-in formatters use this.
+pub fn level_str(level: &log::LogLevel) -> &'static str {
+    return match *level {
+        log::LogLevel::Error => "ERROR",
+        log::LogLevel::Warn => "WARN",
+        log::LogLevel::Info => "INFO",
+        log::LogLevel::Debug => "DEBUG",
+        log::LogLevel::Trace => "TRACE",
+    };
 }
 
 /// This is the base logger configuration in fern.
@@ -45,8 +250,408 @@ pub struct DispatchConfig<'a> {
     pub directives: Vec<LogDirective>
 }
 
+/// A closure formatting a message, given its level and location, into the line which is sent on
+/// to outputs.
 pub type Formatter = Fn(&str, &log::LogLevel, &log::LogLocation) -> String + Sync + Send;
 
+/// A formatter which writes the formatted message into a buffer, rather than returning a new
+/// `String`. `DispatchLogger::format_into()` uses one of these with a buffer which is reused for
+/// every message logged on a thread, avoiding an allocation for each message.
+///
+/// This is implemented for closures taking the buffer, message, level and location, in which
+/// `write!()` can be used after importing `std::fmt::Write`.
+pub trait FormatterInto: Sync + Send {
+    /// Appends the formatted message to `buf`.
+    fn format_into(&self, buf: &mut String, msg: &str, level: &log::LogLevel,
+                   location: &log::LogLocation);
+}
+
+impl <F> FormatterInto for F
+        where F: Fn(&mut String, &str, &log::LogLevel, &log::LogLocation) + Sync + Send {
+    fn format_into(&self, buf: &mut String, msg: &str, level: &log::LogLevel,
+                   location: &log::LogLocation) {
+        self(buf, msg, level, location);
+    }
+}
+
+/// Ready made formatters, for when you don't need a custom format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    /// Just the level and message:
+    ///
+    /// ```text
+    /// [INFO] connection established
+    /// ```
+    Simple,
+    /// The local date and time with milliseconds, level, thread name, module path and source
+    /// location:
+    ///
+    /// ```text
+    /// [2016-03-01 12:00:00.123][INFO][main][my_app::net src/net.rs:42] connection established
+    /// ```
+    Detailed,
+    /// One JSON object per message, with the UTC time in RFC 3339 format:
+    ///
+    /// ```text
+    /// {"timestamp":"2016-03-01T12:00:00.123Z","level":"INFO","target":"my_app::net",
+    ///  "file":"src/net.rs","line":42,"thread":"main","message":"connection established"}
+    /// ```
+    ///
+    /// (without the line break). Threads without a name are given as `null`.
+    ///
+    /// Each key-value pair in the thread's logging context, set with `fern::context_scope()`, is
+    /// added as a string field before the message, as in `"request_id":"a1b2"`. Keys with the
+    /// same name as one of the fields above are left out.
+    ///
+    /// Use `json_formatter()` to give the fields other names.
+    Json,
+    /// The same as `env_logger`'s default format, without colors: the UTC time in RFC 3339
+    /// format, the level padded to five characters, and the module path:
+    ///
+    /// ```text
+    /// [2016-03-01T12:00:00Z INFO  my_app::net] connection established
+    /// ```
+    ///
+    /// `env_logger` shows the target rather than the module path, which is the same unless a
+    /// message is logged with an explicit target. See `init_env_logger()`.
+    EnvLogger,
+    /// Aligned columns for reading logs in a terminal while developing: the time since the
+    /// formatter was created, the level padded to five characters, and the file name and line,
+    /// padded to 20 characters:
+    ///
+    /// ```text
+    /// [   1.234s] INFO  net.rs:42            connection established
+    /// ```
+    ///
+    /// The level is colored as by `LevelColors::new()`, but only if both stdout and stderr are
+    /// terminals, and the `NO_COLOR` environment variable isn't set, when the formatter is
+    /// created. Use `dev_pretty_formatter()` to choose.
+    DevPretty,
+}
+
+/// Names of the fields written by `json_formatter()`, for pipelines expecting particular ones,
+/// such as `@timestamp` for Elasticsearch:
+///
+/// ```rust
+/// # extern crate fern;
+/// # fn main() {
+/// let format = fern::json_formatter(&fern::JsonKeys {
+///     timestamp: "@timestamp",
+///     message: "msg",
+///     ..fern::JsonKeys::new()
+/// });
+/// # let _ = format;
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JsonKeys<'a> {
+    /// Name of the UTC time field, `timestamp` by default
+    pub timestamp: &'a str,
+    /// Name of the level field, `level` by default
+    pub level: &'a str,
+    /// Name of the message field, `message` by default
+    pub message: &'a str,
+    /// Name of the target field, `target` by default
+    pub target: &'a str,
+}
+
+impl JsonKeys<'static> {
+    /// Returns the names used by `Preset::Json`.
+    pub fn new() -> JsonKeys<'static> {
+        return JsonKeys {
+            timestamp: "timestamp",
+            level: "level",
+            message: "message",
+            target: "target",
+        };
+    }
+}
+
+/// Returns a formatter producing the same JSON objects as `Preset::Json`, but with the
+/// timestamp, level, message and target fields named as given. Context keys with the same name
+/// as any of the fields are left out.
+pub fn json_formatter(keys: &JsonKeys) -> Box<Formatter> {
+    let timestamp = json_escape(keys.timestamp);
+    let level_key = json_escape(keys.level);
+    let message = json_escape(keys.message);
+    let target = json_escape(keys.target);
+    let reserved = [keys.timestamp.to_string(), keys.level.to_string(),
+                    keys.target.to_string(), "file".to_string(), "line".to_string(),
+                    "thread".to_string(), keys.message.to_string()];
+    return Box::new(move |msg: &str, level: &log::LogLevel, location: &log::LogLocation| {
+        let now = clock::now_utc();
+        let thread = match thread::current().name() {
+            Some(name) => format!("\"{}\"", json_escape(name)),
+            None => "null".to_string(),
+        };
+        let fields = context::with_context(|fields| {
+            let mut formatted = String::new();
+            for &(ref key, ref value) in fields {
+                if !reserved.contains(key) {
+                    formatted.push_str(&format!("\"{}\":\"{}\",",
+                                                json_escape(key), json_escape(value)));
+                }
+            }
+            formatted
+        });
+        format!("{{\"{}\":\"{}.{:03}Z\",\"{}\":\"{}\",\"{}\":\"{}\",\
+                 \"file\":\"{}\",\"line\":{},\"thread\":{},{}\"{}\":\"{}\"}}",
+                timestamp, now.strftime("%Y-%m-%dT%H:%M:%S").unwrap(), now.tm_nsec / 1_000_000,
+                level_key, level_str(level), target, json_escape(location.module_path()),
+                json_escape(location.file()), location.line(), thread, fields, message,
+                json_escape(msg))
+    });
+}
+
+/// Returns the formatter of `Preset::DevPretty`, with the level colored only if `color` is
+/// true.
+pub fn dev_pretty_formatter(color: bool) -> Box<Formatter> {
+    let started = Instant::now();
+    let colors = match color {
+        true => Some(colors::LevelColors::new()),
+        false => None,
+    };
+    return Box::new(move |msg: &str, level: &log::LogLevel, location: &log::LogLocation| {
+        let level = match colors {
+            Some(ref colors) => colors.paint(level, &format!("{:<5}", level_str(level))),
+            None => format!("{:<5}", level_str(level)),
+        };
+        format!("{} {} {:<20} {}", format_uptime(started.elapsed(), 3), level,
+                short_location(location), msg)
+    });
+}
+
+/// Returns the last `segments` segments of a module path, such as `client::conn` for
+/// `my_app::net::http::client::conn` and 2 segments. Paths with no more segments than that, and
+/// a count of 0, give the whole path. See `DispatchLogger::short_module_paths()`.
+pub fn module_path_tail(path: &str, segments: usize) -> &str {
+    if segments == 0 {
+        return path;
+    }
+    return match path.rmatch_indices("::").nth(segments - 1) {
+        Some((index, _)) => &path[index + 2..],
+        None => path,
+    };
+}
+
+/// Formats a location as its file name, without the directories, and line, as in `main.rs:42`.
+/// This is how `Preset::DevPretty` shows locations, and how fern shows them on stderr when
+/// logging fails.
+pub fn short_location(location: &log::LogLocation) -> String {
+    let file = location.file();
+    let file = match file.rfind(|c| c == '/' || c == '\\') {
+        Some(index) => &file[index + 1..],
+        None => file,
+    };
+    return format!("{}:{}", file, location.line());
+}
+
+/// Returns whether `Preset::DevPretty` colors levels: when both stdout and stderr are terminals
+/// and `NO_COLOR` isn't set.
+fn dev_color_enabled() -> bool {
+    return io::IsTerminal::is_terminal(&io::stdout()) && io::IsTerminal::is_terminal(&io::stderr())
+           && env::var_os("NO_COLOR").is_none();
+}
+
+impl Preset {
+    /// Returns a new formatter for this preset.
+    pub fn formatter(&self) -> Box<Formatter> {
+        return match *self {
+            Preset::Simple => Box::new(|msg: &str, level: &log::LogLevel,
+                                        _location: &log::LogLocation| {
+                format!("[{}] {}", level_str(level), msg)
+            }),
+            Preset::Detailed => Box::new(|msg: &str, level: &log::LogLevel,
+                                          location: &log::LogLocation| {
+                let now = clock::now();
+                format!("[{}.{:03}][{}][{}][{} {}:{}] {}",
+                        now.strftime("%Y-%m-%d %H:%M:%S").unwrap(), now.tm_nsec / 1_000_000,
+                        level_str(level), thread::current().name().unwrap_or("<unnamed>"),
+                        location.module_path(), location.file(), location.line(), msg)
+            }),
+            Preset::Json => json_formatter(&JsonKeys::new()),
+            Preset::EnvLogger => Box::new(|msg: &str, level: &log::LogLevel,
+                                           location: &log::LogLocation| {
+                format!("[{} {:<5} {}] {}",
+                        time::strftime("%Y-%m-%dT%H:%M:%SZ", &clock::now_utc()).unwrap(),
+                        level_str(level), location.module_path(), msg)
+            }),
+            Preset::DevPretty => dev_pretty_formatter(dev_color_enabled()),
+        };
+    }
+}
+
+/// Formats a time elapsed since startup as seconds, with `precision` digits after the decimal
+/// point, as in `[  12.345678s]` for a precision of 6. The seconds are padded to at least four
+/// digits so that lines stay aligned. `DispatchLogger::uptime_prefix()` prefixes messages with
+/// this.
+pub fn format_uptime(elapsed: Duration, precision: usize) -> String {
+    let mut formatted = String::new();
+    write_uptime(&mut formatted, elapsed, precision);
+    return formatted;
+}
+
+/// Writes the output of `format_uptime()` to `buf`.
+pub fn write_uptime(buf: &mut String, elapsed: Duration, precision: usize) {
+    let precision = cmp::min(precision, 9);
+    let secs = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1_000_000_000.0;
+    let width = match precision {
+        0 => 4,
+        precision => 5 + precision,
+    };
+    let _ = fmt::Write::write_fmt(buf, format_args!("[{:>width$.precision$}s]", secs,
+                                                    width = width, precision = precision));
+}
+
+/// Escapes a string for use inside a JSON string literal.
+pub fn json_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    return escaped;
+}
+
+/// Returns a formatter producing ArcSight Common Event Format (CEF) lines, such as:
+///
+/// ```text
+/// CEF:0|vendor|product|version|my_app::db|WARN|6|msg=connection lost
+/// ```
+///
+/// The signature id is the module path the message was logged from, and the name is the
+/// level. The level is mapped to a CEF severity: `Trace` is 1, `Debug` 2, `Info` 3, `Warn` 6
+/// and `Error` 9. The message is placed in the `msg` extension.
+///
+/// Header fields are escaped by prefixing `\` and `|` with a backslash, and extension values
+/// by prefixing `\` and `=` with a backslash, with newlines written as `\n` and `\r`, as
+/// required by the CEF specification.
+pub fn cef_formatter(vendor: &str, product: &str, version: &str) -> Box<Formatter> {
+    let prefix = format!("CEF:0|{}|{}|{}|", cef_escape_header(vendor), cef_escape_header(product),
+                         cef_escape_header(version));
+    return Box::new(move |msg: &str, level: &log::LogLevel, location: &log::LogLocation| {
+        let severity = match *level {
+            log::LogLevel::Trace => 1,
+            log::LogLevel::Debug => 2,
+            log::LogLevel::Info => 3,
+            log::LogLevel::Warn => 6,
+            log::LogLevel::Error => 9,
+        };
+        format!("{}{}|{}|{}|msg={}", prefix, cef_escape_header(location.module_path()),
+                level_str(level), severity, cef_escape_extension(msg))
+    });
+}
+
+/// Returns a formatter producing Graylog Extended Log Format (GELF) 1.1 messages, such as:
+///
+/// ```text
+/// {"version":"1.1","host":"web-1","short_message":"connection lost","timestamp":1456833600.123,
+///  "level":4,"_target":"my_app::db","_file":"src/db.rs","_line":42}
+/// ```
+///
+/// (without the line break). The level is mapped to a syslog severity: `Error` is 3, `Warn` 4,
+/// `Info` 6, and `Debug` and `Trace` 7. The target, file and line are included as the `_target`,
+/// `_file` and `_line` additional fields.
+///
+/// GELF messages are usually sent over UDP or TCP - with a file or stdout output, this produces
+/// one message per line, as long as the line separator is `\n`.
+pub fn gelf_formatter(host: &str) -> Box<Formatter> {
+    let host = json_escape(host);
+    return Box::new(move |msg: &str, level: &log::LogLevel, location: &log::LogLocation| {
+        let now = clock::get_time();
+        let severity = match *level {
+            log::LogLevel::Error => 3,
+            log::LogLevel::Warn => 4,
+            log::LogLevel::Info => 6,
+            log::LogLevel::Debug | log::LogLevel::Trace => 7,
+        };
+        format!("{{\"version\":\"1.1\",\"host\":\"{}\",\"short_message\":\"{}\",\
+                 \"timestamp\":{}.{:03},\"level\":{},\"_target\":\"{}\",\"_file\":\"{}\",\
+                 \"_line\":{}}}",
+                host, json_escape(msg), now.sec, now.nsec / 1_000_000, severity,
+                json_escape(location.module_path()), json_escape(location.file()),
+                location.line())
+    });
+}
+
+/// Wraps a formatter so that each message is formatted as one physical line, for log
+/// aggregators which split messages on newlines, such as one with a stack trace:
+///
+/// ```rust
+/// # extern crate fern;
+/// # extern crate log;
+/// # fn main() {
+/// let format = fern::single_line(Box::new(|msg: &str, level: &log::LogLevel,
+///                                          _location: &log::LogLocation| {
+///     format!("[{}] {}", level, msg)
+/// }), "\\n");
+/// # let _ = format;
+/// # }
+/// ```
+///
+/// Each line break in the formatted message, whether `\n`, `\r\n` or `\r`, is replaced with
+/// `joiner`, such as `"\\n"` to write it as a literal `\n`, or `" | "`. The line separator is
+/// added by outputs after formatting, so is still written once at the end.
+pub fn single_line(format: Box<Formatter>, joiner: &str) -> Box<Formatter> {
+    let joiner = joiner.to_string();
+    return Box::new(move |msg: &str, level: &log::LogLevel, location: &log::LogLocation| {
+        let formatted = format(msg, level, location);
+        if !formatted.contains(|c| c == '\n' || c == '\r') {
+            return formatted;
+        }
+        let mut joined = String::with_capacity(formatted.len() + joiner.len());
+        let mut chars = formatted.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\r' => {
+                    if chars.peek() == Some(&'\n') {
+                        chars.next();
+                    }
+                    joined.push_str(&joiner);
+                },
+                '\n' => joined.push_str(&joiner),
+                c => joined.push(c),
+            }
+        }
+        joined
+    });
+}
+
+fn cef_escape_header(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '|' => escaped.push_str("\\|"),
+            // Newlines aren't allowed in header fields at all.
+            '\r' | '\n' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    return escaped;
+}
+
+fn cef_escape_extension(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '=' => escaped.push_str("\\="),
+            '\r' => escaped.push_str("\\r"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    return escaped;
+}
+
 
 /// This enum contains various outputs that you can send messages to.
 enum OutputConfigOptions<'a> {
@@ -54,10 +659,16 @@ enum OutputConfigOptions<'a> {
     Child(DispatchConfig<'a>),
     /// File logger - all messages sent to this will be output into the specified path. Note that
     /// the file will be opened appending, so nothing in the file will be overwritten.
-    File { path: &'a path::Path, line_sep: &'a str },
+    /// Without a line separator, the one given to the `DispatchLogger` is used. With truncate,
+    /// the file is emptied instead.
+    File { path: &'a path::Path, line_sep: Option<&'a str>, truncate: bool },
     /// File logger with OpenOptions - all messages will be sent to the specified file. The file
     /// will be opened using the specified OpenOptions.
-    FileOptions { path: &'a path::Path, options: &'a fs::OpenOptions, line_sep: &'a str },
+    FileOptions {
+        path: &'a path::Path,
+        options: &'a fs::OpenOptions,
+        line_sep: Option<&'a str>,
+    },
     /// Stdout logger - all messages sent to this will be printed to stdout.
     Stdout,
     /// Stderr logger - all messages sent to this will be printed to stderr.
@@ -85,11 +696,30 @@ impl <'a> OutputConfig<'a> {
     /// Note that the file will be opened with write(true), append(true) and create(true). If you
     /// need to open with other options, use `OutputConfig::file_with_options()`.
     ///
-    /// Log files created using this function will use `\n` as the line separator. To specify a
-    /// different separator, use `file_with_line_sep`. `file(p)` behaves exactly the same as
-    /// `file_with_line_sep(p, "\n")`
+    /// Log files created using this function will use the line separator of the
+    /// `DispatchLogger` they're given to, which is `\n` unless it was created with
+    /// `DispatchLogger::with_line_sep()`. To specify a separator for this file, use
+    /// `file_with_line_sep`.
     pub fn file<P: ?Sized + AsRef<path::Path>>(path: &'a P) -> OutputConfig<'a> {
-        return OutputConfig(OutputConfigOptions::File { path: path.as_ref(), line_sep: "\n" });
+        return OutputConfig(OutputConfigOptions::File {
+            path: path.as_ref(),
+            line_sep: None,
+            truncate: false,
+        });
+    }
+
+    /// Returns a file logger which empties the file when it's opened, instead of appending to
+    /// it, so that the file only holds the messages of the current run. The file will be opened
+    /// with write(true), truncate(true) and create(true), as with
+    /// `WriterLogger::with_truncated_file()`.
+    ///
+    /// As with `file()`, the line separator of the `DispatchLogger` is used.
+    pub fn truncated_file<P: ?Sized + AsRef<path::Path>>(path: &'a P) -> OutputConfig<'a> {
+        return OutputConfig(OutputConfigOptions::File {
+            path: path.as_ref(),
+            line_sep: None,
+            truncate: true,
+        });
     }
 
     /// Returns a file logger. All messages sent to this will be outputted to the specified path.
@@ -100,21 +730,26 @@ impl <'a> OutputConfig<'a> {
     /// separator character.
     pub fn file_with_line_sep<P: ?Sized + AsRef<path::Path>>(path: &'a P, line_sep: &'a str)
             -> OutputConfig<'a> {
-        return OutputConfig(OutputConfigOptions::File { path: path.as_ref(), line_sep: line_sep });
+        return OutputConfig(OutputConfigOptions::File {
+            path: path.as_ref(),
+            line_sep: Some(line_sep),
+            truncate: false,
+        });
     }
 
     /// Returns a file logger with OpenOptions. All messages will be sent to the specified file.
     /// The file will be opened using the specified OpenOptions.
     ///
-    /// Log files created using this function will use `\n` as the line separator. To specify a
-    /// different separator, use `file_with_options_and_line_sep`. `file_with_options(p, o)`
-    /// behaves exactly the same as `file_with_options_and_line_sep(p, o, "\n")`
-    pub fn file_with_options<P: ?Sized>(path: &'a P, options: &'a fs::OpenOptions)
-            -> OutputConfig<'a> where P: AsRef<path::Path> {
+    /// Log files created using this function will use the line separator of the
+    /// `DispatchLogger` they're given to, which is `\n` unless it was created with
+    /// `DispatchLogger::with_line_sep()`. To specify a separator for this file, use
+    /// `file_with_options_and_line_sep`.
+    pub fn file_with_options<P: ?Sized + AsRef<path::Path>>(path: &'a P,
+            options: &'a fs::OpenOptions) -> OutputConfig<'a> {
         return OutputConfig(OutputConfigOptions::FileOptions {
             path: path.as_ref(),
             options: options,
-            line_sep: "\n",
+            line_sep: None,
         });
     }
 
@@ -123,21 +758,23 @@ impl <'a> OutputConfig<'a> {
     ///
     /// Log files created using this function will use the specified separator as the newline
     /// separator character.
-    pub fn file_with_options_and_line_sep<P: ?Sized>(path: &'a P, options: &'a fs::OpenOptions,
-            line_sep: &'a str) -> OutputConfig<'a> where P: AsRef<path::Path> {
+    pub fn file_with_options_and_line_sep<P: ?Sized + AsRef<path::Path>>(path: &'a P,
+            options: &'a fs::OpenOptions, line_sep: &'a str) -> OutputConfig<'a> {
         return OutputConfig(OutputConfigOptions::FileOptions {
             path: path.as_ref(),
             options: options,
-            line_sep: line_sep,
+            line_sep: Some(line_sep),
         });
     }
 
-    /// Returns an stdout logger. All messages sent to this will be printed to stdout.
+    /// Returns an stdout logger. All messages sent to this will be printed to stdout, followed by
+    /// the line separator of the `DispatchLogger` this is given to.
     pub fn stdout() -> OutputConfig<'static> {
         return OutputConfig(OutputConfigOptions::Stdout);
     }
 
-    /// Returns an stderr logger. All messages sent to this will be printed to stderr.
+    /// Returns an stderr logger. All messages sent to this will be printed to stderr, followed by
+    /// the line separator of the `DispatchLogger` this is given to.
     pub fn stderr() -> OutputConfig<'static> {
         return OutputConfig(OutputConfigOptions::Stderr);
     }
@@ -155,32 +792,222 @@ impl <'a> OutputConfig<'a> {
 
 }
 
+/// Returns an stdout output. This is a shorthand for `OutputConfig::stdout()`, meant to be used
+/// along with the `outputs![]` macro.
+pub fn stdout() -> OutputConfig<'static> {
+    return OutputConfig::stdout();
+}
+
+/// Returns an stderr output. This is a shorthand for `OutputConfig::stderr()`, meant to be used
+/// along with the `outputs![]` macro.
+pub fn stderr() -> OutputConfig<'static> {
+    return OutputConfig::stderr();
+}
+
+/// Returns the output best suited to where the program is running:
+///
+/// - a `JournaldLogger`, on Linux when stderr is connected to the systemd journal, as it is for
+///   systemd services by default. This is detected by checking that the `JOURNAL_STREAM`
+///   variable systemd sets matches stderr, see `JournaldLogger` for the fields it sends.
+/// - stderr otherwise, including on Windows, where there is no event log output yet.
+///
+/// If the journal's socket can't be used, stderr is used as well, which the journal also
+/// receives.
+pub fn auto_output() -> OutputConfig<'static> {
+    #[cfg(target_os = "linux")]
+    {
+        if journald::stderr_is_journal() {
+            if let Ok(logger) = journald::JournaldLogger::new() {
+                return OutputConfig::custom(Box::new(logger));
+            }
+        }
+    }
+    return OutputConfig::stderr();
+}
+
+/// Opens a file output. Unlike `OutputConfig::file()`, the file is opened immediately, so any
+/// error opening it is returned here rather than when the logger is built.
+///
+/// The file will be opened with write(true), append(true) and create(true), and will use `\n`
+/// as the line separator.
+pub fn file<P: ?Sized + AsRef<path::Path>>(path: &P) -> io::Result<OutputConfig<'static>> {
+    let log = try!(loggers::WriterLogger::<fs::File>::with_file(path.as_ref(), "\n"));
+    return Ok(OutputConfig::custom(Box::new(log)));
+}
+
+/// Location of the sample record `validate()` formats.
+static VALIDATE_LOCATION: log::LogLocation = log::LogLocation {
+    __module_path: "fern::validate",
+    __file: file!(),
+    __line: 0,
+};
+
+/// Checks `config` without building it, returning every problem found, so that a new logging
+/// configuration can be checked before it's deployed. This checks that:
+///
+/// - the path of each file output can be opened for writing. Existing files are opened without
+///   changing them, and for files which don't exist yet, their directory is checked instead,
+///   so that nothing is created or removed.
+/// - each directive has a module name `parse_directives()` can represent, which isn't empty and
+///   doesn't contain whitespace, `,` or `=`.
+/// - the formatter doesn't panic on a sample record.
+/// - custom outputs find no problems with `Logger::validate()`, such as network outputs whose
+///   host doesn't resolve.
+///
+/// Child configurations are checked the same way. Each problem names the output it was found
+/// in, as in `output #1 (file (logs/app.log)): can't open for writing: Not a directory`, with
+/// outputs of children given as `output #0 > output #1`.
+pub fn validate(config: &DispatchConfig) -> Result<(), Vec<String>> {
+    let mut problems = Vec::new();
+    validate_into(config, "", &mut problems);
+    return match problems.is_empty() {
+        true => Ok(()),
+        false => Err(problems),
+    };
+}
+
+fn validate_into(config: &DispatchConfig, prefix: &str, problems: &mut Vec<String>) {
+    for (index, directive) in config.directives.iter().enumerate() {
+        if directive.name.is_empty() {
+            problems.push(format!("{}directive #{} has an empty module name", prefix, index));
+        } else if directive.name.contains(|c: char| c.is_whitespace() || c == ',' || c == '=') {
+            problems.push(format!("{}directive #{} has a module name which can't be parsed: {:?}",
+                                  prefix, index, directive.name));
+        }
+    }
+
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        (config.format)("validation message", &log::LogLevel::Info, &VALIDATE_LOCATION);
+    }));
+    if let Err(payload) = result {
+        let message = match payload.downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => payload.downcast_ref::<String>().cloned().unwrap_or_default(),
+        };
+        problems.push(format!("{}formatter panicked on a sample record: {}", prefix, message));
+    }
+
+    for (index, output) in config.output.iter().enumerate() {
+        let output_prefix = format!("{}output #{}", prefix, index);
+        match output.0 {
+            OutputConfigOptions::Child(ref child) => {
+                validate_into(child, &format!("{} > ", output_prefix), problems);
+            },
+            OutputConfigOptions::File{path, ..} | OutputConfigOptions::FileOptions{path, ..} => {
+                if let Err(e) = check_writable(path) {
+                    problems.push(format!("{} (file ({})): can't open for writing: {}",
+                                          output_prefix, path.display(), e));
+                }
+            },
+            OutputConfigOptions::Custom(ref log) => {
+                for problem in log.validate() {
+                    problems.push(format!("{} ({}): {}", output_prefix, log.describe(), problem));
+                }
+            },
+            OutputConfigOptions::Stdout | OutputConfigOptions::Stderr
+                | OutputConfigOptions::Null => {},
+        }
+    }
+}
+
+/// Opens `path` for writing without changing its contents, or if it doesn't exist, checks that
+/// a file could be created in its directory.
+fn check_writable(path: &path::Path) -> io::Result<()> {
+    match fs::OpenOptions::new().write(true).open(path) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {},
+        result => return result.map(drop),
+    }
+    return check_dir_writable(match path.parent() {
+        Some(dir) if dir != path::Path::new("") => dir,
+        _ => path::Path::new("."),
+    });
+}
+
+/// Checks that the current user may create files in `dir`.
+#[cfg(unix)]
+fn check_dir_writable(dir: &path::Path) -> io::Result<()> {
+    let dir = match CString::new(dir.as_os_str().as_bytes()) {
+        Ok(dir) => dir,
+        Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidInput, e)),
+    };
+    return match unsafe { libc::access(dir.as_ptr(), libc::W_OK | libc::X_OK) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    };
+}
+
+/// Checks that `dir` exists and isn't read-only.
+#[cfg(not(unix))]
+fn check_dir_writable(dir: &path::Path) -> io::Result<()> {
+    return match try!(fs::metadata(dir)).permissions().readonly() {
+        true => Err(io::Error::new(io::ErrorKind::PermissionDenied, "directory is read-only")),
+        false => Ok(()),
+    };
+}
+
+/// Builds an output, using `default_line_sep` for outputs which weren't given a line separator.
+/// This is how `DispatchLogger::with_line_sep()` applies its separator.
+pub fn build_output(output: OutputConfig, default_line_sep: &str)
+        -> io::Result<Box<api::Logger>> {
+    return Ok(match output.0 {
+        OutputConfigOptions::Child(config) => {
+            let DispatchConfig {format, level, output, directives} = config;
+            Box::new(try!(loggers::DispatchLogger::with_line_sep(format, output, level,
+                                                                 directives, default_line_sep)))
+        },
+        OutputConfigOptions::File{path, line_sep, truncate} => Box::new(try!(
+            open_file(path, line_sep.unwrap_or(default_line_sep), truncate))),
+        OutputConfigOptions::FileOptions{path, options, line_sep} => Box::new(try!(
+            loggers::WriterLogger::<fs::File>::with_file_with_options(
+                path, options, line_sep.unwrap_or(default_line_sep)))),
+        OutputConfigOptions::Stdout => Box::new(
+            loggers::WriterLogger::<io::Stdout>::with_stdout()
+                .line_sep(console_line_sep(default_line_sep))),
+        OutputConfigOptions::Stderr => Box::new(
+            loggers::WriterLogger::<io::Stderr>::with_stderr()
+                .line_sep(console_line_sep(default_line_sep))),
+        OutputConfigOptions::Null => Box::new(loggers::NullLogger),
+        OutputConfigOptions::Custom(log) => log,
+    });
+}
+
+fn open_file(path: &path::Path, line_sep: &str, truncate: bool)
+        -> io::Result<loggers::WriterLogger<fs::File>> {
+    return match truncate {
+        true => loggers::WriterLogger::<fs::File>::with_truncated_file(path, line_sep),
+        false => loggers::WriterLogger::<fs::File>::with_file(path, line_sep),
+    };
+}
+
+/// Returns the line separator for stdout and stderr, given the dispatcher's default. On Windows,
+/// `\r\n` is replaced with `\n`, which the console and text mode readers already turn into a
+/// line break - see `WriterLogger::with_stdout()`.
+#[cfg(windows)]
+fn console_line_sep(default_line_sep: &str) -> &str {
+    return match default_line_sep {
+        "\r\n" => "\n",
+        line_sep => line_sep,
+    };
+}
+
+#[cfg(not(windows))]
+fn console_line_sep(default_line_sep: &str) -> &str {
+    return default_line_sep;
+}
+
 impl <'a> IntoLog for OutputConfig<'a> {
     fn into_fern_logger(self) -> io::Result<Box<api::Logger>> {
-        return Ok(match self.0 {
-            OutputConfigOptions::Child(config) => try!(config.into_fern_logger()),
-            OutputConfigOptions::File{path, line_sep} => Box::new(try!(
-                loggers::WriterLogger::<fs::File>::with_file(path, line_sep))),
-            OutputConfigOptions::FileOptions{path, options, line_sep} => Box::new(try!(
-                loggers::WriterLogger::<fs::File>::with_file_with_options(
-                    path, options, line_sep))),
-            OutputConfigOptions::Stdout => Box::new(
-                loggers::WriterLogger::<io::Stdout>::with_stdout()),
-            OutputConfigOptions::Stderr => Box::new(
-                loggers::WriterLogger::<io::Stderr>::with_stderr()),
-            OutputConfigOptions::Null => Box::new(loggers::NullLogger),
-            OutputConfigOptions::Custom(log) => log,
-        });
+        return build_output(self, "\n");
     }
 
     fn into_log(self) -> io::Result<Box<log::Log>> {
         return Ok(match self.0 {
             OutputConfigOptions::Child(config) => try!(config.into_log()),
-            OutputConfigOptions::File{path, line_sep} => Box::new(try!(
-                loggers::WriterLogger::<fs::File>::with_file(path, line_sep))),
+            OutputConfigOptions::File{path, line_sep, truncate} => Box::new(try!(
+                open_file(path, line_sep.unwrap_or("\n"), truncate))),
             OutputConfigOptions::FileOptions{path, options, line_sep} => Box::new(try!(
                 loggers::WriterLogger::<fs::File>::with_file_with_options(
-                    path, options, line_sep))),
+                    path, options, line_sep.unwrap_or("\n")))),
             OutputConfigOptions::Stdout => Box::new(
                 loggers::WriterLogger::<io::Stdout>::with_stdout()),
             OutputConfigOptions::Stderr => Box::new(
@@ -205,6 +1032,38 @@ impl <'a> IntoLog for DispatchConfig<'a> {
     }
 }
 
+impl IntoLog for loggers::DispatchLogger {
+    fn into_fern_logger(self) -> io::Result<Box<api::Logger>> {
+        return Ok(Box::new(self));
+    }
+
+    fn into_log(self) -> io::Result<Box<log::Log>> {
+        return Ok(Box::new(self));
+    }
+}
+
+impl IntoLog for sync::Arc<loggers::DispatchLogger> {
+    fn into_fern_logger(self) -> io::Result<Box<api::Logger>> {
+        return Ok(Box::new(self));
+    }
+
+    fn into_log(self) -> io::Result<Box<log::Log>> {
+        let logger: Box<api::Logger> = Box::new(self);
+        return Ok(Box::new(logger));
+    }
+}
+
+impl IntoLog for sync::Arc<loggers::CompositeLogger> {
+    fn into_fern_logger(self) -> io::Result<Box<api::Logger>> {
+        return Ok(Box::new(self));
+    }
+
+    fn into_log(self) -> io::Result<Box<log::Log>> {
+        let logger: Box<api::Logger> = Box::new(self);
+        return Ok(Box::new(logger));
+    }
+}
+
 impl log::Log for Box<api::Logger> {
     fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
         true
@@ -238,3 +1097,56 @@ pub fn init_global_logger<L: IntoLog>(config: L, global_log_level: log::LogLevel
     }));
     return Ok(());
 }
+
+/// Initializes the global logger with a simple default configuration: messages at `Info` and
+/// above are written to stderr, formatted as `[LEVEL module::path] message`.
+///
+/// This is meant for quick scripts and examples - use `init_global_logger()` with a full
+/// configuration for anything else. Like `init_global_logger()`, this returns an error if the
+/// global logger has already been initialized.
+pub fn init() -> Result<(), InitError> {
+    let config = DispatchConfig {
+        format: Box::new(|msg: &str, level: &log::LogLevel, location: &log::LogLocation| {
+            format!("[{} {}] {}", level_str(level), location.module_path(), msg)
+        }),
+        output: vec![OutputConfig::stderr()],
+        level: log::LogLevelFilter::Info,
+        directives: vec![],
+    };
+    return init_global_logger(config, log::LogLevelFilter::Info);
+}
+
+/// Initializes the global logger like `env_logger::init()` does, for switching from
+/// `env_logger` to fern without changing what's logged: messages are written to stderr in the
+/// format of `Preset::EnvLogger`, and filtered by the `RUST_LOG` environment variable.
+///
+/// `RUST_LOG` is parsed with `parse_directives_lenient()`, and, as with `env_logger`, invalid
+/// entries are skipped with a warning on stderr. Without a default level in `RUST_LOG`, only
+/// messages matching one of its directives are logged, and if `RUST_LOG` isn't set, only
+/// errors are logged. `env_logger`'s regex filters, after a `/`, aren't supported.
+///
+/// Like `init_global_logger()`, this returns an error if the global logger has already been
+/// initialized.
+pub fn init_env_logger() -> Result<(), InitError> {
+    let (default_level, mut directives) = match env::var("RUST_LOG") {
+        Ok(spec) => {
+            let (default_level, directives, errors) = parse_directives_lenient(&spec);
+            for error in errors {
+                let _ = writeln!(&mut io::stderr(), "warning: {} in RUST_LOG, ignoring it", error);
+            }
+            (default_level.unwrap_or(log::LogLevelFilter::Off), directives)
+        },
+        Err(_) => (log::LogLevelFilter::Error, Vec::new()),
+    };
+    // Every module path starts with the empty name, so this applies wherever no other
+    // directive does
+    let level = directives.iter().map(|directive| directive.level).fold(default_level, cmp::max);
+    directives.push(LogDirective::inclusive(String::new(), default_level));
+    let config = DispatchConfig {
+        format: Preset::EnvLogger.formatter(),
+        output: vec![OutputConfig::stderr()],
+        level: level,
+        directives: directives,
+    };
+    return init_global_logger(config, level);
+}
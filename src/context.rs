@@ -0,0 +1,122 @@
+use std::cell;
+use std::marker;
+use std::sync;
+
+thread_local! {
+    // Key-value pairs set by the current thread's `ContextGuard`s, oldest first
+    static CONTEXT: cell::RefCell<Vec<(String, String)>> = cell::RefCell::new(Vec::new());
+    // Filter applied by `with_context()` while a message is formatted for an output with one
+    static FILTER: cell::RefCell<Option<sync::Arc<ContextFilter>>> = cell::RefCell::new(None);
+}
+
+/// Which context keys an output's formatter sees, set with `DispatchLogger::output_context()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ContextFilter {
+    /// Every key. This is the default.
+    All,
+    /// Only the given keys.
+    Only(Vec<String>),
+    /// Every key except the given ones.
+    Except(Vec<String>),
+}
+
+impl ContextFilter {
+    /// Returns whether formatters see `key` with this filter.
+    pub fn allows(&self, key: &str) -> bool {
+        return match *self {
+            ContextFilter::All => true,
+            ContextFilter::Only(ref keys) => keys.iter().any(|allowed| allowed == key),
+            ContextFilter::Except(ref keys) => !keys.iter().any(|hidden| hidden == key),
+        };
+    }
+}
+
+/// Calls `f` with `filter` applied to what `with_context()` returns on this thread.
+pub fn filtered<F, R>(filter: &sync::Arc<ContextFilter>, f: F) -> R where F: FnOnce() -> R {
+    let previous = FILTER.with(|active| active.replace(Some(filter.clone())));
+    // Restores the previous filter even if `f` panics
+    let _guard = FilterGuard(previous);
+    return f();
+}
+
+struct FilterGuard(Option<sync::Arc<ContextFilter>>);
+
+impl Drop for FilterGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        let _ = FILTER.try_with(|active| *active.borrow_mut() = previous);
+    }
+}
+
+/// Sets a key-value pair in the current thread's logging context until the returned guard is
+/// dropped, such as a request id for everything logged while handling that request. Formatters
+/// can include the context in messages - `Preset::Json` adds each pair as a field.
+///
+/// Setting a key which is already set hides the earlier value until this guard is dropped:
+///
+/// ```rust
+/// # extern crate fern;
+/// # fn main() {
+/// let _request = fern::context_scope("request_id", "a1b2");
+/// {
+///     let _user = fern::context_scope("user", "alice");
+///     fern::with_context(|fields| assert_eq!(fields.len(), 2));
+/// }
+/// fern::with_context(|fields| {
+///     assert_eq!(fields, &[("request_id".to_string(), "a1b2".to_string())][..])
+/// });
+/// # }
+/// ```
+pub fn context_scope(key: &str, value: &str) -> ContextGuard {
+    let index = CONTEXT.with(|context| {
+        let mut context = context.borrow_mut();
+        context.push((key.to_string(), value.to_string()));
+        context.len() - 1
+    });
+    return ContextGuard {
+        index: index,
+        _not_send: marker::PhantomData,
+    };
+}
+
+/// Calls `f` with the current thread's logging context, as set by `context_scope()`. Each key is
+/// given once, with its most recently set value, in the order the keys were first set.
+///
+/// While a message is formatted for an output with a `ContextFilter`, only the keys it allows
+/// are given.
+pub fn with_context<F, R>(f: F) -> R where F: FnOnce(&[(String, String)]) -> R {
+    let filter = FILTER.with(|active| active.borrow().clone());
+    let fields = CONTEXT.with(|context| {
+        let mut fields: Vec<(String, String)> = Vec::new();
+        for &(ref key, ref value) in context.borrow().iter() {
+            if let Some(ref filter) = filter {
+                if !filter.allows(key) {
+                    continue;
+                }
+            }
+            match fields.iter_mut().find(|field| field.0 == *key) {
+                Some(field) => field.1 = value.clone(),
+                None => fields.push((key.clone(), value.clone())),
+            }
+        }
+        fields
+    });
+    return f(&fields);
+}
+
+/// Removes a key-value pair from the logging context when dropped. See `context_scope()`.
+///
+/// Guards are meant to be dropped in the reverse order they were created, as happens with
+/// scopes. Dropping a guard early also removes any pairs set after it.
+pub struct ContextGuard {
+    index: usize,
+    // The context is per thread, so guards can't be moved to other threads
+    _not_send: marker::PhantomData<*const ()>,
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        let index = self.index;
+        let _ = CONTEXT.try_with(|context| context.borrow_mut().truncate(index));
+    }
+}
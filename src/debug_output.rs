@@ -0,0 +1,78 @@
+use log;
+
+use api;
+use errors::LogError;
+use loggers;
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn OutputDebugStringW(output: *const u16);
+}
+
+/// The signature of `OutputDebugStringW()`, which `DebugOutputLogger` sends messages through: a
+/// nul-terminated UTF-16 string.
+pub type OutputDebugStringFn = unsafe extern "system" fn(*const u16);
+
+/// A logger sending messages to the Windows debugger with `OutputDebugStringW()`, where they
+/// show up in the output window of Visual Studio, or in DebugView when no debugger is attached.
+/// This is meant for GUI programs, which have no console for stdout or stderr.
+///
+/// Each formatted message is sent followed by a newline, as the debugger shows the strings it
+/// receives one after another. When no debugger or viewer is listening, the messages are
+/// discarded by Windows.
+///
+/// This is only available on Windows, with the `debug_output` feature enabled.
+pub struct DebugOutputLogger {
+    output: OutputDebugStringFn,
+}
+
+impl DebugOutputLogger {
+    /// Creates a logger sending messages to the debugger with `OutputDebugStringW()`.
+    pub fn new() -> DebugOutputLogger {
+        return DebugOutputLogger::with_output_fn(OutputDebugStringW);
+    }
+
+    /// Creates a logger which calls the given function instead of `OutputDebugStringW()`, such
+    /// as a stub recording messages in tests.
+    pub fn with_output_fn(output: OutputDebugStringFn) -> DebugOutputLogger {
+        return DebugOutputLogger {
+            output: output,
+        };
+    }
+}
+
+/// Encodes a message as a nul-terminated UTF-16 string, followed by a newline. Nul characters
+/// within the message would end it early, so they're replaced.
+fn to_wide_line(msg: &str) -> Vec<u16> {
+    let mut wide: Vec<u16> = msg.encode_utf16().map(|c| if c == 0 { 0xFFFD } else { c }).collect();
+    wide.extend_from_slice(&[b'\n' as u16, 0]);
+    return wide;
+}
+
+impl api::Logger for DebugOutputLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, _level: &log::LogLevel, _location: &log::LogLocation,
+            _target: &str) -> Result<(), LogError> {
+        let wide = to_wide_line(msg);
+        unsafe { (self.output)(wide.as_ptr()) };
+        return Ok(());
+    }
+
+    fn describe(&self) -> String {
+        return "debugger output".to_string();
+    }
+}
+
+impl log::Log for DebugOutputLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        loggers::log_with_fern_logger(self, record);
+    }
+}
@@ -13,6 +13,110 @@ pub enum LogError {
     Io(io::Error),
     /// Poison error - this will only occur within fern logger implementations if write!() panics.
     Poison(String),
+    /// An output of a `DispatchLogger` failed.
+    Output {
+        /// Position of the output, in the order of `DispatchLogger::output_descriptions()`
+        index: usize,
+        /// Description of the output, from `Logger::describe()`
+        description: String,
+        /// Error returned by the output
+        error: Box<LogError>,
+    },
+}
+
+/// Names of the IO error kinds kept by `LogError::encode()`. Other kinds are encoded as `Other`.
+const IO_ERROR_KINDS: [(io::ErrorKind, &'static str); 19] = [
+    (io::ErrorKind::NotFound, "NotFound"),
+    (io::ErrorKind::PermissionDenied, "PermissionDenied"),
+    (io::ErrorKind::ConnectionRefused, "ConnectionRefused"),
+    (io::ErrorKind::ConnectionReset, "ConnectionReset"),
+    (io::ErrorKind::ConnectionAborted, "ConnectionAborted"),
+    (io::ErrorKind::NotConnected, "NotConnected"),
+    (io::ErrorKind::AddrInUse, "AddrInUse"),
+    (io::ErrorKind::AddrNotAvailable, "AddrNotAvailable"),
+    (io::ErrorKind::BrokenPipe, "BrokenPipe"),
+    (io::ErrorKind::AlreadyExists, "AlreadyExists"),
+    (io::ErrorKind::WouldBlock, "WouldBlock"),
+    (io::ErrorKind::InvalidInput, "InvalidInput"),
+    (io::ErrorKind::InvalidData, "InvalidData"),
+    (io::ErrorKind::TimedOut, "TimedOut"),
+    (io::ErrorKind::WriteZero, "WriteZero"),
+    (io::ErrorKind::Interrupted, "Interrupted"),
+    (io::ErrorKind::UnexpectedEof, "UnexpectedEof"),
+    // Stable since Rust 1.83, which is why that's the crate's minimum version
+    (io::ErrorKind::StorageFull, "StorageFull"),
+    (io::ErrorKind::Other, "Other"),
+];
+
+impl LogError {
+    /// Returns a code identifying the variant of this error, which stays the same across
+    /// versions of fern: `io`, `poison` or `output`.
+    pub fn code(&self) -> &'static str {
+        return match self {
+            &LogError::Io(..) => "io",
+            &LogError::Poison(..) => "poison",
+            &LogError::Output{..} => "output",
+        };
+    }
+
+    /// Encodes this error as a string which `decode()` turns back into the same error, such as
+    /// for sending errors from a worker process back to its supervisor. The string starts with
+    /// `code()` and a colon, as in `io:PermissionDenied:access denied`.
+    ///
+    /// IO errors keep their kind and message, but not their source, so an error from the OS is
+    /// decoded into an error with the same kind and message rather than the same OS error code.
+    pub fn encode(&self) -> String {
+        return match self {
+            &LogError::Io(ref e) => {
+                let kind = IO_ERROR_KINDS.iter().find(|&&(kind, _)| kind == e.kind())
+                                         .map_or("Other", |&(_, name)| name);
+                format!("io:{}:{}", kind, e)
+            },
+            &LogError::Poison(ref e) => format!("poison:{}", e),
+            // The description is prefixed with its length, as it can hold anything
+            &LogError::Output{index, ref description, ref error} => {
+                format!("output:{}:{}:{}{}", index, description.len(), description, error.encode())
+            },
+        };
+    }
+
+    /// Decodes an error encoded with `encode()`, returning `None` if `encoded` isn't one.
+    pub fn decode(encoded: &str) -> Option<LogError> {
+        let (code, rest) = match encoded.find(':') {
+            Some(end) => (&encoded[..end], &encoded[end + 1..]),
+            None => return None,
+        };
+        return match code {
+            "io" => {
+                let mut parts = rest.splitn(2, ':');
+                let name = parts.next().unwrap_or("");
+                let kind = match IO_ERROR_KINDS.iter().find(|&&(_, kind_name)| kind_name == name) {
+                    Some(&(kind, _)) => kind,
+                    None => return None,
+                };
+                parts.next().map(|message| LogError::Io(io::Error::new(kind, message)))
+            },
+            "poison" => Some(LogError::Poison(rest.to_string())),
+            "output" => {
+                let mut parts = rest.splitn(3, ':');
+                let index = parts.next().and_then(|index| index.parse().ok());
+                let len = parts.next().and_then(|len| len.parse::<usize>().ok());
+                let (index, len, rest) = match (index, len, parts.next()) {
+                    (Some(index), Some(len), Some(rest)) => (index, len, rest),
+                    _ => return None,
+                };
+                if rest.len() < len || !rest.is_char_boundary(len) {
+                    return None;
+                }
+                LogError::decode(&rest[len..]).map(|error| LogError::Output {
+                    index: index,
+                    description: rest[..len].to_string(),
+                    error: Box::new(error),
+                })
+            },
+            _ => None,
+        };
+    }
 }
 
 impl convert::From<io::Error> for LogError {
@@ -32,6 +136,7 @@ impl error::Error for LogError {
         match self {
             &LogError::Io(..) => "IO error while logging",
             &LogError::Poison(..) => "lock within logger poisoned",
+            &LogError::Output{..} => "output of dispatch logger failed",
         }
     }
 
@@ -39,6 +144,7 @@ impl error::Error for LogError {
         match self {
             &LogError::Io(ref e) => Some(e),
             &LogError::Poison(..) => None,
+            &LogError::Output{ref error, ..} => Some(&**error),
         }
     }
 }
@@ -48,6 +154,9 @@ impl fmt::Display for LogError {
         match self {
             &LogError::Io(ref e) => write!(f, "IO Error: {}", e),
             &LogError::Poison(ref e) => write!(f, "Poison Error: {}", e),
+            &LogError::Output{index, ref description, ref error} => {
+                write!(f, "output #{} ({}) failed: {}", index, description, error)
+            },
         }
     }
 }
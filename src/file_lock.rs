@@ -0,0 +1,104 @@
+// Advisory locking of whole files, used by `WriterLogger::exclusive()`. `lock()` takes an
+// exclusive lock on the whole file, failing immediately if another open file already holds one.
+// The lock is released by `unlock()`, or when the file is closed.
+
+pub use self::imp::{lock, unlock};
+
+#[cfg(unix)]
+mod imp {
+    use std::fs;
+    use std::io;
+    use std::os::raw::c_int;
+    use std::os::unix::io::AsRawFd;
+
+    const LOCK_EX: c_int = 2;
+    const LOCK_NB: c_int = 4;
+    const LOCK_UN: c_int = 8;
+
+    extern "C" {
+        fn flock(fd: c_int, operation: c_int) -> c_int;
+    }
+
+    fn flock_file(file: &fs::File, operation: c_int) -> io::Result<()> {
+        if unsafe { flock(file.as_raw_fd(), operation) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        return Ok(());
+    }
+
+    pub fn lock(file: &fs::File) -> io::Result<()> {
+        return flock_file(file, LOCK_EX | LOCK_NB);
+    }
+
+    pub fn unlock(file: &fs::File) -> io::Result<()> {
+        return flock_file(file, LOCK_UN);
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::fs;
+    use std::io;
+    use std::mem;
+    use std::os::raw::c_void;
+    use std::os::windows::io::AsRawHandle;
+
+    const LOCKFILE_FAIL_IMMEDIATELY: u32 = 0x1;
+    const LOCKFILE_EXCLUSIVE_LOCK: u32 = 0x2;
+
+    #[repr(C)]
+    #[allow(non_snake_case)]
+    struct OVERLAPPED {
+        Internal: usize,
+        InternalHigh: usize,
+        Offset: u32,
+        OffsetHigh: u32,
+        hEvent: *mut c_void,
+    }
+
+    extern "system" {
+        fn LockFileEx(file: *mut c_void, flags: u32, reserved: u32, bytes_low: u32,
+                      bytes_high: u32, overlapped: *mut OVERLAPPED) -> i32;
+        fn UnlockFileEx(file: *mut c_void, reserved: u32, bytes_low: u32, bytes_high: u32,
+                        overlapped: *mut OVERLAPPED) -> i32;
+    }
+
+    pub fn lock(file: &fs::File) -> io::Result<()> {
+        let mut overlapped: OVERLAPPED = unsafe { mem::zeroed() };
+        let result = unsafe {
+            LockFileEx(file.as_raw_handle() as *mut c_void,
+                       LOCKFILE_EXCLUSIVE_LOCK | LOCKFILE_FAIL_IMMEDIATELY, 0, !0, !0,
+                       &mut overlapped)
+        };
+        if result == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        return Ok(());
+    }
+
+    pub fn unlock(file: &fs::File) -> io::Result<()> {
+        let mut overlapped: OVERLAPPED = unsafe { mem::zeroed() };
+        let result = unsafe {
+            UnlockFileEx(file.as_raw_handle() as *mut c_void, 0, !0, !0, &mut overlapped)
+        };
+        if result == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod imp {
+    use std::fs;
+    use std::io;
+
+    pub fn lock(_file: &fs::File) -> io::Result<()> {
+        return Err(io::Error::new(io::ErrorKind::Other,
+                                  "file locking isn't supported on this platform"));
+    }
+
+    pub fn unlock(_file: &fs::File) -> io::Result<()> {
+        return Ok(());
+    }
+}
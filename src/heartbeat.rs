@@ -0,0 +1,81 @@
+use std::io::Write;
+use std::io;
+use std::sync;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use log;
+
+use api;
+
+/// Target, and module, of the messages logged by a `Heartbeat`.
+const HEARTBEAT_TARGET: &'static str = "fern::heartbeat";
+
+static HEARTBEAT_LOCATION: log::LogLocation = log::LogLocation {
+    __module_path: HEARTBEAT_TARGET,
+    __file: file!(),
+    __line: 0,
+};
+
+/// A background thread logging `heartbeat` at a fixed interval, even while the program is idle,
+/// so that alerts on logs going quiet aren't set off when there's just nothing to log:
+///
+/// ```rust
+/// # extern crate fern;
+/// # extern crate log;
+/// # fn setup() -> std::io::Result<()> {
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let logger = Arc::new(try!(fern::DispatchLogger::new(
+///     Box::new(|msg, level, _location| format!("[{}] {}", level, msg)),
+///     vec![fern::OutputConfig::stdout()], log::LogLevelFilter::Info, vec![])));
+/// let heartbeat = fern::Heartbeat::start(logger.clone(), Duration::from_secs(60),
+///                                        log::LogLevel::Info);
+/// // ...
+/// drop(heartbeat);
+/// # Ok(())
+/// # }
+/// # fn main() { setup().unwrap(); }
+/// ```
+///
+/// Heartbeats are logged from the `fern::heartbeat` module and target, and go through the
+/// logger's level and directives like any other message. The first one is logged one interval
+/// after starting.
+///
+/// Dropping this stops the thread, and waits for it to finish.
+pub struct Heartbeat {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Heartbeat {
+    /// Starts logging heartbeats to `logger` every `interval`, at `level`.
+    pub fn start(logger: sync::Arc<api::Logger>, interval: Duration, level: log::LogLevel)
+            -> Heartbeat {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = thread::spawn(move || {
+            // Nothing is ever sent, so this only returns once the sender has been dropped
+            while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                if let Err(e) = logger.log_with_target("heartbeat", &level, &HEARTBEAT_LOCATION,
+                                                       HEARTBEAT_TARGET) {
+                    let _ = writeln!(&mut io::stderr(), "Error logging heartbeat: {}", e);
+                }
+            }
+        });
+        return Heartbeat {
+            stop: Some(stop),
+            thread: Some(thread),
+        };
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
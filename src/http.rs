@@ -0,0 +1,84 @@
+use std::io::{Read, Write};
+use std::io;
+use std::net;
+use std::sync::atomic;
+use std::time::Duration;
+
+/// How long to wait for connecting to, writing to and reading from the server.
+const TIMEOUT_SECS: u64 = 10;
+
+/// Splits an `http://host[:port][/path]` URL into the address to connect to, the host header
+/// and the path.
+fn parse_url(url: &str) -> io::Result<(String, String, String)> {
+    let rest = match url.starts_with("http://") {
+        true => &url["http://".len()..],
+        false => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                           format!("only http:// URLs are supported: {}", url))),
+    };
+    let (host, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let address = match host.contains(':') {
+        true => host.to_string(),
+        false => format!("{}:80", host),
+    };
+    return Ok((address, host.to_string(), path.to_string()));
+}
+
+/// Checks that `url` is a supported URL, and that its host resolves to at least one address.
+pub fn resolve(url: &str) -> io::Result<()> {
+    let (address, _, _) = try!(parse_url(url));
+    return match try!(net::ToSocketAddrs::to_socket_addrs(&*address)).next() {
+        Some(_) => Ok(()),
+        None => Err(io::Error::new(io::ErrorKind::Other,
+                                   format!("no addresses found for {}", address))),
+    };
+}
+
+/// Sends `body` to `url` in an HTTP/1.1 POST request, returning an error if the request fails
+/// or the response status isn't 2xx. The bytes of the request written, including its headers,
+/// are added to `bytes_written`.
+pub fn post(url: &str, content_type: &str, body: &[u8], bytes_written: &atomic::AtomicU64)
+        -> io::Result<()> {
+    let (address, host, path) = try!(parse_url(url));
+    let timeout = Some(Duration::from_secs(TIMEOUT_SECS));
+    let mut last_error = io::Error::new(io::ErrorKind::Other,
+                                        format!("no addresses found for {}", address));
+    let mut stream = None;
+    for addr in try!(net::ToSocketAddrs::to_socket_addrs(&*address)) {
+        match net::TcpStream::connect_timeout(&addr, Duration::from_secs(TIMEOUT_SECS)) {
+            Ok(connected) => {
+                stream = Some(connected);
+                break;
+            },
+            Err(e) => last_error = e,
+        }
+    }
+    let mut stream = match stream {
+        Some(stream) => stream,
+        None => return Err(last_error),
+    };
+    try!(stream.set_read_timeout(timeout));
+    try!(stream.set_write_timeout(timeout));
+
+    let head = format!("POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: {}\r\n\
+                        Content-Length: {}\r\nConnection: close\r\n\r\n",
+                       path, host, content_type, body.len());
+    try!(stream.write_all(head.as_bytes()));
+    bytes_written.fetch_add(head.len() as u64, atomic::Ordering::SeqCst);
+    try!(stream.write_all(body));
+    bytes_written.fetch_add(body.len() as u64, atomic::Ordering::SeqCst);
+    try!(stream.flush());
+
+    let mut response = Vec::new();
+    try!(stream.read_to_end(&mut response));
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or("");
+    let status = status_line.split(' ').nth(1).unwrap_or("");
+    if !status.starts_with('2') {
+        return Err(io::Error::new(io::ErrorKind::Other,
+                                  format!("{} responded with {:?}", url, status_line)));
+    }
+    return Ok(());
+}
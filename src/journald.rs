@@ -0,0 +1,121 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::net::UnixDatagram;
+use std::path;
+
+use log;
+
+use api;
+use errors::LogError;
+use loggers;
+
+/// Socket journald receives messages on with its native protocol.
+const JOURNALD_SOCKET: &'static str = "/run/systemd/journal/socket";
+
+/// A logger sending messages to the systemd journal with journald's native protocol, so that
+/// each message keeps its level and source location as journal fields:
+///
+/// - `MESSAGE`, the formatted message
+/// - `PRIORITY`, the level as a syslog severity: `Error` is 3, `Warn` 4, `Info` 6, and `Debug`
+///   and `Trace` 7
+/// - `CODE_FILE` and `CODE_LINE`, the source location
+/// - `TARGET`, the target the message was logged with
+///
+/// The journal records the time and process itself, so messages are best formatted without
+/// them. Each message is sent in one datagram, and messages too large for a datagram return an
+/// error. Only available on Linux.
+pub struct JournaldLogger {
+    socket: UnixDatagram,
+    path: path::PathBuf,
+}
+
+impl JournaldLogger {
+    /// Creates a logger sending messages to journald's socket. This succeeds even if journald
+    /// isn't running, in which case logging returns an error.
+    pub fn new() -> io::Result<JournaldLogger> {
+        return JournaldLogger::with_socket(JOURNALD_SOCKET);
+    }
+
+    /// Creates a logger sending messages to the given socket instead of journald's default one.
+    pub fn with_socket<P: AsRef<path::Path>>(path: P) -> io::Result<JournaldLogger> {
+        return Ok(JournaldLogger {
+            socket: try!(UnixDatagram::unbound()),
+            path: path.as_ref().to_path_buf(),
+        });
+    }
+}
+
+/// Appends a field in journald's native format. Values containing a newline are written with
+/// their length instead of being terminated by one.
+fn push_field(datagram: &mut Vec<u8>, name: &str, value: &[u8]) {
+    datagram.extend_from_slice(name.as_bytes());
+    match value.contains(&b'\n') {
+        true => {
+            datagram.push(b'\n');
+            datagram.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        },
+        false => datagram.push(b'='),
+    }
+    datagram.extend_from_slice(value);
+    datagram.push(b'\n');
+}
+
+/// Returns whether this process's stderr is connected to the journal, which systemd says by
+/// setting `JOURNAL_STREAM` to the device and inode numbers of the stream, as in `8:12345`.
+/// The numbers are compared with stderr's own, so that child processes which inherited the
+/// variable, but not the stream, aren't detected.
+pub fn stderr_is_journal() -> bool {
+    let stream = match env::var("JOURNAL_STREAM") {
+        Ok(stream) => stream,
+        Err(_) => return false,
+    };
+    return match fs::metadata("/proc/self/fd/2") {
+        Ok(metadata) => stream == format!("{}:{}", metadata.dev(), metadata.ino()),
+        Err(_) => false,
+    };
+}
+
+impl api::Logger for JournaldLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        let priority = match *level {
+            log::LogLevel::Error => "3",
+            log::LogLevel::Warn => "4",
+            log::LogLevel::Info => "6",
+            log::LogLevel::Debug | log::LogLevel::Trace => "7",
+        };
+        let mut datagram = Vec::with_capacity(msg.len() + 128);
+        push_field(&mut datagram, "MESSAGE", msg.as_bytes());
+        push_field(&mut datagram, "PRIORITY", priority.as_bytes());
+        push_field(&mut datagram, "CODE_FILE", location.__file.as_bytes());
+        push_field(&mut datagram, "CODE_LINE", location.__line.to_string().as_bytes());
+        push_field(&mut datagram, "TARGET", target.as_bytes());
+        try!(self.socket.send_to(&datagram, &self.path));
+        return Ok(());
+    }
+
+    fn needs_flush(&self) -> bool {
+        return false;
+    }
+
+    fn describe(&self) -> String {
+        return "journald".to_string();
+    }
+}
+
+impl log::Log for JournaldLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        loggers::log_with_fern_logger(self, record);
+    }
+}
@@ -134,6 +134,14 @@
 //! This uses the `if let Err(e) =` syntax to catch any errors that happen when initializing
 //! the logger.
 //!
+//! If you just want messages to show up somewhere, for instance in a small script, `fern::init()`
+//! sets up a global logger writing `Info` and above to stderr in one line:
+//!
+//! ```rust
+//! # extern crate fern;
+//! fern::init().unwrap();
+//! ```
+//!
 //!
 //! Eventually, this section will contain a few more examples as well. For now though, the above
 //! tutorial paired with the fern docs should be enough to get you started on configuring a logger
@@ -164,15 +172,124 @@
 //! error!("Error message");
 //! # }
 //! ```
+//!
+//! #### Compiling out levels
+//!
+//! The `max_level_off`, `max_level_error`, `max_level_warn`, `max_level_info` and
+//! `max_level_debug` features make every `DispatchLogger` drop messages less severe than the
+//! given level, without checking its configuration. Each of them also enables the
+//! `static_max_level` feature, and the tests which rely on messages at every level being logged
+//! are compiled out when it's on. `cargo test --all-features` therefore enables
+//! `max_level_off`, and only runs the tests which don't depend on levels - the
+//! `tests/max_level.rs` tests check the features themselves, with `--features max_level_info`.
 
+#[macro_use]
 extern crate log;
+extern crate time;
+#[cfg(unix)]
+extern crate libc;
 
 pub use errors::{LogError, InitError};
 pub use api::Logger;
-pub use config::{DispatchConfig, OutputConfig, IntoLog, init_global_logger, LogDirective};
-pub use loggers::NullLogger;
+pub use combinators::LoggerExt;
+pub use blob::BlobStore;
+pub use config::{DispatchConfig, OutputConfig, IntoLog, init_global_logger, init, LogDirective};
+pub use config::init_env_logger;
+pub use config::{stdout, stderr, file, parse_level, parse_directives, level_str, directives};
+pub use config::{parse_directives_lenient, matches, validate};
+pub use config::{Formatter, FormatterInto, Preset, cef_formatter, gelf_formatter};
+pub use config::single_line;
+pub use config::{JsonKeys, json_formatter, dev_pretty_formatter};
+pub use config::{format_uptime, short_location, module_path_tail, auto_output};
+pub use colors::{ColorMode, LevelColors, Rgb};
+pub use capture::capture;
+pub use context::{ContextFilter, ContextGuard, context_scope, with_context};
+pub use loggers::{DispatchLogger, EmptyOutput, ErrorPolicy, NullLogger, WriterLogger};
+pub use loggers::{IoErrorAction, IoErrorPolicy, ThreadFilter};
+pub use loggers::{ThreadLocalWriterLogger, SyncAll, Durability};
+pub use loggers::{ChannelLogger, QueueFullMode, OnceLogger, TestTeeLogger, MemoryLogger};
+pub use loggers::{CompositeLogger, HandlerId};
+pub use loggers::{SamplingLogger, SummarizingLogger, RingBufferLogger, BroadcastLogger};
+pub use loggers::{MemoryRingLogger, StormLogger};
+pub use heartbeat::Heartbeat;
+pub use rotation::{RotationCoordinator, RotatingFileLogger, Schedule, WriterFactory};
+pub use pipe::{PipeLogger, PipeReader, PipeRecord, LevelPrefixWriter, parse_level_prefix};
+pub use timing::{TimedLogger, TimingStats};
+pub use udp::UdpLogger;
+#[cfg(target_os = "linux")]
+pub use journald::JournaldLogger;
+#[cfg(unix)]
+pub use signal::cycle_level_on_sigusr1;
+#[cfg(all(target_os = "macos", feature = "os_log"))]
+pub use os_log::OsLogLogger;
+#[cfg(all(target_os = "android", feature = "android"))]
+pub use android::{AndroidLogger, AndroidLogWrite};
+#[cfg(all(windows, feature = "debug_output"))]
+pub use debug_output::{DebugOutputLogger, OutputDebugStringFn};
+#[cfg(feature = "otlp")]
+pub use otlp::{OtlpLogger, otlp_severity};
+#[cfg(feature = "webhook")]
+pub use webhook::WebhookLogger;
+#[cfg(feature = "redis")]
+pub use redis::RedisLogger;
+
+/// Builds a `Vec<OutputConfig>` from a list of outputs.
+///
+/// This is mostly useful along with the `fern::stdout()`, `fern::stderr()` and `fern::file()`
+/// helpers:
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate fern;
+/// # fn setup() -> std::io::Result<()> {
+/// use fern::{stdout, file};
+///
+/// let output = outputs![stdout(), try!(file("output.log"))];
+/// # Ok(())
+/// # }
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! outputs {
+    ($($output:expr),*) => (
+        <::std::vec::Vec<$crate::OutputConfig>>::from(vec![$($output),*])
+    );
+    ($($output:expr,)*) => (outputs![$($output),*]);
+}
+
+pub mod clock;
+pub mod scope;
 
 mod api;
+mod blob;
+mod capture;
+mod colors;
+mod combinators;
 mod config;
+mod context;
 mod loggers;
 mod errors;
+mod file_lock;
+mod heartbeat;
+mod pipe;
+mod rotation;
+mod timing;
+mod udp;
+#[cfg(target_os = "linux")]
+mod journald;
+#[cfg(unix)]
+mod signal;
+#[cfg(all(target_os = "macos", feature = "os_log"))]
+mod os_log;
+#[cfg(all(target_os = "android", feature = "android"))]
+mod android;
+#[cfg(all(windows, feature = "debug_output"))]
+mod debug_output;
+#[cfg(feature = "otlp")]
+mod otlp;
+#[cfg(feature = "webhook")]
+mod webhook;
+#[cfg(feature = "redis")]
+mod redis;
+#[cfg(any(feature = "otlp", feature = "webhook"))]
+mod http;
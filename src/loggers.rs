@@ -1,33 +1,305 @@
 use std::io::Write;
 use std::io;
 use std::sync;
+use std::sync::atomic;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 use std::fs;
 use std::path;
+use std::cell;
+use std::cmp;
+use std::panic;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::hash_map;
 
 use log;
+use time;
 
-use config::IntoLog;
 use errors::LogError;
 use api;
+use capture;
+use clock;
 use config;
+use context;
+use file_lock;
 
+/// What a `DispatchLogger` does when it has no outputs, and so messages sent to it would
+/// silently disappear.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmptyOutput {
+    /// Discard messages without warning. Use this when having no outputs is intentional.
+    Discard,
+    /// Write a warning to stderr, once, the first time a message is logged. This is the default.
+    Warn,
+    /// Send messages to stderr instead.
+    Stderr,
+}
+
+/// Which threads a `DispatchLogger` passes messages from, for looking at what one thread does
+/// without the noise from the others. See `DispatchLogger::only_threads()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ThreadFilter {
+    /// Threads whose name matches one of the patterns, in which `*` matches any run of
+    /// characters, as in `worker-*`. Threads without a name never match.
+    Named(Vec<String>),
+    /// Threads with one of the given ids.
+    Ids(Vec<thread::ThreadId>),
+}
+
+impl ThreadFilter {
+    /// Returns whether messages from the current thread pass this filter.
+    pub fn allows_current(&self) -> bool {
+        let current = thread::current();
+        return match *self {
+            ThreadFilter::Named(ref patterns) => match current.name() {
+                Some(name) => patterns.iter().any(|pattern| wildcard_matches(pattern, name)),
+                None => false,
+            },
+            ThreadFilter::Ids(ref ids) => ids.contains(&current.id()),
+        };
+    }
+}
+
+/// Returns whether `text` matches `pattern`, in which `*` matches any run of characters.
+fn wildcard_matches(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let (first, rest_parts) = match parts.split_first() {
+        Some(split) => split,
+        None => return text.is_empty(),
+    };
+    if !text.starts_with(first) {
+        return false;
+    }
+    let mut rest = &text[first.len()..];
+    let (last, middle) = match rest_parts.split_last() {
+        Some(split) => split,
+        // No wildcards, so the whole text has to be matched
+        None => return rest.is_empty(),
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(start) => rest = &rest[start + part.len()..],
+            None => return false,
+        }
+    }
+    return rest.ends_with(last);
+}
+
+fn level_filter_from_usize(level: usize) -> log::LogLevelFilter {
+    return match level {
+        0 => log::LogLevelFilter::Off,
+        1 => log::LogLevelFilter::Error,
+        2 => log::LogLevelFilter::Warn,
+        3 => log::LogLevelFilter::Info,
+        4 => log::LogLevelFilter::Debug,
+        _ => log::LogLevelFilter::Trace,
+    };
+}
+
+/// The most verbose level which `DispatchLogger` passes on, regardless of its configuration, as
+/// set by the `max_level_*` features. When several are enabled, the most restrictive one wins.
+#[cfg(feature = "max_level_off")]
+const STATIC_MAX_LEVEL: log::LogLevelFilter = log::LogLevelFilter::Off;
+#[cfg(all(feature = "max_level_error", not(feature = "max_level_off")))]
+const STATIC_MAX_LEVEL: log::LogLevelFilter = log::LogLevelFilter::Error;
+#[cfg(all(feature = "max_level_warn",
+          not(any(feature = "max_level_off", feature = "max_level_error"))))]
+const STATIC_MAX_LEVEL: log::LogLevelFilter = log::LogLevelFilter::Warn;
+#[cfg(all(feature = "max_level_info",
+          not(any(feature = "max_level_off", feature = "max_level_error",
+                  feature = "max_level_warn"))))]
+const STATIC_MAX_LEVEL: log::LogLevelFilter = log::LogLevelFilter::Info;
+#[cfg(all(feature = "max_level_debug",
+          not(any(feature = "max_level_off", feature = "max_level_error",
+                  feature = "max_level_warn", feature = "max_level_info"))))]
+const STATIC_MAX_LEVEL: log::LogLevelFilter = log::LogLevelFilter::Debug;
+#[cfg(not(any(feature = "max_level_off", feature = "max_level_error", feature = "max_level_warn",
+              feature = "max_level_info", feature = "max_level_debug")))]
+const STATIC_MAX_LEVEL: log::LogLevelFilter = log::LogLevelFilter::Trace;
+
+/// Target of messages which are always sent to a `DispatchLogger`'s audit output.
+const AUDIT_TARGET: &'static str = "audit";
+
+/// Target and module path of the banner logged by `DispatchLogger::log_banner()`.
+const BANNER_TARGET: &'static str = "fern::banner";
+
+/// Target and module path of the events logged by `DispatchLogger::lifecycle_events()`.
+const LIFECYCLE_TARGET: &'static str = "lifecycle";
+
+/// Number of the next message prefixed by `DispatchLogger::record_numbers()`, counted across
+/// every logger in the process.
+static NEXT_RECORD_NUMBER: atomic::AtomicU64 = atomic::AtomicU64::new(1);
+
+/// Returns the current UTC time for lifecycle events, as in `2016-03-01T12:00:00Z`.
+fn lifecycle_time() -> String {
+    return time::strftime("%Y-%m-%dT%H:%M:%SZ", &clock::now_utc()).unwrap_or_default();
+}
+
+/// Target and module path of the summaries logged by `SummarizingLogger`.
+const SUMMARY_TARGET: &'static str = "fern::summary";
+
+/// Target and module path of the notices logged by `StormLogger`.
+const STORM_TARGET: &'static str = "fern::storm";
+
+/// Target and module path of the messages logged by `DispatchLogger::cycle_level()`.
+const LEVEL_TARGET: &'static str = "fern::level";
+
+thread_local! {
+    /// Buffer which messages are formatted into by `DispatchLogger`s with a `FormatterInto`.
+    static FORMAT_BUFFER: cell::RefCell<String> = cell::RefCell::new(String::new());
+}
+
+/// A temporary directive installed by `DispatchLogger::boost_target()`.
+struct Boost {
+    directive: config::LogDirective,
+    expires: Instant,
+}
+
+/// What a `DispatchLogger` does when one of its outputs fails to log or flush a message. See
+/// `DispatchLogger::output_error_policy()`.
+pub enum ErrorPolicy {
+    /// Return the error from the dispatch logger, once the message has been sent to the other
+    /// outputs. This is the default.
+    Propagate,
+    /// Ignore the error. Use this for best-effort outputs whose failures shouldn't be noticed.
+    Ignore,
+    /// Call the given closure with the error, and then ignore it.
+    Callback(Box<Fn(&LogError) + Sync + Send>),
+}
+
+/// An output of a `DispatchLogger`, along with its tags and error policy.
+struct Output {
+    logger: Box<api::Logger>,
+    tags: HashMap<String, String>,
+    error_policy: ErrorPolicy,
+    // Only messages this returns true for are sent to the output
+    predicate: Option<Box<Fn(&str) -> bool + Sync + Send>>,
+    // Only messages at this level or more severe are sent to the output
+    level: Option<log::LogLevelFilter>,
+    // Only messages less severe than this are sent to the output
+    less_severe_than: Option<log::LogLevel>,
+    // Used instead of the logger's formatter, only for messages the output accepts
+    format: Option<Box<config::Formatter>>,
+    // Applied to the context while formatting messages for this output
+    context_filter: Option<sync::Arc<context::ContextFilter>>,
+}
+
+impl Output {
+    fn new(logger: Box<api::Logger>) -> Output {
+        return Output {
+            logger: logger,
+            tags: HashMap::new(),
+            error_policy: ErrorPolicy::Propagate,
+            predicate: None,
+            level: None,
+            less_severe_than: None,
+            format: None,
+            context_filter: None,
+        };
+    }
+
+    fn accepts_level(&self, level: &log::LogLevel) -> bool {
+        let below = match self.less_severe_than {
+            Some(limit) => *level > limit,
+            None => true,
+        };
+        return below && match self.level {
+            Some(output_level) => *level <= output_level,
+            None => true,
+        };
+    }
+
+    /// Applies this output's error policy to the result of logging to it. `index` is the
+    /// position of this output, which is included in propagated errors.
+    fn handle_result(&self, index: usize, result: Result<(), LogError>) -> Result<(), LogError> {
+        return match (result, &self.error_policy) {
+            (Err(e), &ErrorPolicy::Propagate) => Err(LogError::Output {
+                index: index,
+                description: self.logger.describe(),
+                error: Box::new(e),
+            }),
+            (Err(e), &ErrorPolicy::Callback(ref callback)) => {
+                callback(&e);
+                Ok(())
+            },
+            _ => Ok(()),
+        };
+    }
+}
+
+/// The logger built from a `DispatchConfig`. This filters messages by level and directives,
+/// formats them, and then sends them on to each of its outputs.
+///
+/// The `max_level_off`, `max_level_error`, `max_level_warn`, `max_level_info` and
+/// `max_level_debug` features compile out handling of less severe messages entirely: with
+/// `max_level_info`, for instance, `Debug` and `Trace` messages are always dropped, including
+/// audit messages. This is in addition to the `log` crate's own `max_level_*` features.
+///
+/// If an output fails, the message is still sent to the rest of the outputs, and the first error
+/// is returned afterwards as a `LogError::Output` naming the output, unless the output was given
+/// a different `ErrorPolicy`. Each output
+/// receives the complete formatted message regardless of what happens with the others.
+///
+/// You usually don't need to use this directly - `DispatchConfig` builds into it. Creating one
+/// yourself is useful when you want to keep it around to inspect it.
 pub struct DispatchLogger {
-    pub output: Vec<Box<api::Logger>>,
-    pub level: log::LogLevelFilter,
-    pub format: Box<config::Formatter>,
-    pub directives: Vec<config::LogDirective>
+    output: Vec<Output>,
+    level: atomic::AtomicUsize,
+    quiet: atomic::AtomicBool,
+    verbose: atomic::AtomicBool,
+    format: Box<config::Formatter>,
+    format_into: Option<Box<config::FormatterInto>>,
+    directives: Vec<config::LogDirective>,
+    boosts: sync::Mutex<Vec<Boost>>,
+    boost_count: atomic::AtomicUsize,
+    instance_tag: Option<String>,
+    started: Instant,
+    uptime_precision: Option<usize>,
+    record_number_width: Option<usize>,
+    hooks: Vec<Box<Fn(&str) -> String + Sync + Send>>,
+    record_callbacks: Vec<Box<Fn(&log::LogLevel) + Sync + Send>>,
+    trim_end: bool,
+    audit: Option<Box<api::Logger>>,
+    empty_output: EmptyOutput,
+    warned_empty_output: atomic::AtomicBool,
+    stderr_backup: bool,
+    preformatted: bool,
+    max_buffer_capacity: Option<usize>,
+    lifecycle_events: bool,
+    shut_down: atomic::AtomicBool,
+    diagnostic_tap: Option<sync::Mutex<fs::File>>,
+    short_module_paths: Option<(log::LogLevel, usize)>,
+    catch_format_panics: bool,
+    warned_format_panic: atomic::AtomicBool,
+    thread_filter: Option<ThreadFilter>,
 }
 
 impl DispatchLogger {
+    /// Creates a new dispatch logger, building each of the given output configurations. This
+    /// will return an error if any of the outputs fail to build, for instance if a log file
+    /// can't be opened.
     pub fn new(format: Box<config::Formatter>, config_output: Vec<config::OutputConfig>,
-            level: log::LogLevelFilter, mut directives: Vec<config::LogDirective>) -> io::Result<DispatchLogger> {
+            level: log::LogLevelFilter, directives: Vec<config::LogDirective>) -> io::Result<DispatchLogger> {
+        return DispatchLogger::with_line_sep(format, config_output, level, directives, "\n");
+    }
 
+    /// Like `new()`, but with `line_sep` as the line separator for outputs which don't specify
+    /// their own: stdout, stderr, files from `OutputConfig::file()` and
+    /// `OutputConfig::file_with_options()`, and the outputs of child configurations. Outputs
+    /// created with an explicit separator, and custom outputs, keep theirs.
+    ///
+    /// On Windows, stdout and stderr keep using `\n` when `line_sep` is `\r\n`, as explained
+    /// in `WriterLogger::with_stdout()`.
+    pub fn with_line_sep(format: Box<config::Formatter>, config_output: Vec<config::OutputConfig>,
+            level: log::LogLevelFilter, mut directives: Vec<config::LogDirective>, line_sep: &str)
+            -> io::Result<DispatchLogger> {
         let output = try!(config_output.into_iter().fold(Ok(Vec::new()),
                      |processed: io::Result<Vec<Box<api::Logger>>>, next: config::OutputConfig| {
             // If an error has already been found, don't try to process any future outputs, just
             // continue passing along the error.
             let mut processed_so_far = try!(processed);
-            return match next.into_fern_logger() {
+            return match config::build_output(next, line_sep) {
                 Err(e) => Err(e), // If this one errors, return the error instead of the Vec so far
                 Ok(processed_value) => {
                     // If it's ok, add the processed logger to the vec, and pass the vec along
@@ -45,114 +317,3275 @@ impl DispatchLogger {
         });
 
         return Ok(DispatchLogger {
-            output: output,
-            level: level,
+            output: output.into_iter().map(Output::new).collect(),
+            level: atomic::AtomicUsize::new(level as usize),
+            quiet: atomic::AtomicBool::new(false),
+            verbose: atomic::AtomicBool::new(false),
             format: format,
-            directives: directives
+            format_into: None,
+            directives: directives,
+            boosts: sync::Mutex::new(Vec::new()),
+            boost_count: atomic::AtomicUsize::new(0),
+            instance_tag: None,
+            started: Instant::now(),
+            uptime_precision: None,
+            record_number_width: None,
+            hooks: Vec::new(),
+            record_callbacks: Vec::new(),
+            trim_end: false,
+            audit: None,
+            empty_output: EmptyOutput::Warn,
+            warned_empty_output: atomic::AtomicBool::new(false),
+            stderr_backup: true,
+            preformatted: false,
+            max_buffer_capacity: None,
+            lifecycle_events: false,
+            shut_down: atomic::AtomicBool::new(false),
+            diagnostic_tap: None,
+            short_module_paths: None,
+            catch_format_panics: false,
+            warned_format_panic: atomic::AtomicBool::new(false),
+            thread_filter: None,
         });
     }
 
-    // From https://github.com/rust-lang/log/blob/63fee41a26bf0a6400dd1c952137c97b9ef5c645/env/src/lib.rs#L149
-    fn directive_check(&self, level: &log::LogLevel, target: &str) -> bool {
-        // Search for the longest match, the vector is assumed to be pre-sorted.
-        for directive in self.directives.iter().rev() {
-            match &directive.name {
-                name if target.starts_with(&**name) => return level >= &directive.level,
-                _ => {}
+    /// Sets a tag, such as the application version or a git hash, which is prefixed to every
+    /// message after formatting, as `[tag] message`. This is useful for correlating logs with a
+    /// release.
+    pub fn instance_tag(mut self, tag: &str) -> DispatchLogger {
+        self.instance_tag = Some(tag.to_string());
+        return self;
+    }
+
+    /// Prefixes every message, after formatting, with the time since this logger was created, as
+    /// in `[  12.345678s] message` for a precision of 6 - see `fern::format_uptime()`. Unlike the
+    /// wall clock time, this always increases, so it's useful for timing startup even when the
+    /// clock isn't synchronized yet. The uptime goes before the instance tag, if there is one.
+    pub fn uptime_prefix(mut self, precision: usize) -> DispatchLogger {
+        self.uptime_precision = Some(precision);
+        return self;
+    }
+
+    /// Shortens the module paths formatters see for messages at `from` and more verbose levels
+    /// to their last `segments` segments, as with `fern::module_path_tail()`, so that deeply
+    /// nested modules don't crowd out trace messages. For instance, with
+    /// `short_module_paths(LogLevel::Trace, 2)`, trace messages from `my_app::net::http::client`
+    /// show `http::client`, while messages at other levels keep the whole path.
+    ///
+    /// Outputs receive the shortened location too. Directives are still matched against the
+    /// whole module path, and targets are left alone.
+    pub fn short_module_paths(mut self, from: log::LogLevel, segments: usize) -> DispatchLogger {
+        self.short_module_paths = Some((from, segments));
+        return self;
+    }
+
+    /// Sets whether a formatter which panics, such as by indexing past the end of a message it
+    /// parses, is caught instead of unwinding through the code which logged the message. With
+    /// this on, the message is logged in a fallback format with just the level and the message,
+    /// as in `[WARN] message`, and a warning is written to stderr the first time it happens.
+    /// This applies to the format given to `new()` as well as to `format_into()`.
+    ///
+    /// Catching a panic doesn't stop the process's panic hook from running first, so unless the
+    /// program has replaced it with `std::panic::set_hook()`, the default hook still prints a
+    /// `thread panicked` message to stderr for every caught panic. fern leaves the hook alone, as
+    /// it's shared by every thread.
+    ///
+    /// This defaults to false, which saves the cost of `catch_unwind()` on every message.
+    pub fn catch_format_panics(mut self, catch: bool) -> DispatchLogger {
+        self.catch_format_panics = catch;
+        return self;
+    }
+
+    /// Prefixes every message, after formatting, with a record number, as in `#000001 message`
+    /// for a width of 6, to line up the logs of two runs when comparing them. Numbers start at 1
+    /// in each process, and are counted across every logger using them, so each message has
+    /// its own number even when several loggers number their messages. Numbers wider than
+    /// `width` are written in full. The record number goes before the uptime and instance tag.
+    pub fn record_numbers(mut self, width: usize) -> DispatchLogger {
+        self.record_number_width = Some(width);
+        return self;
+    }
+
+    /// Returns the time elapsed since this logger was created.
+    pub fn uptime(&self) -> Duration {
+        return self.started.elapsed();
+    }
+
+    /// Sets a formatter which writes messages into a buffer, to use instead of the format given
+    /// to `new()`. The buffer is reused for every message logged on the same thread, so unless
+    /// there are message hooks, formatting a message doesn't allocate once the buffer has grown
+    /// large enough.
+    pub fn format_into(mut self, formatter: Box<config::FormatterInto>) -> DispatchLogger {
+        self.format_into = Some(formatter);
+        return self;
+    }
+
+    /// Sets the most memory, in bytes, this thread's reused buffer is left holding after a
+    /// message is formatted with `format_into()`. Once a giant message has been logged, the
+    /// buffer is shrunk back to `capacity`, so that it doesn't stay that large for the rest of
+    /// the thread's life. Messages no larger than this don't allocate. By default, the buffer
+    /// keeps the size of the largest message logged on the thread.
+    pub fn max_buffer_capacity(mut self, capacity: usize) -> DispatchLogger {
+        self.max_buffer_capacity = Some(capacity);
+        return self;
+    }
+
+    /// Returns the capacity of the buffer which `format_into()` formats messages into on the
+    /// current thread. The buffer is shared by every dispatch logger on the thread.
+    pub fn format_buffer_capacity(&self) -> usize {
+        return FORMAT_BUFFER.with(|buffer| {
+            match buffer.try_borrow() {
+                Ok(buf) => buf.capacity(),
+                Err(_) => 0,
             }
+        });
+    }
+
+    /// Adds a hook which is run on every message after it has been formatted and tagged, and
+    /// before it's sent to any output. The message is replaced by whatever the hook returns, so
+    /// this is the place to enrich messages, or to redact secrets such as API keys from them
+    /// before they reach any output. Hooks are run in the order they were added.
+    pub fn message_hook(mut self, hook: Box<Fn(&str) -> String + Sync + Send>) -> DispatchLogger {
+        self.hooks.push(hook);
+        return self;
+    }
+
+    /// Adds a callback which is called with the level of every message passing this logger's
+    /// level and directives, before it's formatted, such as for counting messages by level in
+    /// your own metrics. Callbacks are called in the order they were added.
+    pub fn on_record(mut self, callback: Box<Fn(&log::LogLevel) + Sync + Send>)
+            -> DispatchLogger {
+        self.record_callbacks.push(callback);
+        return self;
+    }
+
+    /// Sets whether trailing whitespace, such as spaces and tabs left over from formatting an
+    /// empty value, is removed from every message once it's been formatted and passed through the
+    /// message hooks. Whitespace inside messages is kept. This defaults to false, so messages are
+    /// sent exactly as they were formatted.
+    pub fn trim_trailing_whitespace(mut self, trim: bool) -> DispatchLogger {
+        self.trim_end = trim;
+        return self;
+    }
+
+    /// Sets an audit output, which receives every message logged with the `audit` target, such as
+    /// `info!(target: "audit", ...)`, regardless of this logger's level and directives. Audit
+    /// messages are formatted like any other message, and are also sent to the regular outputs
+    /// if they pass the level and directives.
+    ///
+    /// Note that the global log level given to `init_global_logger()` still applies, as the
+    /// `log` crate filters messages before they reach fern.
+    pub fn audit(mut self, output: Box<api::Logger>) -> DispatchLogger {
+        self.audit = Some(output);
+        return self;
+    }
+
+    /// Only passes messages logged on the threads `filter` allows, dropping messages from every
+    /// other thread as if they didn't pass the level. This is meant for debugging one thread,
+    /// such as a single worker, in isolation:
+    ///
+    /// ```rust
+    /// # extern crate fern;
+    /// # extern crate log;
+    /// # fn main() {
+    /// let logger = fern::DispatchLogger::new(
+    ///     Box::new(|msg, _level, _location| msg.to_string()),
+    ///     vec![fern::OutputConfig::stderr()], log::LogLevelFilter::Trace, vec![]).unwrap()
+    ///     .only_threads(fern::ThreadFilter::Named(vec!["worker-3".to_string()]));
+    /// # let _ = logger;
+    /// # }
+    /// ```
+    ///
+    /// Audit messages are still sent to the audit output, from every thread.
+    pub fn only_threads(mut self, filter: ThreadFilter) -> DispatchLogger {
+        self.thread_filter = Some(filter);
+        return self;
+    }
+
+    /// Sets what this logger does if it has no outputs. See `EmptyOutput`. This defaults to
+    /// `EmptyOutput::Warn`.
+    pub fn on_empty_output(mut self, behavior: EmptyOutput) -> DispatchLogger {
+        self.empty_output = behavior;
+        if behavior == EmptyOutput::Stderr && self.output.is_empty() {
+            self.output.push(Output::new(Box::new(WriterLogger::<io::Stderr>::with_stderr())));
         }
-        false
+        return self;
     }
-}
 
-impl api::Logger for DispatchLogger {
-    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+    /// Sets whether messages which fail to log through the `log` crate's macros are written to
+    /// stderr along with the error, which is the default. With this set to false, they're
+    /// dropped silently, which keeps a broken output from flooding stderr, such as in CI logs.
+    /// Errors are still returned when logging through `fern::Logger` directly.
+    pub fn stderr_backup(mut self, backup: bool) -> DispatchLogger {
+        self.stderr_backup = backup;
+        return self;
+    }
+
+    /// Turns on a diagnostic mode for finding out why messages don't show up where they should:
+    /// every message logged is appended to the file at `path`, before formatting, along with
+    /// what happened to it at each output. For instance:
+    ///
+    /// ```text
+    /// 2016-03-01T12:00:00Z INFO my_app::db (db.rs:42) "connected" -> [#0 stdout: ok]
+    /// 2016-03-01T12:00:01Z DEBUG my_app::db (db.rs:50) "query" -> filtered out
+    /// ```
+    ///
+    /// Each output is listed as reached and `ok`, as having returned an `error`, or as
+    /// `skipped` by its own level or predicate. Messages this logger's level, directives or
+    /// boosts don't let through are listed as `filtered out`, and those taken by
+    /// `fern::capture()` as `captured`. Errors writing to the file itself are ignored.
+    ///
+    /// This makes logging much slower, so it's meant for debugging the logging setup rather
+    /// than for production.
+    pub fn diagnostic_tap(mut self, path: &path::Path) -> io::Result<DispatchLogger> {
+        self.diagnostic_tap = Some(sync::Mutex::new(try!(append_options(None).open(path))));
+        return Ok(self);
+    }
+
+    /// Sets whether messages are taken to be formatted already, and are sent to outputs without
+    /// being formatted again, for nesting this logger as an output of another dispatcher:
+    ///
+    /// ```rust
+    /// # extern crate fern;
+    /// # extern crate log;
+    /// # fn setup() -> std::io::Result<()> {
+    /// let db_logger = try!(fern::DispatchLogger::new(
+    ///     Box::new(|msg, _level, _location| msg.to_string()),
+    ///     vec![fern::OutputConfig::stderr()], log::LogLevelFilter::Debug,
+    ///     fern::directives(&[("my_app::db", log::LogLevelFilter::Debug)])))
+    ///     .preformatted(true);
+    /// let logger = try!(fern::DispatchLogger::new(
+    ///     Box::new(|msg, level, _location| format!("[{}] {}", level, msg)),
+    ///     vec![fern::OutputConfig::stdout(), fern::OutputConfig::custom(Box::new(db_logger))],
+    ///     log::LogLevelFilter::Debug, vec![]));
+    /// # let _ = logger;
+    /// # Ok(())
+    /// # }
+    /// # fn main() { setup().unwrap(); }
+    /// ```
+    ///
+    /// With this set, the format, the uptime prefix and the instance tag are skipped. The level,
+    /// directives, message hooks and outputs with their own formatter still apply. This defaults
+    /// to false.
+    pub fn preformatted(mut self, preformatted: bool) -> DispatchLogger {
+        self.preformatted = preformatted;
+        return self;
+    }
+
+    /// Adds an output, annotated with the given key-value tags, such as `&[("kind", "audit")]`.
+    /// Tags don't affect logging - they identify outputs for `find_outputs()` and
+    /// `remove_outputs()`, which is easier than keeping track of their positions. Outputs from
+    /// `DispatchLogger::new()` have no tags.
+    pub fn tagged_output(mut self, output: Box<api::Logger>, tags: &[(&str, &str)])
+            -> DispatchLogger {
+        let mut output = Output::new(output);
+        output.tags = tags.iter().map(|&(key, value)| (key.to_string(), value.to_string()))
+                          .collect();
+        self.output.push(output);
+        return self;
+    }
+
+    /// Adds an output which only receives messages for which `predicate` returns true, such as
+    /// an alert file for messages containing `PANIC`. The predicate is given the message as
+    /// formatted for the outputs, including any prefixes and hooks.
+    ///
+    /// ```rust,no_run
+    /// # extern crate fern;
+    /// # extern crate log;
+    /// # fn setup() -> std::io::Result<()> {
+    /// let path = std::path::Path::new("alerts.log");
+    /// let alerts = try!(fern::WriterLogger::<std::fs::File>::with_file(path, "\n"));
+    /// let logger = try!(fern::DispatchLogger::new(
+    ///     Box::new(|msg, _level, _location| msg.to_string()),
+    ///     vec![fern::OutputConfig::stdout()], log::LogLevelFilter::Info, vec![]))
+    ///     .matching_output(Box::new(|msg| msg.contains("PANIC")), Box::new(alerts));
+    /// # let _ = logger;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {}
+    /// ```
+    pub fn matching_output(mut self, predicate: Box<Fn(&str) -> bool + Sync + Send>,
+                           output: Box<api::Logger>) -> DispatchLogger {
+        let mut output = Output::new(output);
+        output.predicate = Some(predicate);
+        self.output.push(output);
+        return self;
+    }
+
+    /// Adds an output with its own level and formatter, such as a verbose file alongside a terse
+    /// console. Messages are first checked against this logger's level and directives, and then
+    /// against `level`; `format` is only called for messages passing both, so messages this
+    /// output rejects cost it nothing. The formatter is given the original message, and this
+    /// logger's prefixes and hooks aren't applied to its result.
+    pub fn formatted_output(mut self, format: Box<config::Formatter>,
+                            level: log::LogLevelFilter, output: Box<api::Logger>)
+            -> DispatchLogger {
+        let mut output = Output::new(output);
+        output.level = Some(level);
+        output.format = Some(format);
+        self.output.push(output);
+        return self;
+    }
+
+    /// Adds two outputs splitting messages by severity: `severe` receives messages at `threshold`
+    /// and more severe ones, and `rest` receives all the others. This is useful for sending
+    /// errors to an alerting endpoint, and everything else to a general collector:
+    ///
+    /// ```rust,no_run
+    /// # extern crate fern;
+    /// # extern crate log;
+    /// # fn setup() -> std::io::Result<()> {
+    /// let alerts = try!(fern::UdpLogger::new("alerts.internal:12201"));
+    /// let collector = try!(fern::UdpLogger::new("collector.internal:12201"));
+    /// let logger = try!(fern::DispatchLogger::new(fern::gelf_formatter("web-1"), vec![],
+    ///                                             log::LogLevelFilter::Info, vec![]))
+    ///     .split_output(log::LogLevel::Error, Box::new(alerts), Box::new(collector));
+    /// # let _ = logger;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {}
+    /// ```
+    ///
+    /// Messages still need to pass this logger's level and directives.
+    pub fn split_output(mut self, threshold: log::LogLevel, severe: Box<api::Logger>,
+                        rest: Box<api::Logger>) -> DispatchLogger {
+        let mut severe = Output::new(severe);
+        severe.level = Some(threshold.to_log_level_filter());
+        let mut rest = Output::new(rest);
+        rest.less_severe_than = Some(threshold);
+        self.output.push(severe);
+        self.output.push(rest);
+        return self;
+    }
+
+    /// Sets what this logger does when the output at `index`, in the order of
+    /// `output_descriptions()`, fails. See `ErrorPolicy`. Outputs use `ErrorPolicy::Propagate`
+    /// unless this is called.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no output at `index`.
+    pub fn output_error_policy(mut self, index: usize, policy: ErrorPolicy) -> DispatchLogger {
+        self.output[index].error_policy = policy;
+        return self;
+    }
+
+    /// Sets which context keys, set with `fern::context_scope()`, are seen when formatting
+    /// messages for the output at `index`, in the order of `output_descriptions()`, such as only
+    /// the request id on the console while a JSON file gets every key:
+    ///
+    /// ```rust
+    /// # extern crate fern;
+    /// # extern crate log;
+    /// # fn setup() -> std::io::Result<()> {
+    /// let logger = try!(fern::DispatchLogger::new(fern::Preset::Json.formatter(),
+    ///     vec![try!(fern::file("app.json")), fern::stdout()], log::LogLevelFilter::Info,
+    ///     vec![]))
+    ///     .output_context(1, fern::ContextFilter::Only(vec!["request_id".to_string()]));
+    /// # let _ = logger;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {}
+    /// ```
+    ///
+    /// Outputs with a filter format each message themselves, with their own formatter or this
+    /// logger's, rather than sharing the formatted message with the other outputs. Outputs use
+    /// `ContextFilter::All` unless this is called.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no output at `index`.
+    pub fn output_context(mut self, index: usize, filter: context::ContextFilter)
+            -> DispatchLogger {
+        self.output[index].context_filter = match filter {
+            context::ContextFilter::All => None,
+            filter => Some(sync::Arc::new(filter)),
+        };
+        return self;
+    }
+
+    /// Returns the tags of the output at `index`, in the order of `output_descriptions()`, or
+    /// `None` if there is no such output.
+    pub fn output_tags(&self, index: usize) -> Option<&HashMap<String, String>> {
+        return self.output.get(index).map(|output| &output.tags);
+    }
+
+    /// Returns the positions of the outputs tagged with `key` set to `value`, in the order of
+    /// `output_descriptions()`.
+    pub fn find_outputs(&self, key: &str, value: &str) -> Vec<usize> {
+        return self.output.iter().enumerate()
+                   .filter(|&(_, output)| output.tags.get(key).map_or(false, |v| v == value))
+                   .map(|(index, _)| index)
+                   .collect();
+    }
+
+    /// Removes the outputs tagged with `key` set to `value`, and returns them.
+    ///
+    /// This needs the logger to be mutable, so it can't be used once the logger is shared as the
+    /// global logger.
+    pub fn remove_outputs(&mut self, key: &str, value: &str) -> Vec<Box<api::Logger>> {
+        let mut removed = Vec::new();
+        for index in self.find_outputs(key, value).into_iter().rev() {
+            removed.push(self.output.remove(index).logger);
+        }
+        removed.reverse();
+        return removed;
+    }
+
+    /// Returns the number of outputs this logger sends messages to.
+    pub fn output_count(&self) -> usize {
+        return self.output.len();
+    }
+
+    /// Returns a description of each output this logger sends messages to, in order. See
+    /// `Logger::describe()`.
+    pub fn output_descriptions(&self) -> Vec<String> {
+        return self.output.iter().map(|output| output.logger.describe()).collect();
+    }
+
+    /// Returns the level of this logger. Messages with a lower level than this aren't passed on,
+    /// unless the level is changed by `set_verbose()` or `set_quiet()` - see
+    /// `effective_level()`.
+    pub fn level(&self) -> log::LogLevelFilter {
+        return level_filter_from_usize(self.level.load(atomic::Ordering::SeqCst));
+    }
+
+    /// Changes the level of this logger while it's in use.
+    ///
+    /// To change the configuration of a logger after it has been set as the global logger, wrap
+    /// it in an `Arc` and keep a clone: `init_global_logger()` accepts an `Arc<DispatchLogger>`.
+    /// Note that the global log level given to `init_global_logger()` still applies, as the
+    /// `log` crate filters messages before they reach fern.
+    pub fn set_level(&self, level: log::LogLevelFilter) {
+        self.level.store(level as usize, atomic::Ordering::SeqCst);
+    }
+
+    /// Sets whether this logger is quiet. While quiet, anything less severe than `Error` is
+    /// dropped, regardless of the level, directives and `set_verbose()`.
+    pub fn set_quiet(&self, quiet: bool) {
+        self.quiet.store(quiet, atomic::Ordering::SeqCst);
+    }
+
+    /// Sets whether this logger is verbose. While verbose, messages one level lower than this
+    /// logger's level are passed on as well, so `Info` becomes `Debug`, and `Debug` becomes
+    /// `Trace`. This has no effect on a logger whose level is `Off`. `set_quiet()` takes
+    /// precedence over this.
+    pub fn set_verbose(&self, verbose: bool) {
+        self.verbose.store(verbose, atomic::Ordering::SeqCst);
+    }
+
+    /// Returns the level currently used for filtering messages, after applying `set_quiet()` and
+    /// `set_verbose()` to the level.
+    pub fn effective_level(&self) -> log::LogLevelFilter {
+        let level = self.level();
+        if self.quiet.load(atomic::Ordering::SeqCst) {
+            return cmp::min(level, log::LogLevelFilter::Error);
+        }
+        if self.verbose.load(atomic::Ordering::SeqCst) && level != log::LogLevelFilter::Off {
+            return level_filter_from_usize(cmp::min(level as usize + 1,
+                                                    log::LogLevelFilter::Trace as usize));
+        }
+        return level;
+    }
+
+    /// Returns this logger's effective level and directives in the syntax read by
+    /// `fern::parse_directives()`, such as `info,my_app::db=debug`, for showing the
+    /// configuration in use. The level is the one returned by `effective_level()`, and
+    /// directives are listed in the order they're checked in, shortest name first.
+    pub fn directives_string(&self) -> String {
+        let mut entries = vec![self.effective_level().to_string().to_lowercase()];
+        for directive in &self.directives {
+            // The syntax's plain directives are inclusive, so others are written as the level
+            // below theirs, which lets through the same messages
+            let (separator, level) = match (directive.exact, directive.inclusive) {
+                (true, _) => ("==", directive.level),
+                (false, true) => ("=", directive.level),
+                (false, false) => ("=", level_filter_from_usize(
+                    (directive.level as usize).saturating_sub(1))),
+            };
+            entries.push(format!("{}{}{}", directive.name, separator,
+                                 level.to_string().to_lowercase()));
+        }
+        return entries.join(",");
+    }
+
+    /// Temporarily lets through messages at `level` and above for `target` and anything under
+    /// it, such as turning `db::pool` up to `Trace` while investigating an incident. Until
+    /// `duration` has passed, this takes the place of this logger's level and directives for
+    /// matching targets, after which their usual filtering resumes. Boosting a target which is
+    /// already boosted replaces the earlier boost.
+    ///
+    /// `set_quiet()` still applies to boosted targets, and so does the global log level given to
+    /// `init_global_logger()`, as the `log` crate filters messages before they reach fern.
+    pub fn boost_target(&self, target: &str, level: log::LogLevelFilter, duration: Duration) {
+        let boost = Boost {
+            directive: config::LogDirective::new(target.to_string(), level),
+            expires: Instant::now() + duration,
+        };
+        let mut boosts = match self.boosts.lock() {
+            Ok(boosts) => boosts,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        boosts.retain(|existing| existing.directive.name != target);
+        boosts.push(boost);
+        self.boost_count.store(boosts.len(), atomic::Ordering::SeqCst);
+    }
+
+    /// Logs a banner message, such as
+    /// `logging started (fern 0.4.0, level: INFO, outputs: [stdout, file (app.log)])`, to every
+    /// output. This is meant to be called once, right after creating the logger, so that it's
+    /// clear from the outputs when logging started and that it works.
+    ///
+    /// The banner is logged at `Info` from the `fern::banner` module and target, and is
+    /// formatted like any other message, but it isn't affected by this logger's level or
+    /// directives.
+    pub fn log_banner(&self) -> Result<(), LogError> {
+        static BANNER_LOCATION: log::LogLocation = log::LogLocation {
+            __module_path: BANNER_TARGET,
+            __file: file!(),
+            __line: line!(),
+        };
+        let banner = format!("logging started (fern {}, level: {}, outputs: [{}])",
+                             env!("CARGO_PKG_VERSION"), self.effective_level(),
+                             self.output_descriptions().join(", "));
+        return self.log_unfiltered(&banner, &BANNER_LOCATION, BANNER_TARGET);
+    }
+
+    /// Logs a `logger started` event to every output, and makes `shutdown()` log a matching
+    /// `logger stopped` event, so that audit trails show when logging began and ended:
+    ///
+    /// ```text
+    /// logger started (time: 2016-03-01T12:00:00Z, fern 0.4.0, level: INFO, outputs: [stdout])
+    /// logger stopped (time: 2016-03-01T12:30:00Z, uptime: 1800.000s)
+    /// ```
+    ///
+    /// Both events are logged at `Info` from the `lifecycle` module and target, so that they
+    /// can be told apart from the program's own messages, and are formatted like any other
+    /// message, but they aren't affected by this logger's level or directives. Call this after
+    /// every other builder method, so that the started event describes the final
+    /// configuration. Returns an error if the started event can't be logged.
+    pub fn lifecycle_events(mut self) -> Result<DispatchLogger, LogError> {
+        self.lifecycle_events = true;
+        let started = format!("logger started (time: {}, fern {}, level: {}, outputs: [{}])",
+                              lifecycle_time(), env!("CARGO_PKG_VERSION"),
+                              self.effective_level(), self.output_descriptions().join(", "));
+        try!(self.log_lifecycle_event(&started));
+        return Ok(self);
+    }
+
+    /// Shuts this logger down on a clean exit: logs the `logger stopped` event, if
+    /// `lifecycle_events()` is enabled, and then flushes every output. Messages logged from then
+    /// on are discarded, so the stopped event is the last thing written - though messages which
+    /// other threads were already logging may still get written after it. Calling this again
+    /// does nothing.
+    ///
+    /// Like logging, every output is flushed even if logging the event fails, and the first
+    /// error is returned.
+    pub fn shutdown(&self) -> Result<(), LogError> {
+        if self.shut_down.swap(true, atomic::Ordering::SeqCst) {
+            return Ok(());
+        }
+        let mut result = Ok(());
+        if self.lifecycle_events {
+            let elapsed = self.started.elapsed();
+            result = self.log_lifecycle_event(&format!(
+                "logger stopped (time: {}, uptime: {}.{:03}s)", lifecycle_time(),
+                elapsed.as_secs(), elapsed.subsec_millis()));
+        }
+        let flush_result = api::Logger::flush(self);
+        return result.and(flush_result);
+    }
+
+    /// Returns whether `shutdown()` has been called.
+    pub fn is_shut_down(&self) -> bool {
+        return self.shut_down.load(atomic::Ordering::SeqCst);
+    }
+
+    fn log_lifecycle_event(&self, msg: &str) -> Result<(), LogError> {
+        static LIFECYCLE_LOCATION: log::LogLocation = log::LogLocation {
+            __module_path: LIFECYCLE_TARGET,
+            __file: file!(),
+            __line: line!(),
+        };
+        let level = log::LogLevel::Info;
+        let prefix = self.prefix();
+        let new_msg = self.format_message(&prefix, msg, &level, &LIFECYCLE_LOCATION);
+        return self.send_to_outputs(Some(&new_msg), &prefix, msg, &level, &LIFECYCLE_LOCATION,
+                                    LIFECYCLE_TARGET);
+    }
+
+    /// Changes this logger's level to the next more verbose one, going from `Error` to `Warn`,
+    /// `Info`, `Debug` and `Trace`, and then from `Trace`, or `Off`, back to `Error`. Returns the
+    /// new level. See `fern::cycle_level_on_sigusr1()`.
+    ///
+    /// The change is logged as `log level changed to DEBUG`, at `Info` from the `fern::level`
+    /// module and target, to every output, regardless of the level and directives.
+    pub fn cycle_level(&self) -> Result<log::LogLevelFilter, LogError> {
+        static LEVEL_LOCATION: log::LogLocation = log::LogLocation {
+            __module_path: LEVEL_TARGET,
+            __file: file!(),
+            __line: line!(),
+        };
+        let level = match self.level() {
+            log::LogLevelFilter::Trace => log::LogLevelFilter::Error,
+            level => level_filter_from_usize(level as usize + 1),
+        };
+        self.set_level(level);
+        try!(self.log_unfiltered(&format!("log level changed to {}", level), &LEVEL_LOCATION,
+                                 LEVEL_TARGET));
+        return Ok(level);
+    }
+
+    /// Formats a message logged by fern itself, and sends it to every output at `Info`.
+    fn log_unfiltered(&self, msg: &str, location: &log::LogLocation, target: &str)
             -> Result<(), LogError> {
-        if *level > self.level || self.directive_check(level, location.__module_path) {
+        if self.is_shut_down() {
             return Ok(());
         }
+        let level = log::LogLevel::Info;
+        let prefix = self.prefix();
+        let new_msg = self.format_message(&prefix, msg, &level, location);
+        return self.send_to_outputs(Some(&new_msg), &prefix, msg, &level, location, target);
+    }
+
+    /// Formats a message, starting with `prefix` from `prefix()`.
+    fn format_message(&self, prefix: &str, msg: &str, level: &log::LogLevel,
+            location: &log::LogLocation) -> String {
+        let new_msg = match self.format_into {
+            _ if self.preformatted => msg.to_string(),
+            Some(ref formatter) => {
+                let mut buf = String::new();
+                self.format_into_buffer(&**formatter, prefix, &mut buf, msg, level, location);
+                buf
+            },
+            None => {
+                let new_msg = self.call_format(msg, level, location);
+                match prefix.is_empty() {
+                    true => new_msg,
+                    false => {
+                        let mut buf = String::with_capacity(prefix.len() + new_msg.len());
+                        buf.push_str(prefix);
+                        buf.push_str(&new_msg);
+                        buf
+                    },
+                }
+            },
+        };
+        let mut new_msg = self.hooks.iter().fold(new_msg, |new_msg, hook| hook(&new_msg));
+        self.trim_message(&mut new_msg);
+        return new_msg;
+    }
 
-        let new_msg = (self.format)(msg, level, location);
-        for logger in &self.output {
-            try!(logger.log(&new_msg, level, location));
+    fn trim_message(&self, msg: &mut String) {
+        if self.trim_end {
+            let len = msg.trim_end().len();
+            msg.truncate(len);
         }
-        return Ok(());
     }
-}
 
-impl log::Log for DispatchLogger {
-    fn enabled(&self, metadata: &log::LogMetadata) -> bool {
-        metadata.level() <= self.level
+    /// Returns the record number, uptime and instance tag which go before a formatted message.
+    /// This takes the message's record number, so it's called once for each message, and the
+    /// result is shared by every output formatting it. Preformatted messages have no prefix.
+    fn prefix(&self) -> String {
+        let mut buf = String::new();
+        if self.preformatted {
+            return buf;
+        }
+        if let Some(width) = self.record_number_width {
+            let number = NEXT_RECORD_NUMBER.fetch_add(1, atomic::Ordering::SeqCst);
+            buf.push_str(&format!("#{:0width$} ", number, width = width));
+        }
+        if let Some(precision) = self.uptime_precision {
+            config::write_uptime(&mut buf, self.uptime(), precision);
+            buf.push(' ');
+        }
+        if let Some(ref tag) = self.instance_tag {
+            buf.push('[');
+            buf.push_str(tag);
+            buf.push_str("] ");
+        }
+        return buf;
     }
 
-    fn log(&self, record: &log::LogRecord) {
-        // shortstop for checking level here, so we don't have to do any conversions in
-        // log_with_fern_logger
-        if record.level() > self.level {
-            return;
+    fn format_into_buffer(&self, formatter: &config::FormatterInto, prefix: &str,
+            buf: &mut String, msg: &str, level: &log::LogLevel, location: &log::LogLocation) {
+        buf.push_str(prefix);
+        if !self.catch_format_panics {
+            return formatter.format_into(buf, msg, level, location);
+        }
+        let start = buf.len();
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            formatter.format_into(buf, msg, level, location);
+        }));
+        if result.is_err() {
+            // Drop whatever the formatter wrote before panicking
+            buf.truncate(start);
+            buf.push_str(&self.format_panicked(msg, level));
         }
-        log_with_fern_logger(self, record);
     }
-}
 
-pub struct WriterLogger<T: io::Write + Send> {
-    writer: sync::Arc<sync::Mutex<T>>,
-    line_sep: String,
-}
+    fn call_format(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> String {
+        if !self.catch_format_panics {
+            return (self.format)(msg, level, location);
+        }
+        return match panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            (self.format)(msg, level, location)
+        })) {
+            Ok(new_msg) => new_msg,
+            Err(_) => self.format_panicked(msg, level),
+        };
+    }
 
-impl <T: io::Write + Send> WriterLogger<T> {
-    pub fn new(writer: T, line_sep: &str) -> WriterLogger<T> {
-        return WriterLogger {
-            writer: sync::Arc::new(sync::Mutex::new(writer)),
-            line_sep: line_sep.to_string(),
+    /// Warns about a panicking formatter the first time, and returns the fallback format.
+    fn format_panicked(&self, msg: &str, level: &log::LogLevel) -> String {
+        if !self.warned_format_panic.swap(true, atomic::Ordering::SeqCst) {
+            let _ = writeln!(&mut io::stderr(),
+                "Warning: fern formatter panicked, so messages it panics on are logged with just \
+                their level and message.");
+        }
+        return format!("[{}] {}", config::level_str(level), msg);
+    }
+
+    /// Formats the message and calls `f` with the result. With a `FormatterInto` and no hooks,
+    /// the message is formatted into this thread's reused buffer. Preformatted messages are passed
+    /// on as they are, unless there are hooks or trimming to apply.
+    fn with_formatted_message<F>(&self, prefix: &str, msg: &str, level: &log::LogLevel,
+            location: &log::LogLocation, f: F) -> Result<(), LogError>
+            where F: FnOnce(&str) -> Result<(), LogError> {
+        if self.preformatted && self.hooks.is_empty() && !self.trim_end {
+            return f(msg);
+        }
+        let formatter = match self.format_into {
+            Some(ref formatter) if self.hooks.is_empty() && !self.preformatted => formatter,
+            _ => return f(&self.format_message(prefix, msg, level, location)),
         };
+        return FORMAT_BUFFER.with(|buffer| {
+            match buffer.try_borrow_mut() {
+                Ok(mut buf) => {
+                    buf.clear();
+                    self.format_into_buffer(&**formatter, prefix, &mut buf, msg, level, location);
+                    self.trim_message(&mut buf);
+                    let result = f(&buf);
+                    if let Some(capacity) = self.max_buffer_capacity {
+                        if buf.capacity() > capacity {
+                            buf.clear();
+                            buf.shrink_to(capacity);
+                        }
+                    }
+                    result
+                },
+                // The buffer is already in use when an output logs another message on this
+                // thread while this one is being sent.
+                Err(_) => {
+                    let mut buf = String::new();
+                    self.format_into_buffer(&**formatter, prefix, &mut buf, msg, level, location);
+                    self.trim_message(&mut buf);
+                    f(&buf)
+                },
+            }
+        });
     }
 
-    pub fn with_stdout() -> WriterLogger<io::Stdout> {
-        return WriterLogger::new(io::stdout(), "\n");
+    /// Sends a message to every output accepting its level. Outputs with their own formatter
+    /// format `raw` themselves, and the others are given `formatted`, which is only `None` when
+    /// none of them accept the message.
+    fn send_to_outputs(&self, formatted: Option<&str>, prefix: &str, raw: &str,
+            level: &log::LogLevel, location: &log::LogLocation, target: &str)
+            -> Result<(), LogError> {
+        if self.output.is_empty() && self.empty_output == EmptyOutput::Warn
+                && !self.warned_empty_output.swap(true, atomic::Ordering::SeqCst) {
+            let _ = writeln!(&mut io::stderr(),
+                "Warning: fern logger has no outputs, so all messages are discarded. Add an \
+                output, or use EmptyOutput::Discard if this is intentional.");
+        }
+        let mut result = Ok(());
+        // What happened at each output, for the diagnostic tap
+        let mut reached = match self.diagnostic_tap {
+            Some(_) => Some(Vec::new()),
+            None => None,
+        };
+        for (index, output) in self.output.iter().enumerate() {
+            if !output.accepts_level(level) {
+                if let Some(ref mut reached) = reached {
+                    reached.push(format!("#{} {}: skipped by level", index,
+                                         output.logger.describe()));
+                }
+                continue;
+            }
+            let own_msg;
+            let msg = match (&output.format, formatted, &output.context_filter) {
+                (format, _, &Some(ref filter)) => {
+                    own_msg = context::filtered(filter, || match *format {
+                        Some(ref format) => format(raw, level, location),
+                        None => self.format_message(prefix, raw, level, location),
+                    });
+                    &*own_msg
+                },
+                (&Some(ref format), _, &None) => {
+                    own_msg = format(raw, level, location);
+                    &*own_msg
+                },
+                (&None, Some(formatted), &None) => formatted,
+                (&None, None, &None) => continue,
+            };
+            if let Some(ref predicate) = output.predicate {
+                if !predicate(msg) {
+                    if let Some(ref mut reached) = reached {
+                        reached.push(format!("#{} {}: skipped by predicate", index,
+                                             output.logger.describe()));
+                    }
+                    continue;
+                }
+            }
+            let output_result = output.logger.log_with_target(msg, level, location, target);
+            if let Some(ref mut reached) = reached {
+                reached.push(match output_result {
+                    Ok(()) => format!("#{} {}: ok", index, output.logger.describe()),
+                    Err(ref e) => format!("#{} {}: error: {}", index, output.logger.describe(),
+                                          e),
+                });
+            }
+            if let Err(e) = output.handle_result(index, output_result) {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        if let Some(reached) = reached {
+            self.write_tap(raw, level, location, target, &format!("[{}]", reached.join(", ")));
+        }
+        return result;
     }
 
-    pub fn with_stderr() -> WriterLogger<io::Stderr> {
-        return WriterLogger::new(io::stderr(), "\n");
+    /// Appends a message and what happened to it to the diagnostic tap, if there is one.
+    fn write_tap(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str, outcome: &str) {
+        if let Some(ref tap) = self.diagnostic_tap {
+            let mut tap = match tap.lock() {
+                Ok(tap) => tap,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            let _ = writeln!(tap, "{} {} {} ({}) {:?} -> {}", lifecycle_time(), level, target,
+                             config::short_location(location), msg, outcome);
+        }
     }
 
-    pub fn with_file(path: &path::Path, line_sep: &str) -> io::Result<WriterLogger<fs::File>> {
-        return Ok(WriterLogger::new(try!(fs::OpenOptions::new().write(true).append(true)
-                                            .create(true).open(path)), line_sep));
+    /// Returns the level of the longest active boost matching the target, removing any boosts
+    /// which have expired.
+    fn boost_level(&self, target: &str) -> Option<log::LogLevelFilter> {
+        // Checked first so that logging doesn't lock anything unless a boost was installed.
+        if self.boost_count.load(atomic::Ordering::SeqCst) == 0 {
+            return None;
+        }
+        let mut boosts = match self.boosts.lock() {
+            Ok(boosts) => boosts,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let now = Instant::now();
+        boosts.retain(|boost| boost.expires > now);
+        self.boost_count.store(boosts.len(), atomic::Ordering::SeqCst);
+        return boosts.iter()
+                     .filter(|boost| target.starts_with(&*boost.directive.name))
+                     .max_by_key(|boost| boost.directive.name.len())
+                     .map(|boost| boost.directive.level);
     }
 
-    pub fn with_file_with_options(path: &path::Path, options: &fs::OpenOptions, line_sep: &str)
-            -> io::Result<WriterLogger<fs::File>> {
-        return Ok(WriterLogger::new(try!(options.open(path)), line_sep));
+    /// Returns whether a message passes this logger's level, directives and boosts. Directives
+    /// match the module path the message was logged from, and boosts its target.
+    fn passes(&self, level: &log::LogLevel, target: &str, module_path: &str) -> bool {
+        if self.quiet.load(atomic::Ordering::SeqCst) && *level > log::LogLevel::Error {
+            return false;
+        }
+        if let Some(ref filter) = self.thread_filter {
+            if !filter.allows_current() {
+                return false;
+            }
+        }
+        return match self.boost_level(target) {
+            Some(boost_level) => *level <= boost_level,
+            None => {
+                *level <= self.effective_level() && !self.directive_check(level, module_path)
+            },
+        };
     }
-}
 
-impl <T: io::Write + Send> api::Logger for WriterLogger<T> {
-    fn log(&self, msg: &str, _level: &log::LogLevel, _location: &log::LogLocation)
-            -> Result<(), LogError> {
-        try!(write!(try!(self.writer.lock()), "{}{}", msg, self.line_sep));
-        return Ok(());
+    /// Returns whether a message might pass, without checking directives or boosts.
+    fn may_pass(&self, level: log::LogLevel, target: &str) -> bool {
+        if level > STATIC_MAX_LEVEL {
+            return false;
+        }
+        return level <= self.effective_level() || self.is_audit_target(target)
+               || self.boost_count.load(atomic::Ordering::SeqCst) > 0;
     }
-}
 
-impl <T: io::Write + Send> log::Log for WriterLogger<T> {
-    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
-        true
+    fn is_audit_target(&self, target: &str) -> bool {
+        return self.audit.is_some() && target == AUDIT_TARGET;
     }
 
-    fn log(&self, record: &log::LogRecord) {
-        log_with_fern_logger(self, record);
+    fn directive_check(&self, level: &log::LogLevel, module_path: &str) -> bool {
+        return !config::matches(&self.directives, level, module_path);
     }
 }
 
-/// A logger implementation which does nothing with logged messages.
-#[derive(Clone, Copy)]
-pub struct NullLogger;
-
-impl api::Logger for NullLogger {
-    fn log(&self, _msg: &str, _level: &log::LogLevel, _location: &log::LogLocation)
+impl api::Logger for DispatchLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
             -> Result<(), LogError> {
-        return Ok(());
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        // This is a constant comparison, so the compiler removes everything below for levels
+        // disabled with the max_level_* features.
+        if *level > STATIC_MAX_LEVEL || self.is_shut_down() {
+            return Ok(());
+        }
+        let audit = match self.audit {
+            Some(ref audit) if target == AUDIT_TARGET => Some(audit),
+            _ => None,
+        };
+        let passes = self.passes(level, target, location.__module_path);
+        if !passes {
+            self.write_tap(msg, level, location, target, "filtered out");
+        }
+        if !passes && audit.is_none() {
+            return Ok(());
+        }
+        let short_location;
+        let location = match self.short_module_paths {
+            Some((from, segments)) if *level >= from => {
+                short_location = log::LogLocation {
+                    __module_path: config::module_path_tail(location.__module_path, segments),
+                    .. *location
+                };
+                &short_location
+            },
+            _ => location,
+        };
+        if passes {
+            for callback in &self.record_callbacks {
+                callback(level);
+            }
+        }
+        // Outputs are checked before formatting, so that the shared formatter isn't called when
+        // only outputs with their own formatter will receive the message.
+        let capturing = passes && capture::is_capturing();
+        let shared_needed = capturing || audit.is_some()
+            || self.output.iter().any(|output| output.format.is_none()
+                                               && output.context_filter.is_none()
+                                               && output.accepts_level(level));
+        // Taken once, so that a message has the same record number at every output
+        let prefix = match shared_needed || self.output.iter().any(|output| {
+            output.format.is_none() && output.context_filter.is_some()
+                && output.accepts_level(level)
+        }) {
+            true => self.prefix(),
+            false => String::new(),
+        };
+        if !shared_needed {
+            return self.send_to_outputs(None, &prefix, msg, level, location, target);
+        }
+
+        return self.with_formatted_message(&prefix, msg, level, location, |new_msg| {
+            // The message is fully formatted before being sent anywhere, and is sent to every
+            // output even if an earlier one fails, so a failing output never affects what the
+            // others receive. The first error is returned once all outputs have been tried.
+            let mut result = Ok(());
+            if let Some(audit) = audit {
+                result = audit.log_with_target(new_msg, level, location, target);
+            }
+            if capturing && capture::push(new_msg) {
+                self.write_tap(msg, level, location, target, "captured");
+                return result;
+            }
+            if passes {
+                let output_result = self.send_to_outputs(Some(new_msg), &prefix, msg, level,
+                                                         location, target);
+                if result.is_ok() {
+                    result = output_result;
+                }
+            }
+            return result;
+        });
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        // Like logging, every output is flushed even if an earlier one fails.
+        let mut result = match self.audit {
+            Some(ref audit) if audit.needs_flush() => audit.flush(),
+            _ => Ok(()),
+        };
+        for (index, output) in self.output.iter().enumerate() {
+            if output.logger.needs_flush() {
+                if let Err(e) = output.handle_result(index, output.logger.flush()) {
+                    if result.is_ok() {
+                        result = Err(e);
+                    }
+                }
+            }
+        }
+        return result;
+    }
+
+    fn needs_flush(&self) -> bool {
+        return self.audit.iter().any(|audit| audit.needs_flush())
+               || self.output.iter().any(|output| output.logger.needs_flush());
+    }
+
+    fn describe(&self) -> String {
+        return format!("dispatch (level: {}, outputs: [{}])", self.effective_level(),
+                       self.output_descriptions().join(", "));
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (index, output) in self.output.iter().enumerate() {
+            for problem in output.logger.validate() {
+                problems.push(format!("output #{} ({}): {}", index, output.logger.describe(),
+                                      problem));
+            }
+        }
+        return problems;
+    }
+}
+
+impl log::Log for DispatchLogger {
+    fn enabled(&self, metadata: &log::LogMetadata) -> bool {
+        self.may_pass(metadata.level(), metadata.target())
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        // shortstop for checking level here, so we don't have to do any conversions in
+        // log_with_fern_logger
+        if !self.may_pass(record.level(), record.target()) {
+            return;
+        }
+        match self.stderr_backup {
+            true => log_with_fern_logger(self, record),
+            false => {
+                let _ = api::Logger::log_with_target(self, &format!("{}", record.args()),
+                                                     &record.level(), record.location(),
+                                                     record.target());
+            },
+        }
+    }
+}
+
+/// How often a `WriterLogger` writing to a file syncs it to disk, trading the performance of
+/// logging for how many messages survive a crash of the machine. See
+/// `WriterLogger::durability()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Durability {
+    /// Never sync, leaving it to the OS to write the data out when it sees fit. This is the
+    /// default.
+    None,
+    /// Sync from a background thread once every interval, if anything was written since the
+    /// last sync. At most the messages of one interval are lost, without logging ever waiting
+    /// for the disk.
+    PeriodicFsync(Duration),
+    /// Sync after every error, as with `sync_level(LogLevelFilter::Error)`.
+    FsyncEachError,
+    /// Sync after every message. This is by far the slowest.
+    FsyncEachLine,
+}
+
+/// A writer which can be made to write all of its data through to disk, like `fs::File`. See
+/// `WriterLogger::sync_level()`.
+pub trait SyncAll: io::Write {
+    /// Writes all data and metadata through to disk, like `fs::File::sync_all()`.
+    fn sync_all(&self) -> io::Result<()>;
+}
+
+impl SyncAll for fs::File {
+    fn sync_all(&self) -> io::Result<()> {
+        return fs::File::sync_all(self);
+    }
+}
+
+fn sync_writer<T: SyncAll>(writer: &T) -> io::Result<()> {
+    return writer.sync_all();
+}
+
+/// Returns OpenOptions with write(true), append(true) and create(true), which also create the
+/// file with the given permissions on Unix, if any. See `WriterLogger::with_file_mode()`.
+pub fn append_options(mode: Option<u32>) -> fs::OpenOptions {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).append(true).create(true);
+    if let Some(mode) = mode {
+        set_create_mode(&mut options, mode);
+    }
+    return options;
+}
+
+/// Creates a new file in `dir` named from the current local time, as described in
+/// `WriterLogger::with_timestamped_file()`.
+fn create_timestamped_file(dir: &path::Path, prefix: &str, suffix: &str)
+        -> io::Result<(fs::File, path::PathBuf)> {
+    let timestamp = match time::now().strftime("%Y-%m-%dT%H-%M-%S") {
+        Ok(timestamp) => timestamp.to_string(),
+        Err(e) => return Err(io::Error::new(io::ErrorKind::Other, e)),
+    };
+    let mut counter = 0;
+    loop {
+        let name = match counter {
+            0 => format!("{}{}{}", prefix, timestamp, suffix),
+            _ => format!("{}{}.{}{}", prefix, timestamp, counter, suffix),
+        };
+        let path = dir.join(name);
+        match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((file, path)),
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => counter += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn set_create_mode(options: &mut fs::OpenOptions, mode: u32) {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.mode(mode);
+}
+
+#[cfg(not(unix))]
+fn set_create_mode(_options: &mut fs::OpenOptions, _mode: u32) {}
+
+/// What a logger does when writing a message fails with an `io::ErrorKind`. See
+/// `IoErrorPolicy`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IoErrorAction {
+    /// Try writing again, from where the failed write left off.
+    Retry,
+    /// Return the error from the logger.
+    Fail,
+    /// Discard the rest of the message, and count it as dropped.
+    Drop,
+}
+
+/// Maps the kinds of I/O errors writing a message may fail with to what the logger does about
+/// them, for `WriterLogger::io_error_policy()` and `UdpLogger::io_error_policy()`. For instance,
+/// a non-blocking socket may be allowed to drop messages with `WouldBlock` rather than failing:
+///
+/// ```rust
+/// use std::io::ErrorKind;
+/// use fern::{IoErrorAction, IoErrorPolicy};
+///
+/// let policy = IoErrorPolicy::new().on(ErrorKind::WouldBlock, IoErrorAction::Drop);
+/// assert_eq!(policy.action(ErrorKind::Interrupted), IoErrorAction::Retry);
+/// assert_eq!(policy.action(ErrorKind::WouldBlock), IoErrorAction::Drop);
+/// assert_eq!(policy.action(ErrorKind::PermissionDenied), IoErrorAction::Fail);
+/// ```
+#[derive(Clone, Debug)]
+pub struct IoErrorPolicy {
+    actions: Vec<(io::ErrorKind, IoErrorAction)>,
+    otherwise: IoErrorAction,
+    max_retries: usize,
+}
+
+impl IoErrorPolicy {
+    /// Creates a policy retrying writes interrupted by a signal, with `Interrupted`, and failing
+    /// on any other error. This is the default for every logger which takes a policy.
+    pub fn new() -> IoErrorPolicy {
+        return IoErrorPolicy {
+            actions: vec![(io::ErrorKind::Interrupted, IoErrorAction::Retry)],
+            otherwise: IoErrorAction::Fail,
+            max_retries: 3,
+        };
+    }
+
+    /// Sets the action for errors of `kind`, replacing any action set for it before.
+    pub fn on(mut self, kind: io::ErrorKind, action: IoErrorAction) -> IoErrorPolicy {
+        self.actions.retain(|&(k, _)| k != kind);
+        self.actions.push((kind, action));
+        return self;
+    }
+
+    /// Sets the action for errors of any kind without an action of its own. This defaults to
+    /// `IoErrorAction::Fail`.
+    pub fn otherwise(mut self, action: IoErrorAction) -> IoErrorPolicy {
+        self.otherwise = action;
+        return self;
+    }
+
+    /// Sets how many times in a row a write is retried before the error is returned anyway, so
+    /// that a writer which keeps failing doesn't hang the logging thread. This defaults to 3.
+    pub fn max_retries(mut self, max_retries: usize) -> IoErrorPolicy {
+        self.max_retries = max_retries;
+        return self;
+    }
+
+    /// Returns the action for errors of `kind`.
+    pub fn action(&self, kind: io::ErrorKind) -> IoErrorAction {
+        return match self.actions.iter().find(|&&(k, _)| k == kind) {
+            Some(&(_, action)) => action,
+            None => self.otherwise,
+        };
+    }
+
+    /// Calls `attempt` until it succeeds or fails with an error which isn't retried, returning
+    /// `Ok(false)` if the error's action is to drop the message.
+    pub fn run<F>(&self, mut attempt: F) -> io::Result<bool> where F: FnMut() -> io::Result<()> {
+        let mut retries = 0;
+        loop {
+            let e = match attempt() {
+                Ok(()) => return Ok(true),
+                Err(e) => e,
+            };
+            match self.action(e.kind()) {
+                IoErrorAction::Retry if retries < self.max_retries => retries += 1,
+                IoErrorAction::Drop => return Ok(false),
+                _ => return Err(e),
+            }
+        }
+    }
+
+    /// Writes all of `parts` to `writer`, retrying failed writes from where they left off as
+    /// the policy says, and adding the bytes written to `bytes_written`. Returns `Ok(false)` if
+    /// the rest of the message was dropped.
+    fn write_all<W: io::Write + ?Sized>(&self, writer: &mut W, parts: &[&[u8]],
+            bytes_written: &atomic::AtomicU64) -> io::Result<bool> {
+        for part in parts {
+            let mut written = 0;
+            while written < part.len() {
+                let mut n = 0;
+                if !try!(self.run(|| writer.write(&part[written..]).map(|count| n = count))) {
+                    return Ok(false);
+                }
+                if n == 0 {
+                    return Err(io::Error::new(io::ErrorKind::WriteZero,
+                                              "failed to write whole log message"));
+                }
+                written += n;
+                bytes_written.fetch_add(n as u64, atomic::Ordering::SeqCst);
+            }
+        }
+        return Ok(true);
+    }
+}
+
+/// A logger implementation which writes messages to any `io::Write`, such as a file or stdout.
+///
+/// Each message is written followed by the line separator, with the writer held behind a mutex
+/// so that messages from different threads don't interleave.
+pub struct WriterLogger<T: io::Write + Send> {
+    writer: sync::Arc<sync::Mutex<T>>,
+    line_sep: String,
+    label: String,
+    buffered: bool,
+    ignore_broken_pipe: bool,
+    closed: atomic::AtomicBool,
+    sync: Option<(log::LogLevelFilter, fn(&T) -> io::Result<()>)>,
+    non_blocking: bool,
+    dropped_count: atomic::AtomicUsize,
+    recover_poisoned: bool,
+    warned_poisoned: atomic::AtomicBool,
+    io_error_policy: IoErrorPolicy,
+    flush_targets: Vec<String>,
+    bytes_written: atomic::AtomicU64,
+    // Stops the thread syncing for `Durability::PeriodicFsync` when dropped
+    periodic_sync: Option<mpsc::Sender<()>>,
+    // Whether anything was written since the last periodic sync
+    dirty: sync::Arc<atomic::AtomicBool>,
+}
+
+impl <T: io::Write + Send> WriterLogger<T> {
+    /// Creates a new logger writing to the given writer, using `line_sep` as the separator after
+    /// each message.
+    pub fn new(writer: T, line_sep: &str) -> WriterLogger<T> {
+        return WriterLogger {
+            writer: sync::Arc::new(sync::Mutex::new(writer)),
+            line_sep: line_sep.to_string(),
+            label: "writer".to_string(),
+            buffered: true,
+            ignore_broken_pipe: false,
+            closed: atomic::AtomicBool::new(false),
+            sync: None,
+            non_blocking: false,
+            dropped_count: atomic::AtomicUsize::new(0),
+            recover_poisoned: false,
+            warned_poisoned: atomic::AtomicBool::new(false),
+            io_error_policy: IoErrorPolicy::new(),
+            flush_targets: Vec::new(),
+            bytes_written: atomic::AtomicU64::new(0),
+            periodic_sync: None,
+            dirty: sync::Arc::new(atomic::AtomicBool::new(false)),
+        };
+    }
+
+    /// Creates a new logger writing to stdout. This logger ignores `BrokenPipe` errors, see
+    /// `ignore_broken_pipe()`. To keep a full pipe from blocking, see `write_in_background()`.
+    ///
+    /// The line separator is `\n` on every platform, Windows included. The Windows console
+    /// starts a new line on `\n` by itself, and when the output is redirected, whatever reads
+    /// it in text mode translates `\n` to `\r\n` - so writing `\r\n` would end lines with
+    /// `\r\r\n` there. For the same reason, a `\r\n` separator given to
+    /// `DispatchLogger::with_line_sep()` for files isn't applied to stdout and stderr on
+    /// Windows. Use `line_sep()` to set another separator anyway.
+    pub fn with_stdout() -> WriterLogger<io::Stdout> {
+        return WriterLogger::new(io::stdout(), "\n").label("stdout").buffered(false)
+                  .ignore_broken_pipe(true);
+    }
+
+    /// Creates a new logger writing to stderr. This logger ignores `BrokenPipe` errors, see
+    /// `ignore_broken_pipe()`. The line separator is `\n`, as for `with_stdout()`.
+    pub fn with_stderr() -> WriterLogger<io::Stderr> {
+        return WriterLogger::new(io::stderr(), "\n").label("stderr").buffered(false)
+                  .ignore_broken_pipe(true);
+    }
+
+    /// Opens the given path with write(true), append(true) and create(true), and creates a new
+    /// logger writing to it.
+    pub fn with_file(path: &path::Path, line_sep: &str) -> io::Result<WriterLogger<fs::File>> {
+        return WriterLogger::<fs::File>::with_file_with_options(path, &append_options(None),
+                                                                line_sep);
+    }
+
+    /// Opens the given path with write(true), truncate(true) and create(true), and creates a new
+    /// logger writing to it. Unlike `with_file()`, this empties the file if it already exists, so
+    /// that it only holds the messages of the current run, such as for a tool which is run
+    /// once in a while and whose previous logs aren't interesting.
+    pub fn with_truncated_file(path: &path::Path, line_sep: &str)
+            -> io::Result<WriterLogger<fs::File>> {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).truncate(true).create(true);
+        return WriterLogger::<fs::File>::with_file_with_options(path, &options, line_sep);
+    }
+
+    /// Like `with_file()`, but if the file doesn't exist yet, creates it with the given
+    /// permissions, such as `0o600` to only let the owner read it. As with any new file, the
+    /// process umask is applied on top. The permissions of existing files are left alone.
+    ///
+    /// On platforms other than Unix, `mode` is ignored and this is the same as `with_file()`.
+    pub fn with_file_mode(path: &path::Path, line_sep: &str, mode: u32)
+            -> io::Result<WriterLogger<fs::File>> {
+        return WriterLogger::<fs::File>::with_file_with_options(path, &append_options(Some(mode)),
+                                                                line_sep);
+    }
+
+    /// Opens the given path with the given OpenOptions, and creates a new logger writing to it.
+    pub fn with_file_with_options(path: &path::Path, options: &fs::OpenOptions, line_sep: &str)
+            -> io::Result<WriterLogger<fs::File>> {
+        return Ok(WriterLogger::new(try!(options.open(path)), line_sep)
+                  .label(&format!("file ({})", path.display())).buffered(false));
+    }
+
+    /// Creates a new file in `dir` named from the current local time, such as
+    /// `app.2016-03-01T12-00-00.log` for a prefix of `app.` and a suffix of `.log`, and creates a
+    /// new logger writing to it. This gives each run of a program its own log file.
+    ///
+    /// Existing files are never written to - if a file with the same name already exists, for
+    /// instance when the program was started twice in the same second, a counter is added to the
+    /// name, as in `app.2016-03-01T12-00-00.1.log`.
+    pub fn with_timestamped_file(dir: &path::Path, prefix: &str, suffix: &str, line_sep: &str)
+            -> io::Result<WriterLogger<fs::File>> {
+        let (file, path) = try!(create_timestamped_file(dir, prefix, suffix));
+        return Ok(WriterLogger::new(file, line_sep).label(&format!("file ({})", path.display()))
+                  .buffered(false));
+    }
+
+    /// Sets the separator written after each message, replacing the one this logger was created
+    /// with.
+    pub fn line_sep(mut self, line_sep: &str) -> WriterLogger<T> {
+        self.line_sep = line_sep.to_string();
+        return self;
+    }
+
+    /// Sets the label describing this logger, returned by `Logger::describe()`. This defaults to
+    /// `stdout`, `stderr` or `file (<path>)` for the respective constructors, and `writer`
+    /// otherwise.
+    pub fn label(mut self, label: &str) -> WriterLogger<T> {
+        self.label = label.to_string();
+        return self;
+    }
+
+    /// Sets whether the writer may buffer data, and so needs to be flushed - see
+    /// `Logger::needs_flush()`. This defaults to false for stdout, stderr and files, which fern
+    /// writes whole lines to, and true otherwise.
+    pub fn buffered(mut self, buffered: bool) -> WriterLogger<T> {
+        self.buffered = buffered;
+        return self;
+    }
+
+    /// Makes this logger flush the writer right after writing each message logged with one of
+    /// `targets`, such as `audit`, so that those messages are never left waiting in a buffer.
+    /// Messages with other targets stay buffered until the writer is flushed as usual. Targets
+    /// are matched exactly. Messages logged with `Logger::log_bytes()` are matched by their
+    /// module path.
+    pub fn flush_targets(mut self, targets: &[&str]) -> WriterLogger<T> {
+        self.flush_targets = targets.iter().map(|target| target.to_string()).collect();
+        return self;
+    }
+
+    /// Sets whether this logger drops messages rather than waiting when another thread is writing
+    /// to the writer. This keeps latency-critical threads from ever blocking on logging, at the
+    /// cost of losing messages under contention - see `dropped_count()`. This defaults to false,
+    /// waiting for the writer.
+    pub fn non_blocking(mut self, non_blocking: bool) -> WriterLogger<T> {
+        self.non_blocking = non_blocking;
+        return self;
+    }
+
+    /// Returns the number of messages dropped because the writer was in use, with
+    /// `non_blocking()` enabled, or because writing them failed with an error the
+    /// `io_error_policy()` drops messages on.
+    pub fn dropped_count(&self) -> usize {
+        return self.dropped_count.load(atomic::Ordering::SeqCst);
+    }
+
+    /// Returns the number of bytes written to the writer since this logger was created,
+    /// counting line separators, and the parts of messages which were only partly written
+    /// before an error. Bytes written by `utf8_bom()` aren't counted.
+    pub fn bytes_written(&self) -> u64 {
+        return self.bytes_written.load(atomic::Ordering::SeqCst);
+    }
+
+    /// Sets whether this logger keeps writing after a thread panicked while holding the writer,
+    /// such as a writer which panics on an I/O error, rather than returning `LogError::Poison`
+    /// for every message from then on. The panicking thread may have left a partial message
+    /// behind, so the first time this happens, a warning naming this logger's label is written
+    /// to stderr. The warning is only written once per logger. This defaults to false.
+    pub fn recover_poisoned(mut self, recover: bool) -> WriterLogger<T> {
+        self.recover_poisoned = recover;
+        return self;
+    }
+
+    /// Sets whether this logger should stop logging silently when the writer returns a
+    /// `BrokenPipe` error, rather than returning the error. This is useful for stdout when the
+    /// program's output is piped into something like `head`, which closes the pipe early.
+    ///
+    /// Once a `BrokenPipe` error has been seen, all further messages are discarded without
+    /// writing.
+    pub fn ignore_broken_pipe(mut self, ignore: bool) -> WriterLogger<T> {
+        self.ignore_broken_pipe = ignore;
+        return self;
+    }
+
+    /// Sets what this logger does when writing a message fails, by the kind of error. Writes
+    /// which are retried continue from where they left off, so a message is never written
+    /// twice. This defaults to `IoErrorPolicy::new()`, retrying on `Interrupted` and failing
+    /// otherwise. `BrokenPipe` errors are still ignored with `ignore_broken_pipe()` if the
+    /// policy fails on them.
+    pub fn io_error_policy(mut self, policy: IoErrorPolicy) -> WriterLogger<T> {
+        self.io_error_policy = policy;
+        return self;
+    }
+}
+
+impl <T: io::Write + Send + 'static> WriterLogger<T> {
+    /// Moves writing to a background thread, returning a `ChannelLogger` which queues up to
+    /// `capacity` messages for it. This keeps a writer which blocks, such as stdout piped into a
+    /// slow consumer which has let the pipe fill up, from stalling the threads logging to it.
+    ///
+    /// With `QueueFullMode::Drop`, messages are dropped once the queue is full, so logging never
+    /// waits for the writer, but whatever is logged while the pipe stays full is lost. With
+    /// `QueueFullMode::Block`, messages are buffered until the queue is full, and logging only
+    /// waits once it is, so all messages are kept but may still stall the program. Either way,
+    /// a larger capacity rides out longer stalls at the cost of memory, and of messages which
+    /// are still queued if the program crashes.
+    pub fn write_in_background(self, capacity: usize, mode: QueueFullMode) -> ChannelLogger {
+        return ChannelLogger::new(Box::new(self), capacity, mode);
+    }
+}
+
+impl WriterLogger<fs::File> {
+    /// Sets whether this logger holds an exclusive advisory lock on its file, using `flock()` on
+    /// Unix and `LockFileEx()` on Windows. Locking fails immediately with an error if another
+    /// process, or another logger in this process, already holds the lock, which catches
+    /// misconfigurations such as two programs writing to the same log file. The lock is
+    /// released when this logger is dropped.
+    ///
+    /// Advisory locks only affect other programs which also lock the file - anything else can
+    /// still write to it, so this doesn't prevent all interleaving.
+    pub fn exclusive(self, exclusive: bool) -> io::Result<WriterLogger<fs::File>> {
+        {
+            let file = match self.writer.lock() {
+                Ok(file) => file,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            try!(match exclusive {
+                true => file_lock::lock(&file),
+                false => file_lock::unlock(&file),
+            });
+        }
+        return Ok(self);
+    }
+
+    /// Writes a UTF-8 byte order mark if the file is empty, as when it has just been created,
+    /// for log viewers which need one to detect the encoding, such as some on Windows. Files
+    /// which already have contents are appended to as they are, without a second mark.
+    pub fn utf8_bom(self) -> io::Result<WriterLogger<fs::File>> {
+        {
+            let mut file = match self.writer.lock() {
+                Ok(file) => file,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            if try!(file.metadata()).len() == 0 {
+                try!(file.write_all("\u{feff}".as_bytes()));
+            }
+        }
+        return Ok(self);
+    }
+}
+
+impl <T: SyncAll + Send> WriterLogger<T> {
+    /// Makes this logger flush the writer and call `sync_all()` after writing each message at
+    /// `level` or more severe, so that those messages survive a crash even if the rest of the
+    /// output is buffered. For instance, `sync_level(LogLevelFilter::Error)` syncs after every
+    /// error. This makes logging those messages much slower, so the level is best kept high.
+    ///
+    /// This defaults to `LogLevelFilter::Off`, never syncing.
+    pub fn sync_level(mut self, level: log::LogLevelFilter) -> WriterLogger<T> {
+        self.sync = match level {
+            log::LogLevelFilter::Off => None,
+            level => Some((level, sync_writer::<T>)),
+        };
+        return self;
+    }
+}
+
+impl <T: SyncAll + Send + 'static> WriterLogger<T> {
+    /// Sets how often this logger syncs its writer to disk. Files opened by fern aren't buffered
+    /// within the process, so once a message has been written it's in the OS's cache, and
+    /// survives the program crashing, but not necessarily the machine - syncing writes it
+    /// through to disk. See `Durability` for the options, which replace any `sync_level()`
+    /// set before.
+    ///
+    /// With `Durability::PeriodicFsync`, a background thread flushes and syncs the writer, and
+    /// stops once this logger is dropped.
+    pub fn durability(mut self, durability: Durability) -> WriterLogger<T> {
+        self.periodic_sync = None;
+        self = match durability {
+            Durability::None | Durability::PeriodicFsync(_) => {
+                self.sync_level(log::LogLevelFilter::Off)
+            },
+            Durability::FsyncEachError => self.sync_level(log::LogLevelFilter::Error),
+            Durability::FsyncEachLine => self.sync_level(log::LogLevelFilter::Trace),
+        };
+        if let Durability::PeriodicFsync(interval) = durability {
+            let (stop, stopped) = mpsc::channel::<()>();
+            let writer = self.writer.clone();
+            let dirty = self.dirty.clone();
+            thread::spawn(move || {
+                // Nothing is ever sent, so this only returns once the sender has been dropped
+                while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    if !dirty.swap(false, atomic::Ordering::SeqCst) {
+                        continue;
+                    }
+                    let mut writer = match writer.lock() {
+                        Ok(writer) => writer,
+                        Err(poisoned) => poisoned.into_inner(),
+                    };
+                    if let Err(e) = writer.flush().and_then(|()| writer.sync_all()) {
+                        let _ = writeln!(&mut io::stderr(), "Error syncing log file: {}", e);
+                    }
+                }
+            });
+            self.periodic_sync = Some(stop);
+        }
+        return self;
+    }
+}
+
+impl <T: io::Write + Send> WriterLogger<T> {
+    /// Locks the writer for writing a message. In non-blocking mode, this returns `None` and
+    /// counts the message as dropped if the writer is already locked.
+    fn lock_for_message(&self) -> Result<Option<sync::MutexGuard<T>>, LogError> {
+        if !self.non_blocking {
+            return Ok(Some(try!(self.lock_writer())));
+        }
+        return match self.writer.try_lock() {
+            Ok(writer) => Ok(Some(writer)),
+            Err(sync::TryLockError::WouldBlock) => {
+                self.dropped_count.fetch_add(1, atomic::Ordering::SeqCst);
+                Ok(None)
+            },
+            Err(sync::TryLockError::Poisoned(e)) => self.recover(e).map(Some),
+        };
+    }
+
+    fn lock_writer(&self) -> Result<sync::MutexGuard<T>, LogError> {
+        return match self.writer.lock() {
+            Ok(writer) => Ok(writer),
+            Err(poisoned) => self.recover(poisoned),
+        };
+    }
+
+    /// Returns the writer from a poisoned lock if `recover_poisoned()` is enabled, warning about
+    /// it the first time.
+    fn recover<'a>(&self, poisoned: sync::PoisonError<sync::MutexGuard<'a, T>>)
+            -> Result<sync::MutexGuard<'a, T>, LogError> {
+        if !self.recover_poisoned {
+            return Err(LogError::from(poisoned));
+        }
+        if !self.warned_poisoned.swap(true, atomic::Ordering::SeqCst) {
+            let _ = writeln!(&mut io::stderr(),
+                "Warning: a thread panicked while writing to fern output {}, which may have left \
+                a partial message. Logging to it continues.", self.label);
+        }
+        return Ok(poisoned.into_inner());
+    }
+
+    /// Flushes and syncs the writer after a message has been written, if the message's level
+    /// calls for it.
+    fn sync_after_write(&self, writer: &mut T, level: &log::LogLevel) -> io::Result<()> {
+        if let Some((sync_level, sync)) = self.sync {
+            if *level <= sync_level {
+                try!(writer.flush());
+                try!(sync(writer));
+            }
+        }
+        return Ok(());
+    }
+
+    /// Writes a message and the line separator, counting the message if the policy dropped it,
+    /// and then flushes or syncs the writer if the message calls for it.
+    fn write_message(&self, writer: &mut T, msg: &[u8], level: &log::LogLevel, target: &str)
+            -> io::Result<()> {
+        let parts = [msg, self.line_sep.as_bytes()];
+        if !try!(self.io_error_policy.write_all(writer, &parts, &self.bytes_written)) {
+            self.dropped_count.fetch_add(1, atomic::Ordering::SeqCst);
+            return Ok(());
+        }
+        if self.periodic_sync.is_some() {
+            self.dirty.store(true, atomic::Ordering::SeqCst);
+        }
+        if self.flush_targets.iter().any(|flush_target| flush_target == target) {
+            try!(writer.flush());
+        }
+        return self.sync_after_write(writer, level);
+    }
+
+    fn handle_write_result(&self, result: io::Result<()>) -> Result<(), LogError> {
+        match result {
+            Err(ref e) if self.ignore_broken_pipe && e.kind() == io::ErrorKind::BrokenPipe => {
+                self.closed.store(true, atomic::Ordering::SeqCst);
+            },
+            Err(e) => return Err(LogError::from(e)),
+            Ok(()) => {},
+        }
+        return Ok(());
+    }
+}
+
+impl <T: io::Write + Send> api::Logger for WriterLogger<T> {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, _location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        if self.closed.load(atomic::Ordering::SeqCst) {
+            return Ok(());
+        }
+        let result = {
+            let mut writer = match try!(self.lock_for_message()) {
+                Some(writer) => writer,
+                None => return Ok(()),
+            };
+            self.write_message(&mut writer, msg.as_bytes(), level, target)
+        };
+        return self.handle_write_result(result);
+    }
+
+    fn log_bytes(&self, bytes: &[u8], level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        if self.closed.load(atomic::Ordering::SeqCst) {
+            return Ok(());
+        }
+        let result = {
+            let mut writer = match try!(self.lock_for_message()) {
+                Some(writer) => writer,
+                None => return Ok(()),
+            };
+            self.write_message(&mut writer, bytes, level, location.__module_path)
+        };
+        return self.handle_write_result(result);
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        let result = try!(self.lock_writer()).flush();
+        return self.handle_write_result(result);
+    }
+
+    fn needs_flush(&self) -> bool {
+        return self.buffered;
+    }
+
+    fn describe(&self) -> String {
+        return self.label.clone();
+    }
+}
+
+impl <T: io::Write + Send> log::Log for WriterLogger<T> {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        log_with_fern_logger(self, record);
+    }
+}
+
+static NEXT_THREAD_LOCAL_WRITER_ID: atomic::AtomicUsize = atomic::AtomicUsize::new(0);
+
+/// One thread's buffered handle to the file of a `ThreadLocalWriterLogger`. It's only locked by
+/// other threads when the logger is flushed or dropped.
+type ThreadWriter = sync::Mutex<io::BufWriter<fs::File>>;
+
+thread_local! {
+    static THREAD_LOCAL_WRITERS: cell::RefCell<HashMap<usize, sync::Arc<ThreadWriter>>>
+        = cell::RefCell::new(HashMap::new());
+}
+
+/// A file logger which avoids a shared lock by giving each thread its own buffered handle to
+/// the file.
+///
+/// Each thread which logs through this logger opens the file separately, in append mode, and
+/// buffers messages in memory. Buffers are written to the file when they fill up, when the
+/// thread exits, or when `Logger::flush()` is called or the logger is dropped, which write out
+/// the buffers of every thread.
+///
+/// Ordering caveats:
+///
+/// - Messages from a single thread are always written in order.
+/// - Messages from different threads are only ordered at flush boundaries - a whole buffer
+///   from one thread is appended at once, so lines from different threads may appear in the
+///   file in a different order than they were logged.
+/// - Each line is buffered whole, so lines themselves are not split up, as long as the
+///   filesystem appends each write atomically (this is true for local files, but not
+///   necessarily for network filesystems).
+/// - Buffers of the main thread may not be written when the program exits, as thread local
+///   destructors aren't guaranteed to run for it. Call `Logger::flush()` or drop the logger
+///   before exiting.
+///
+/// Setting the buffer capacity to 0 writes each line directly, which keeps lines ordered
+/// across threads at the cost of one write per line.
+pub struct ThreadLocalWriterLogger {
+    id: usize,
+    path: path::PathBuf,
+    line_sep: String,
+    buffer_capacity: usize,
+    // Weak, so that a thread's buffer is still written and closed when the thread exits
+    writers: sync::Mutex<Vec<sync::Weak<ThreadWriter>>>,
+}
+
+impl ThreadLocalWriterLogger {
+    /// Creates a new logger appending to the given path, using `line_sep` as the separator after
+    /// each message, and buffering up to `buffer_capacity` bytes per thread.
+    ///
+    /// The file is opened once here to check that it can be, and then again for each thread
+    /// which logs through this logger.
+    pub fn new<P: AsRef<path::Path>>(path: P, line_sep: &str, buffer_capacity: usize)
+            -> io::Result<ThreadLocalWriterLogger> {
+        try!(ThreadLocalWriterLogger::open(path.as_ref()));
+        return Ok(ThreadLocalWriterLogger {
+            id: NEXT_THREAD_LOCAL_WRITER_ID.fetch_add(1, atomic::Ordering::SeqCst),
+            path: path.as_ref().to_path_buf(),
+            line_sep: line_sep.to_string(),
+            buffer_capacity: buffer_capacity,
+            writers: sync::Mutex::new(Vec::new()),
+        });
+    }
+
+    fn open(path: &path::Path) -> io::Result<fs::File> {
+        return fs::OpenOptions::new().append(true).create(true).open(path);
+    }
+
+    /// Writes out the buffers of every thread which is still running, forgetting those of
+    /// threads which have exited. Every buffer is flushed even if one fails, and the first
+    /// error is returned.
+    fn flush_all(&self) -> io::Result<()> {
+        let mut live = Vec::new();
+        {
+            let mut writers = match self.writers.lock() {
+                Ok(writers) => writers,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            writers.retain(|writer| match writer.upgrade() {
+                Some(writer) => {
+                    live.push(writer);
+                    true
+                },
+                None => false,
+            });
+        }
+        let mut result = Ok(());
+        for writer in live {
+            let mut writer = match writer.lock() {
+                Ok(writer) => writer,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            if let Err(e) = writer.flush() {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        return result;
+    }
+}
+
+impl Drop for ThreadLocalWriterLogger {
+    fn drop(&mut self) {
+        // Other threads' handles are closed when those threads exit, but written out now.
+        let _ = self.flush_all();
+        let id = self.id;
+        let _ = THREAD_LOCAL_WRITERS.try_with(|writers| {
+            writers.borrow_mut().remove(&id);
+        });
+    }
+}
+
+impl api::Logger for ThreadLocalWriterLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_bytes(msg.as_bytes(), level, location);
+    }
+
+    fn log_bytes(&self, bytes: &[u8], _level: &log::LogLevel, _location: &log::LogLocation)
+            -> Result<(), LogError> {
+        // Build the whole line first, so that it is written in one piece.
+        let mut line = Vec::with_capacity(bytes.len() + self.line_sep.len());
+        line.extend_from_slice(bytes);
+        line.extend_from_slice(self.line_sep.as_bytes());
+        return THREAD_LOCAL_WRITERS.with(|writers| {
+            let mut writers = writers.borrow_mut();
+            let writer = match writers.entry(self.id) {
+                hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                hash_map::Entry::Vacant(entry) => {
+                    let file = try!(ThreadLocalWriterLogger::open(&self.path));
+                    let writer = sync::Arc::new(sync::Mutex::new(
+                        io::BufWriter::with_capacity(self.buffer_capacity, file)));
+                    let mut registered = match self.writers.lock() {
+                        Ok(registered) => registered,
+                        Err(poisoned) => poisoned.into_inner(),
+                    };
+                    registered.push(sync::Arc::downgrade(&writer));
+                    entry.insert(writer)
+                },
+            };
+            let mut writer = match writer.lock() {
+                Ok(writer) => writer,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            try!(writer.write_all(&line));
+            return Ok(());
+        });
+    }
+
+    /// Writes any messages buffered by any thread to the file.
+    fn flush(&self) -> Result<(), LogError> {
+        return Ok(try!(self.flush_all()));
+    }
+
+    fn needs_flush(&self) -> bool {
+        return self.buffer_capacity > 0;
+    }
+
+    fn describe(&self) -> String {
+        return format!("thread local file ({})", self.path.display());
+    }
+}
+
+impl log::Log for ThreadLocalWriterLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        log_with_fern_logger(self, record);
+    }
+}
+
+/// What a `ChannelLogger` does with a message when its queue is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueueFullMode {
+    /// Block the logging thread until there is room in the queue.
+    Block,
+    /// Drop the message, and count it in `ChannelLogger::dropped_count()`.
+    Drop,
+}
+
+struct ChannelMessage {
+    // None for a message asking the background thread only to flush
+    record: Option<ChannelRecord>,
+    // Signalled once the message has been logged and the output flushed
+    done: Option<mpsc::Sender<()>>,
+}
+
+struct ChannelRecord {
+    msg: String,
+    target: String,
+    level: log::LogLevel,
+    location: log::LogLocation,
+}
+
+/// Counters shared between a `ChannelLogger` and its background thread.
+struct ChannelStats {
+    queue_len: atomic::AtomicUsize,
+    high_water_mark: atomic::AtomicUsize,
+    dropped_count: atomic::AtomicUsize,
+}
+
+/// A logger which sends messages over a bounded channel to a background thread, which then
+/// logs them to another logger. This keeps slow outputs from blocking the logging thread.
+///
+/// With `with_flush_interval()`, the background thread also flushes its output periodically.
+/// Each channel logger keeps its own timer, started when it's created, so giving each buffered
+/// output its own channel logger with a different interval keeps them from all flushing at once.
+///
+/// Messages logged by one thread reach the output in the order that thread logged them, even
+/// while other threads are logging too, as every message goes through the same queue to the one
+/// background thread. Messages from different threads are interleaved in the order they were
+/// queued. With `QueueFullMode::Drop`, dropped messages leave gaps, but the messages which are
+/// kept are still in order.
+///
+/// Dropping this logger waits for the background thread to log all queued messages, and
+/// `flush()` waits for it to log them and then flush the output.
+pub struct ChannelLogger {
+    sender: sync::Mutex<Option<mpsc::SyncSender<ChannelMessage>>>,
+    thread: Option<thread::JoinHandle<()>>,
+    stats: sync::Arc<ChannelStats>,
+    mode: QueueFullMode,
+    flush_on_error: bool,
+    needs_flush: bool,
+    description: String,
+}
+
+impl ChannelLogger {
+    /// Creates a new channel logger sending messages to `output` on a background thread.
+    /// `capacity` is the maximum number of messages which may be queued, and `mode` decides what
+    /// happens to new messages when the queue is full.
+    pub fn new(output: Box<api::Logger>, capacity: usize, mode: QueueFullMode) -> ChannelLogger {
+        return ChannelLogger::spawn(output, capacity, mode, None);
+    }
+
+    /// Like `new()`, but the background thread also flushes `output` every `interval`, if it
+    /// needs flushing, and once more after logging the last queued message when this logger is
+    /// dropped. Flushing happens between messages, so a flush may be late while a slow message
+    /// is being logged.
+    pub fn with_flush_interval(output: Box<api::Logger>, capacity: usize, mode: QueueFullMode,
+                               interval: Duration) -> ChannelLogger {
+        return ChannelLogger::spawn(output, capacity, mode, Some(interval));
+    }
+
+    fn spawn(output: Box<api::Logger>, capacity: usize, mode: QueueFullMode,
+             flush_interval: Option<Duration>) -> ChannelLogger {
+        let (sender, receiver) = mpsc::sync_channel::<ChannelMessage>(capacity);
+        let stats = sync::Arc::new(ChannelStats {
+            queue_len: atomic::AtomicUsize::new(0),
+            high_water_mark: atomic::AtomicUsize::new(0),
+            dropped_count: atomic::AtomicUsize::new(0),
+        });
+        let description = format!("channel ({})", output.describe());
+        let needs_flush = output.needs_flush();
+        let thread_stats = stats.clone();
+        let thread = thread::spawn(move || {
+            let mut next_flush = flush_interval.map(|interval| Instant::now() + interval);
+            loop {
+                let message = match (next_flush, flush_interval) {
+                    (Some(deadline), Some(interval)) => {
+                        let now = Instant::now();
+                        if now >= deadline {
+                            flush_channel_output(&*output);
+                            next_flush = Some(now + interval);
+                            continue;
+                        }
+                        match receiver.recv_timeout(deadline - now) {
+                            Ok(message) => message,
+                            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                        }
+                    },
+                    _ => match receiver.recv() {
+                        Ok(message) => message,
+                        Err(mpsc::RecvError) => break,
+                    },
+                };
+                if let Some(record) = message.record {
+                    thread_stats.queue_len.fetch_sub(1, atomic::Ordering::SeqCst);
+                    if let Err(e) = output.log_with_target(&record.msg, &record.level,
+                                                           &record.location, &record.target) {
+                        let _ = write!(&mut io::stderr(),
+                            "Error logging {{level: {}, location: {}, arguments: {}}}: {}",
+                            record.level, config::short_location(&record.location), record.msg,
+                            e);
+                    }
+                }
+                if let Some(done) = message.done {
+                    flush_channel_output(&*output);
+                    let _ = done.send(());
+                }
+            }
+            if flush_interval.is_some() {
+                flush_channel_output(&*output);
+            }
+        });
+        return ChannelLogger {
+            sender: sync::Mutex::new(Some(sender)),
+            thread: Some(thread),
+            stats: stats,
+            mode: mode,
+            flush_on_error: false,
+            needs_flush: needs_flush,
+            description: description,
+        };
+    }
+
+    /// Makes logging an `Error` message wait until the background thread has logged it, and
+    /// everything queued before it, and then flushed the output, so that errors are written out
+    /// even if the process crashes right after. Other messages are still logged in the
+    /// background. Errors wait for room in the queue even with `QueueFullMode::Drop`.
+    pub fn flush_on_error(mut self, flush_on_error: bool) -> ChannelLogger {
+        self.flush_on_error = flush_on_error;
+        return self;
+    }
+
+    /// Returns the number of messages currently waiting in the queue. With
+    /// `QueueFullMode::Block`, this includes messages from threads waiting for room in the queue.
+    pub fn queue_len(&self) -> usize {
+        return self.stats.queue_len.load(atomic::Ordering::SeqCst);
+    }
+
+    /// Returns the largest number of messages which have been waiting in the queue at once.
+    pub fn high_water_mark(&self) -> usize {
+        return self.stats.high_water_mark.load(atomic::Ordering::SeqCst);
+    }
+
+    /// Returns the number of messages which have been dropped because the queue was full. This
+    /// is always 0 with `QueueFullMode::Block`.
+    pub fn dropped_count(&self) -> usize {
+        return self.stats.dropped_count.load(atomic::Ordering::SeqCst);
+    }
+
+    fn update_high_water_mark(&self, len: usize) {
+        let mut high = self.stats.high_water_mark.load(atomic::Ordering::SeqCst);
+        while len > high {
+            match self.stats.high_water_mark.compare_exchange(high, len,
+                    atomic::Ordering::SeqCst, atomic::Ordering::SeqCst) {
+                Ok(_) => break,
+                Err(current) => high = current,
+            }
+        }
+    }
+}
+
+/// Flushes the output of a `ChannelLogger` on its background thread, where errors can only be
+/// written to stderr.
+fn flush_channel_output(output: &api::Logger) {
+    if output.needs_flush() {
+        if let Err(e) = output.flush() {
+            let _ = writeln!(&mut io::stderr(), "Error flushing {}: {}", output.describe(), e);
+        }
+    }
+}
+
+impl Drop for ChannelLogger {
+    fn drop(&mut self) {
+        // Closing the channel lets the background thread finish once it has drained the queue.
+        if let Ok(mut sender) = self.sender.lock() {
+            sender.take();
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl api::Logger for ChannelLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        let (done, wait) = match self.flush_on_error && *level == log::LogLevel::Error {
+            true => {
+                let (done, wait) = mpsc::channel();
+                (Some(done), Some(wait))
+            },
+            false => (None, None),
+        };
+        let message = ChannelMessage {
+            record: Some(ChannelRecord {
+                msg: msg.to_string(),
+                target: target.to_string(),
+                level: *level,
+                location: *location,
+            }),
+            done: done,
+        };
+        let guard = try!(self.sender.lock());
+        let sender = match *guard {
+            Some(ref sender) => sender,
+            None => return Ok(()),
+        };
+        // Count the message before sending, so the background thread never sees a negative
+        // queue length.
+        let len = self.stats.queue_len.fetch_add(1, atomic::Ordering::SeqCst) + 1;
+        let result = match (self.mode, wait.is_some()) {
+            (QueueFullMode::Block, _) | (_, true) => sender.send(message).map_err(|_| ()),
+            (QueueFullMode::Drop, false) => match sender.try_send(message) {
+                Err(mpsc::TrySendError::Full(_)) => {
+                    self.stats.dropped_count.fetch_add(1, atomic::Ordering::SeqCst);
+                    Err(())
+                },
+                Err(mpsc::TrySendError::Disconnected(_)) => Err(()),
+                Ok(()) => Ok(()),
+            },
+        };
+        match result {
+            Ok(()) => self.update_high_water_mark(len),
+            Err(()) => {
+                self.stats.queue_len.fetch_sub(1, atomic::Ordering::SeqCst);
+            },
+        }
+        drop(guard);
+        if let (Ok(()), Some(wait)) = (result, wait) {
+            // An error here means the background thread panicked, which it has reported already.
+            let _ = wait.recv();
+        }
+        return Ok(());
+    }
+
+    /// Waits for the background thread to log every message queued so far, and then flush the
+    /// output. Errors flushing the output are written to stderr by the background thread.
+    fn flush(&self) -> Result<(), LogError> {
+        let (done, wait) = mpsc::channel();
+        {
+            let guard = try!(self.sender.lock());
+            let sender = match *guard {
+                Some(ref sender) => sender,
+                None => return Ok(()),
+            };
+            if sender.send(ChannelMessage { record: None, done: Some(done) }).is_err() {
+                return Ok(());
+            }
+        }
+        // An error here means the background thread panicked, which it has reported already.
+        let _ = wait.recv();
+        return Ok(());
+    }
+
+    fn needs_flush(&self) -> bool {
+        return self.needs_flush;
+    }
+
+    fn describe(&self) -> String {
+        return self.description.clone();
+    }
+}
+
+impl log::Log for ChannelLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        log_with_fern_logger(self, record);
+    }
+}
+
+/// A logger which passes on at most one message from each call site, or one each time period when
+/// throttled, and drops any repeats.
+///
+/// Call sites are identified by the file and line in the message's `LogLocation`, so messages
+/// logged in a loop by the same statement share a key.
+pub struct OnceLogger {
+    output: Box<api::Logger>,
+    period: Option<Duration>,
+    last_logged: sync::Mutex<HashMap<(&'static str, u32), Instant>>,
+}
+
+impl OnceLogger {
+    /// Creates a logger passing on only the first message from each call site to `output`.
+    pub fn new(output: Box<api::Logger>) -> OnceLogger {
+        return OnceLogger {
+            output: output,
+            period: None,
+            last_logged: sync::Mutex::new(HashMap::new()),
+        };
+    }
+
+    /// Creates a logger passing on at most one message from each call site per `period` to
+    /// `output`.
+    pub fn throttled(output: Box<api::Logger>, period: Duration) -> OnceLogger {
+        return OnceLogger {
+            output: output,
+            period: Some(period),
+            last_logged: sync::Mutex::new(HashMap::new()),
+        };
+    }
+}
+
+impl api::Logger for OnceLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        let key = (location.__file, location.__line);
+        let now = Instant::now();
+        {
+            let mut last_logged = try!(self.last_logged.lock());
+            let repeat = match (last_logged.get(&key), self.period) {
+                (None, _) => false,
+                (Some(_), None) => true,
+                (Some(last), Some(period)) => now.duration_since(*last) < period,
+            };
+            if repeat {
+                return Ok(());
+            }
+            last_logged.insert(key, now);
+        }
+        return self.output.log_with_target(msg, level, location, target);
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        return self.output.flush();
+    }
+
+    fn needs_flush(&self) -> bool {
+        return self.output.needs_flush();
+    }
+
+    fn describe(&self) -> String {
+        return match self.period {
+            Some(period) => format!("throttled ({:?}, {})", period, self.output.describe()),
+            None => format!("once ({})", self.output.describe()),
+        };
+    }
+}
+
+impl log::Log for OnceLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        log_with_fern_logger(self, record);
+    }
+}
+
+/// A logger which passes on the first few messages from each call site in full, and then only
+/// a sample of the rest, such as the first 10 and then every 100th after that. This shows what
+/// a noisy statement logs without flooding the output.
+///
+/// Call sites are identified by the file and line in the message's `LogLocation`, as with
+/// `OnceLogger`. Sampling is deterministic: with a head of 10 and a rate of 100, messages 1 to
+/// 10 from a call site pass, then messages 110, 210, and so on.
+pub struct SamplingLogger {
+    output: Box<api::Logger>,
+    head: usize,
+    rate: usize,
+    counts: sync::Mutex<HashMap<(&'static str, u32), usize>>,
+}
+
+impl SamplingLogger {
+    /// Creates a logger passing on the first `head` messages from each call site to `output`,
+    /// followed by one in every `rate` messages. A `rate` of 1 passes every message, and one of
+    /// 0 none after the first `head`.
+    pub fn new(output: Box<api::Logger>, head: usize, rate: usize) -> SamplingLogger {
+        return SamplingLogger {
+            output: output,
+            head: head,
+            rate: rate,
+            counts: sync::Mutex::new(HashMap::new()),
+        };
+    }
+}
+
+impl api::Logger for SamplingLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        let index = {
+            let mut counts = try!(self.counts.lock());
+            let count = counts.entry((location.__file, location.__line)).or_insert(0);
+            *count += 1;
+            *count - 1
+        };
+        let passes = match index.checked_sub(self.head) {
+            None => true,
+            Some(_) if self.rate == 0 => false,
+            Some(after_head) => (after_head + 1) % self.rate == 0,
+        };
+        if !passes {
+            return Ok(());
+        }
+        return self.output.log_with_target(msg, level, location, target);
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        return self.output.flush();
+    }
+
+    fn needs_flush(&self) -> bool {
+        return self.output.needs_flush();
+    }
+
+    fn describe(&self) -> String {
+        return format!("sampled (first {}, then 1 in {}, {})", self.head, self.rate,
+                       self.output.describe());
+    }
+}
+
+impl log::Log for SamplingLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        log_with_fern_logger(self, record);
+    }
+}
+
+/// A logger which counts the messages at one level, such as `Trace`, instead of passing them on,
+/// and passes on a summary of how many there were instead, such as
+/// `1234 TRACE messages in the last 60.0s`. This keeps the volume of a noisy level visible
+/// without flooding the output. Messages at other levels are passed on as usual.
+///
+/// Summaries are logged at `Info` from the `fern::summary` module and target, with the count
+/// since the previous one. As this logger has no thread of its own, a summary is passed on
+/// with the first message logged once the interval has passed - so the time given may be
+/// longer than the interval - and by `flush()` and when this logger is dropped. No summary is
+/// logged when no messages were counted.
+pub struct SummarizingLogger {
+    output: Box<api::Logger>,
+    level: log::LogLevel,
+    interval: Duration,
+    // Messages counted since the summary window started
+    window: sync::Mutex<(usize, Instant)>,
+}
+
+impl SummarizingLogger {
+    /// Creates a logger counting messages at `level`, and passing on a summary of them at most
+    /// once every `interval`, along with every other message, to `output`.
+    pub fn new(output: Box<api::Logger>, level: log::LogLevel, interval: Duration)
+            -> SummarizingLogger {
+        return SummarizingLogger {
+            output: output,
+            level: level,
+            interval: interval,
+            window: sync::Mutex::new((0, Instant::now())),
+        };
+    }
+
+    /// Passes on a summary of the messages counted so far, if there are any, and if the
+    /// interval has passed or `force` is true.
+    fn summarize(&self, force: bool) -> Result<(), LogError> {
+        static SUMMARY_LOCATION: log::LogLocation = log::LogLocation {
+            __module_path: SUMMARY_TARGET,
+            __file: file!(),
+            __line: line!(),
+        };
+        let (count, elapsed) = {
+            let mut window = match self.window.lock() {
+                Ok(window) => window,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            let elapsed = window.1.elapsed();
+            if !force && elapsed < self.interval {
+                return Ok(());
+            }
+            let count = window.0;
+            *window = (0, Instant::now());
+            (count, elapsed)
+        };
+        if count == 0 {
+            return Ok(());
+        }
+        let secs = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1_000_000_000.0;
+        let summary = format!("{} {} messages in the last {:.1}s", count, self.level, secs);
+        return self.output.log_with_target(&summary, &log::LogLevel::Info, &SUMMARY_LOCATION,
+                                           SUMMARY_TARGET);
+    }
+}
+
+impl api::Logger for SummarizingLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        if *level == self.level {
+            match self.window.lock() {
+                Ok(mut window) => window.0 += 1,
+                Err(poisoned) => poisoned.into_inner().0 += 1,
+            }
+            return self.summarize(false);
+        }
+        try!(self.summarize(false));
+        return self.output.log_with_target(msg, level, location, target);
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        try!(self.summarize(true));
+        return self.output.flush();
+    }
+
+    fn describe(&self) -> String {
+        return format!("summarized ({} every {:?}, {})", self.level, self.interval,
+                       self.output.describe());
+    }
+}
+
+impl Drop for SummarizingLogger {
+    fn drop(&mut self) {
+        let _ = self.summarize(true);
+    }
+}
+
+impl log::Log for SummarizingLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        log_with_fern_logger(self, record);
+    }
+}
+
+/// Most distinct messages a `StormLogger` passes on during one storm. Once there have been this
+/// many, every other error is suppressed until the storm ends.
+const MAX_STORM_MESSAGES: usize = 1000;
+
+/// Error messages counted by a `StormLogger`.
+struct StormState {
+    window_start: Instant,
+    // Errors logged since the window started
+    count: usize,
+    storm: bool,
+    // Messages passed on during the current storm, and the repeats suppressed since the last
+    // notice
+    seen: HashSet<String>,
+    suppressed: usize,
+}
+
+/// A logger which detects log storms, such as a failing subsystem logging thousands of errors a
+/// second, and collapses them. While errors arrive at a normal rate, every message is passed on.
+/// Once more than the threshold of `Error` messages are logged within one window, a storm
+/// begins: a `log storm detected` error is passed on, and from then on each distinct error is
+/// only passed on the first time, with its repeats suppressed and counted.
+///
+/// Notices are logged from the `fern::storm` module and target:
+///
+/// ```text
+/// log storm detected: more than 100 ERROR messages in 1.0s, collapsing repeats
+/// log storm continuing: 5432 repeated ERROR messages suppressed
+/// log storm ended: 12 repeated ERROR messages suppressed
+/// ```
+///
+/// The storm continues as long as each window has more errors than the threshold, with a count
+/// of the suppressed repeats passed on at the end of each window, and ends with the first one
+/// that doesn't. As with `SummarizingLogger`, windows are only checked when messages are
+/// logged, and by `flush()`. Messages less severe than `Error` are always passed on.
+pub struct StormLogger {
+    output: Box<api::Logger>,
+    threshold: usize,
+    window: Duration,
+    state: sync::Mutex<StormState>,
+}
+
+impl StormLogger {
+    /// Creates a logger passing messages on to `output`, and collapsing repeated errors once
+    /// there are more than `threshold` of them within `window`.
+    pub fn new(output: Box<api::Logger>, threshold: usize, window: Duration) -> StormLogger {
+        return StormLogger {
+            output: output,
+            threshold: threshold,
+            window: window,
+            state: sync::Mutex::new(StormState {
+                window_start: Instant::now(),
+                count: 0,
+                storm: false,
+                seen: HashSet::new(),
+                suppressed: 0,
+            }),
+        };
+    }
+
+    /// Returns whether a storm is going on.
+    pub fn in_storm(&self) -> bool {
+        return self.lock_state().storm;
+    }
+
+    fn lock_state(&self) -> sync::MutexGuard<StormState> {
+        return match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+    }
+
+    /// Starts a new window if the current one has passed, returning the notice to pass on if a
+    /// storm continued or ended with it.
+    fn end_window(&self, state: &mut StormState) -> Option<(log::LogLevel, String)> {
+        if state.window_start.elapsed() < self.window {
+            return None;
+        }
+        state.window_start = Instant::now();
+        let count = state.count;
+        state.count = 0;
+        if !state.storm {
+            return None;
+        }
+        let suppressed = state.suppressed;
+        state.suppressed = 0;
+        if count > self.threshold {
+            return match suppressed {
+                0 => None,
+                _ => Some((log::LogLevel::Warn, format!(
+                    "log storm continuing: {} repeated ERROR messages suppressed", suppressed))),
+            };
+        }
+        state.storm = false;
+        state.seen.clear();
+        return Some((log::LogLevel::Info, format!(
+            "log storm ended: {} repeated ERROR messages suppressed", suppressed)));
+    }
+
+    fn log_notice(&self, notice: Option<(log::LogLevel, String)>) -> Result<(), LogError> {
+        static STORM_LOCATION: log::LogLocation = log::LogLocation {
+            __module_path: STORM_TARGET,
+            __file: file!(),
+            __line: line!(),
+        };
+        return match notice {
+            Some((level, msg)) => self.output.log_with_target(&msg, &level, &STORM_LOCATION,
+                                                              STORM_TARGET),
+            None => Ok(()),
+        };
+    }
+}
+
+impl api::Logger for StormLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        let (notice, detected, passes) = {
+            let mut state = self.lock_state();
+            let notice = self.end_window(&mut state);
+            match *level {
+                log::LogLevel::Error => {
+                    state.count += 1;
+                    let detected = !state.storm && state.count > self.threshold;
+                    if detected {
+                        state.storm = true;
+                    }
+                    let passes = !state.storm || (state.seen.len() < MAX_STORM_MESSAGES
+                                                  && state.seen.insert(msg.to_string()));
+                    if !passes {
+                        state.suppressed += 1;
+                    }
+                    (notice, detected, passes)
+                },
+                _ => (notice, false, true),
+            }
+        };
+        try!(self.log_notice(notice));
+        if detected {
+            let secs = self.window.as_secs() as f64
+                       + self.window.subsec_nanos() as f64 / 1_000_000_000.0;
+            try!(self.log_notice(Some((log::LogLevel::Error, format!(
+                "log storm detected: more than {} ERROR messages in {:.1}s, collapsing repeats",
+                self.threshold, secs)))));
+        }
+        if !passes {
+            return Ok(());
+        }
+        return self.output.log_with_target(msg, level, location, target);
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        let notice = self.end_window(&mut self.lock_state());
+        try!(self.log_notice(notice));
+        return self.output.flush();
+    }
+
+    fn describe(&self) -> String {
+        return format!("storm (more than {} errors in {:?}, {})", self.threshold, self.window,
+                       self.output.describe());
+    }
+}
+
+impl log::Log for StormLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        log_with_fern_logger(self, record);
+    }
+}
+
+/// A logger for tests which sends messages to another logger, usually one capturing them for
+/// the test to check, and optionally mirrors them to stdout as well.
+///
+/// Mirrored messages are written with `println!()`, so the test harness captures them like any
+/// other test output: they show up for failed tests, or live with `cargo test -- --nocapture`.
+/// Mirroring is off by default.
+pub struct TestTeeLogger {
+    output: Box<api::Logger>,
+    mirror: bool,
+}
+
+impl TestTeeLogger {
+    /// Creates a new logger sending messages to `output`, with mirroring off.
+    pub fn new(output: Box<api::Logger>) -> TestTeeLogger {
+        return TestTeeLogger {
+            output: output,
+            mirror: false,
+        };
+    }
+
+    /// Sets whether messages are also printed to stdout.
+    pub fn mirror_to_stdout(mut self, mirror: bool) -> TestTeeLogger {
+        self.mirror = mirror;
+        return self;
+    }
+}
+
+impl api::Logger for TestTeeLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        if self.mirror {
+            println!("{}", msg);
+        }
+        return self.output.log_with_target(msg, level, location, target);
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        return self.output.flush();
+    }
+
+    fn needs_flush(&self) -> bool {
+        return self.output.needs_flush();
+    }
+
+    fn describe(&self) -> String {
+        return match self.mirror {
+            true => format!("test tee (stdout, {})", self.output.describe()),
+            false => self.output.describe(),
+        };
+    }
+}
+
+impl log::Log for TestTeeLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        log_with_fern_logger(self, record);
+    }
+}
+
+/// A logger keeping every message in memory, for tests to check what was logged.
+///
+/// Clones share the same messages, so one clone can be given to a `DispatchLogger` or set as the
+/// global logger while the test keeps another to make assertions with:
+///
+/// ```rust
+/// # extern crate fern;
+/// # extern crate log;
+/// # fn main() {
+/// let memory = fern::MemoryLogger::new();
+/// let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+///     vec![fern::OutputConfig::custom(Box::new(memory.clone()))],
+///     log::LogLevelFilter::Info, vec![]).unwrap();
+/// // ... run the code under test with `logger` ...
+/// # let _ = logger;
+/// memory.assert_not_contains("connection refused");
+/// assert_eq!(memory.count_at_level(log::LogLevel::Error), 0);
+/// # }
+/// ```
+///
+/// The assertions panic with every captured message when they fail, to show what was logged
+/// instead.
+#[derive(Clone)]
+pub struct MemoryLogger {
+    records: sync::Arc<sync::Mutex<Vec<(log::LogLevel, String)>>>,
+}
+
+impl MemoryLogger {
+    /// Creates a new logger with no messages.
+    pub fn new() -> MemoryLogger {
+        return MemoryLogger {
+            records: sync::Arc::new(sync::Mutex::new(Vec::new())),
+        };
+    }
+
+    fn lock_records(&self) -> sync::MutexGuard<Vec<(log::LogLevel, String)>> {
+        return match self.records.lock() {
+            Ok(records) => records,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+    }
+
+    /// Returns the level and formatted text of every message logged so far, oldest first.
+    pub fn records(&self) -> Vec<(log::LogLevel, String)> {
+        return self.lock_records().clone();
+    }
+
+    /// Returns the formatted text of every message logged so far, oldest first.
+    pub fn lines(&self) -> Vec<String> {
+        return self.lock_records().iter().map(|&(_, ref msg)| msg.clone()).collect();
+    }
+
+    /// Removes every message logged so far.
+    pub fn clear(&self) {
+        self.lock_records().clear();
+    }
+
+    /// Returns the number of messages logged at exactly `level`.
+    pub fn count_at_level(&self, level: log::LogLevel) -> usize {
+        return self.lock_records().iter().filter(|&&(record_level, _)| record_level == level)
+            .count();
+    }
+
+    /// Panics unless a message containing `text` was logged at `level`.
+    pub fn assert_contains(&self, level: log::LogLevel, text: &str) {
+        let records = self.lock_records();
+        if !records.iter().any(|&(record_level, ref msg)| record_level == level
+                                                          && msg.contains(text)) {
+            panic!("expected a {} message containing {:?}, but captured:\n{}",
+                   level, text, describe_records(&records));
+        }
+    }
+
+    /// Panics if a message containing `text` was logged at any level.
+    pub fn assert_not_contains(&self, text: &str) {
+        let records = self.lock_records();
+        if records.iter().any(|&(_, ref msg)| msg.contains(text)) {
+            panic!("expected no message containing {:?}, but captured:\n{}",
+                   text, describe_records(&records));
+        }
+    }
+}
+
+/// Lists captured messages one per line, for the panic messages of `MemoryLogger`.
+fn describe_records(records: &[(log::LogLevel, String)]) -> String {
+    if records.is_empty() {
+        return "    (no messages)".to_string();
+    }
+    let lines: Vec<String> = records.iter().map(|&(level, ref msg)| {
+        format!("    {:<5} {}", level, msg)
+    }).collect();
+    return lines.join("\n");
+}
+
+impl api::Logger for MemoryLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, _location: &log::LogLocation)
+            -> Result<(), LogError> {
+        self.lock_records().push((*level, msg.to_string()));
+        return Ok(());
+    }
+
+    fn needs_flush(&self) -> bool {
+        return false;
+    }
+
+    fn describe(&self) -> String {
+        return "memory".to_string();
+    }
+}
+
+impl log::Log for MemoryLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        log_with_fern_logger(self, record);
+    }
+}
+
+/// A logger keeping the most recent messages in memory, up to a fixed number, for writing out
+/// after a crash. Older messages are discarded as new ones arrive.
+///
+/// Clones share the same messages. To see what led up to a crash, give this its own
+/// `DispatchLogger` at a more verbose level than the other outputs, and call `dump_on_panic()`
+/// to have the messages written to a file when the program panics.
+#[derive(Clone)]
+pub struct RingBufferLogger {
+    capacity: usize,
+    records: sync::Arc<sync::Mutex<VecDeque<String>>>,
+}
+
+impl RingBufferLogger {
+    /// Creates a new logger keeping the last `capacity` messages.
+    pub fn new(capacity: usize) -> RingBufferLogger {
+        return RingBufferLogger {
+            capacity: capacity,
+            records: sync::Arc::new(sync::Mutex::new(VecDeque::with_capacity(capacity))),
+        };
+    }
+
+    fn lock_records(&self) -> sync::MutexGuard<VecDeque<String>> {
+        return match self.records.lock() {
+            Ok(records) => records,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+    }
+
+    /// Returns the messages currently kept, oldest first.
+    pub fn records(&self) -> Vec<String> {
+        return self.lock_records().iter().cloned().collect();
+    }
+
+    /// Writes the messages currently kept to `writer`, oldest first, each followed by `\n`.
+    pub fn dump_to(&self, writer: &mut io::Write) -> io::Result<()> {
+        for record in self.lock_records().iter() {
+            try!(writer.write_all(record.as_bytes()));
+            try!(writer.write_all(b"\n"));
+        }
+        return writer.flush();
+    }
+
+    /// Installs a panic hook which appends the messages kept by this logger to the file at
+    /// `path` whenever a thread panics, and then runs the previously installed hook, which by
+    /// default prints the panic message. Errors writing the file are ignored.
+    pub fn dump_on_panic<P: AsRef<path::Path>>(&self, path: P) {
+        let ring = self.clone();
+        let path = path.as_ref().to_path_buf();
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if let Ok(mut file) = append_options(None).open(&path) {
+                let _ = ring.dump_to(&mut file);
+            }
+            previous(info);
+        }));
+    }
+
+    /// Installs a panic hook which writes a crash report to a new file in `dir` whenever a
+    /// thread panics, and then runs the previously installed hook. Each report is named from
+    /// the local time, as in `crash-2016-03-01T12-00-00.log`, and holds the messages kept by
+    /// this logger, oldest first, followed by the panic, as in:
+    ///
+    /// ```text
+    /// [INFO] connecting to the database
+    /// [ERROR] connection refused
+    /// --- thread 'main' panicked at src/main.rs:42:5:
+    /// no database
+    /// ```
+    ///
+    /// Reports are never overwritten, see `WriterLogger::with_timestamped_file()`. Errors
+    /// writing them are ignored.
+    pub fn report_crashes<P: AsRef<path::Path>>(&self, dir: P) {
+        let ring = self.clone();
+        let dir = dir.as_ref().to_path_buf();
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if let Ok((mut file, _)) = create_timestamped_file(&dir, "crash-", ".log") {
+                let thread = thread::current();
+                let _ = ring.dump_to(&mut file).and_then(|()| {
+                    writeln!(file, "--- thread '{}' {}", thread.name().unwrap_or("<unnamed>"),
+                             info)
+                });
+            }
+            previous(info);
+        }));
+    }
+}
+
+impl api::Logger for RingBufferLogger {
+    fn log(&self, msg: &str, _level: &log::LogLevel, _location: &log::LogLocation)
+            -> Result<(), LogError> {
+        if self.capacity == 0 {
+            return Ok(());
+        }
+        let mut records = self.lock_records();
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(msg.to_string());
+        return Ok(());
+    }
+
+    fn needs_flush(&self) -> bool {
+        return false;
+    }
+
+    fn describe(&self) -> String {
+        return format!("ring buffer ({})", self.capacity);
+    }
+}
+
+impl log::Log for RingBufferLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        log_with_fern_logger(self, record);
+    }
+}
+
+/// A logger keeping the most recent bytes logged, up to a fixed number, for dumping on demand,
+/// such as over a serial link from a device without a filesystem. Each message is stored
+/// followed by its line separator, and once the buffer is full, the oldest bytes are
+/// overwritten, which may leave the oldest message cut off at the start.
+///
+/// Unlike `RingBufferLogger`, which keeps a number of messages however long they are, this
+/// never holds more than its capacity, which is allocated up front.
+///
+/// Clones share the same buffer.
+#[derive(Clone)]
+pub struct MemoryRingLogger {
+    capacity: usize,
+    line_sep: String,
+    bytes: sync::Arc<sync::Mutex<VecDeque<u8>>>,
+}
+
+impl MemoryRingLogger {
+    /// Creates a new logger keeping the last `capacity` bytes, with `line_sep` as the separator
+    /// after each message.
+    pub fn new(capacity: usize, line_sep: &str) -> MemoryRingLogger {
+        return MemoryRingLogger {
+            capacity: capacity,
+            line_sep: line_sep.to_string(),
+            bytes: sync::Arc::new(sync::Mutex::new(VecDeque::with_capacity(capacity))),
+        };
+    }
+
+    fn lock_bytes(&self) -> sync::MutexGuard<VecDeque<u8>> {
+        return match self.bytes.lock() {
+            Ok(bytes) => bytes,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+    }
+
+    /// Returns the bytes currently kept, oldest first.
+    pub fn snapshot(&self) -> Vec<u8> {
+        return self.lock_bytes().iter().cloned().collect();
+    }
+
+    /// Returns the number of bytes currently kept, which is at most the capacity.
+    pub fn len(&self) -> usize {
+        return self.lock_bytes().len();
+    }
+
+    /// Removes every byte kept so far, such as once they've been dumped.
+    pub fn clear(&self) {
+        self.lock_bytes().clear();
+    }
+
+    fn push(&self, parts: &[&[u8]]) {
+        let mut bytes = self.lock_bytes();
+        for part in parts {
+            // Only the end of a part longer than the whole buffer can be kept
+            let part = &part[part.len().saturating_sub(self.capacity)..];
+            let overflow = (bytes.len() + part.len()).saturating_sub(self.capacity);
+            bytes.drain(..overflow);
+            bytes.extend(part.iter());
+        }
+    }
+}
+
+impl api::Logger for MemoryRingLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_bytes(msg.as_bytes(), level, location);
+    }
+
+    fn log_bytes(&self, bytes: &[u8], _level: &log::LogLevel, _location: &log::LogLocation)
+            -> Result<(), LogError> {
+        self.push(&[bytes, self.line_sep.as_bytes()]);
+        return Ok(());
+    }
+
+    fn needs_flush(&self) -> bool {
+        return false;
+    }
+
+    fn describe(&self) -> String {
+        return format!("memory ring ({} bytes)", self.capacity);
+    }
+}
+
+impl log::Log for MemoryRingLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        log_with_fern_logger(self, record);
+    }
+}
+
+/// A logger publishing every message to any number of subscribers, such as the connections of
+/// a live log viewer:
+///
+/// ```rust
+/// # extern crate fern;
+/// # extern crate log;
+/// # fn main() {
+/// let broadcast = fern::BroadcastLogger::new(100);
+/// let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+///     vec![fern::OutputConfig::custom(Box::new(broadcast.clone()))],
+///     log::LogLevelFilter::Info, vec![]).unwrap();
+/// let messages = broadcast.subscribe();
+/// // ... for each message in `messages`, send it to the viewer ...
+/// # let _ = (logger, messages);
+/// # }
+/// ```
+///
+/// Each subscriber has its own bounded queue. When a subscriber's queue is full, messages for
+/// it are dropped, and counted in `dropped_count()`, so a slow subscriber never blocks logging
+/// or holds up the others. Subscribers are removed once their receiver is dropped.
+///
+/// Clones share the same subscribers.
+#[derive(Clone)]
+pub struct BroadcastLogger {
+    capacity: usize,
+    subscribers: sync::Arc<sync::Mutex<Vec<mpsc::SyncSender<String>>>>,
+    dropped_count: sync::Arc<atomic::AtomicUsize>,
+}
+
+impl BroadcastLogger {
+    /// Creates a logger with no subscribers, queueing up to `capacity` messages for each
+    /// subscriber it later has.
+    pub fn new(capacity: usize) -> BroadcastLogger {
+        return BroadcastLogger {
+            capacity: capacity,
+            subscribers: sync::Arc::new(sync::Mutex::new(Vec::new())),
+            dropped_count: sync::Arc::new(atomic::AtomicUsize::new(0)),
+        };
+    }
+
+    fn lock_subscribers(&self) -> sync::MutexGuard<Vec<mpsc::SyncSender<String>>> {
+        return match self.subscribers.lock() {
+            Ok(subscribers) => subscribers,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+    }
+
+    /// Adds a subscriber, which receives every message logged from now on.
+    pub fn subscribe(&self) -> mpsc::Receiver<String> {
+        let (sender, receiver) = mpsc::sync_channel(self.capacity);
+        self.lock_subscribers().push(sender);
+        return receiver;
+    }
+
+    /// Returns the number of subscribers whose receiver hasn't been dropped yet, as of the last
+    /// message logged.
+    pub fn subscriber_count(&self) -> usize {
+        return self.lock_subscribers().len();
+    }
+
+    /// Returns how many messages have been dropped because a subscriber's queue was full,
+    /// counting each subscriber missing a message separately.
+    pub fn dropped_count(&self) -> usize {
+        return self.dropped_count.load(atomic::Ordering::SeqCst);
+    }
+}
+
+impl api::Logger for BroadcastLogger {
+    fn log(&self, msg: &str, _level: &log::LogLevel, _location: &log::LogLocation)
+            -> Result<(), LogError> {
+        self.lock_subscribers().retain(|subscriber| {
+            match subscriber.try_send(msg.to_string()) {
+                Ok(()) => true,
+                Err(mpsc::TrySendError::Full(_)) => {
+                    self.dropped_count.fetch_add(1, atomic::Ordering::SeqCst);
+                    true
+                },
+                Err(mpsc::TrySendError::Disconnected(_)) => false,
+            }
+        });
+        return Ok(());
+    }
+
+    fn needs_flush(&self) -> bool {
+        return false;
+    }
+
+    fn describe(&self) -> String {
+        return format!("broadcast ({} subscribers)", self.subscriber_count());
+    }
+}
+
+impl log::Log for BroadcastLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        log_with_fern_logger(self, record);
+    }
+}
+
+/// Identifies a handler registered with a `CompositeLogger`, for unregistering it later.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HandlerId(usize);
+
+/// A logger forwarding every message to a set of handlers, which can be registered and
+/// unregistered while it's in use.
+///
+/// The `log` crate only allows setting the global logger once, which is awkward when several
+/// independent parts of a program, such as plugins, each want to configure their own logging.
+/// Instead, a `CompositeLogger` can be set as the global logger early on, with each part
+/// registering its handlers whenever it's ready:
+///
+/// ```rust
+/// # extern crate log;
+/// # extern crate fern;
+/// use std::sync::Arc;
+///
+/// # fn main() {
+/// let composite = Arc::new(fern::CompositeLogger::new());
+/// fern::init_global_logger(composite.clone(), log::LogLevelFilter::Trace).unwrap();
+///
+/// // Later, once a plugin has loaded
+/// let stderr = fern::WriterLogger::<std::io::Stderr>::with_stderr();
+/// let handler = composite.register(Box::new(stderr));
+/// // And when it's unloaded again
+/// composite.unregister(handler);
+/// # }
+/// ```
+///
+/// Handlers receive messages exactly as they were logged. As with `DispatchLogger`, if a handler
+/// fails the message is still sent to the rest, and the first error is returned afterwards.
+pub struct CompositeLogger {
+    handlers: sync::RwLock<Vec<(HandlerId, Box<api::Logger>)>>,
+    next_id: atomic::AtomicUsize,
+}
+
+impl CompositeLogger {
+    /// Creates a new logger without any handlers.
+    pub fn new() -> CompositeLogger {
+        return CompositeLogger {
+            handlers: sync::RwLock::new(Vec::new()),
+            next_id: atomic::AtomicUsize::new(0),
+        };
+    }
+
+    /// Registers a handler, which receives every message from then on.
+    pub fn register(&self, handler: Box<api::Logger>) -> HandlerId {
+        let id = HandlerId(self.next_id.fetch_add(1, atomic::Ordering::SeqCst));
+        let mut handlers = match self.handlers.write() {
+            Ok(handlers) => handlers,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        handlers.push((id, handler));
+        return id;
+    }
+
+    /// Unregisters a handler, returning it, or `None` if it was already unregistered.
+    pub fn unregister(&self, id: HandlerId) -> Option<Box<api::Logger>> {
+        let mut handlers = match self.handlers.write() {
+            Ok(handlers) => handlers,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        return match handlers.iter().position(|&(handler_id, _)| handler_id == id) {
+            Some(index) => Some(handlers.remove(index).1),
+            None => None,
+        };
+    }
+
+    /// Returns the number of handlers currently registered.
+    pub fn handler_count(&self) -> usize {
+        return match self.handlers.read() {
+            Ok(handlers) => handlers.len(),
+            Err(poisoned) => poisoned.into_inner().len(),
+        };
+    }
+}
+
+impl api::Logger for CompositeLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        let handlers = try!(self.handlers.read());
+        let mut result = Ok(());
+        for &(_, ref handler) in handlers.iter() {
+            if let Err(e) = handler.log_with_target(msg, level, location, target) {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        return result;
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        let handlers = try!(self.handlers.read());
+        let mut result = Ok(());
+        for &(_, ref handler) in handlers.iter() {
+            if handler.needs_flush() {
+                if let Err(e) = handler.flush() {
+                    if result.is_ok() {
+                        result = Err(e);
+                    }
+                }
+            }
+        }
+        return result;
+    }
+
+    fn needs_flush(&self) -> bool {
+        // Handlers which need flushing may be registered later
+        return true;
+    }
+
+    fn describe(&self) -> String {
+        let descriptions: Vec<String> = match self.handlers.read() {
+            Ok(handlers) => handlers.iter().map(|&(_, ref handler)| handler.describe()).collect(),
+            Err(_) => Vec::new(),
+        };
+        return format!("composite (handlers: [{}])", descriptions.join(", "));
+    }
+}
+
+impl log::Log for CompositeLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        log_with_fern_logger(self, record);
+    }
+}
+
+/// A logger implementation which does nothing with logged messages.
+#[derive(Clone, Copy)]
+pub struct NullLogger;
+
+impl api::Logger for NullLogger {
+    fn log(&self, _msg: &str, _level: &log::LogLevel, _location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return Ok(());
+    }
+
+    fn needs_flush(&self) -> bool {
+        return false;
+    }
+
+    fn describe(&self) -> String {
+        return "null".to_string();
     }
 }
 
@@ -169,17 +3602,19 @@ impl log::Log for NullLogger {
 /// Implementation of log::Log::log for any type which implements fern::Logger.
 pub fn log_with_fern_logger<T>(logger: &T, record: &log::LogRecord) where T: api::Logger {
     let args_formatted = format!("{}", record.args());
-    if let Err(e) = api::Logger::log(logger, &args_formatted, &record.level(), record.location()) {
+    if let Err(e) = api::Logger::log_with_target(logger, &args_formatted, &record.level(),
+                                                 record.location(), record.target()) {
         let backup_result = write!(&mut io::stderr(),
-                "Error logging {{level: {}, location: {:?}, arguments: {}}}: {:?}",
-                record.level(), record.location(), args_formatted, e);
+                "Error logging {{level: {}, location: {}, arguments: {}}}: {}",
+                record.level(), config::short_location(record.location()), args_formatted, e);
         if let Err(e2) = backup_result {
             panic!(format!(
                 "Backup logging failed after regular logging failed.\n\
-                Log record: {{level: {}, location: {:?}, arguments: {}}}\n\
-                Logging error: {:?}\n\
+                Log record: {{level: {}, location: {}, arguments: {}}}\n\
+                Logging error: {}\n\
                 Backup logging error: {}",
-                record.level(), record.location(), args_formatted, e, e2));
+                record.level(), config::short_location(record.location()), args_formatted, e,
+                e2));
         }
     }
 }
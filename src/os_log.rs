@@ -0,0 +1,118 @@
+use std::ffi;
+use std::os::raw::{c_char, c_void};
+
+use log;
+
+use api;
+use errors::LogError;
+use loggers;
+
+#[allow(non_camel_case_types)]
+type os_log_t = *mut c_void;
+
+const OS_LOG_TYPE_DEFAULT: u8 = 0x00;
+const OS_LOG_TYPE_INFO: u8 = 0x01;
+const OS_LOG_TYPE_DEBUG: u8 = 0x02;
+const OS_LOG_TYPE_ERROR: u8 = 0x10;
+
+extern "C" {
+    static __dso_handle: c_void;
+
+    fn os_log_create(subsystem: *const c_char, category: *const c_char) -> os_log_t;
+    fn os_release(object: *mut c_void);
+    fn _os_log_impl(dso: *const c_void, log: os_log_t, log_type: u8, format: *const c_char,
+                    buf: *mut u8, size: u32);
+}
+
+/// A logger sending messages to the macOS unified logging system, where they show up in
+/// Console.app and `log stream`, under the configured subsystem and category.
+///
+/// Levels are mapped to `os_log` types: `Error` to `OS_LOG_TYPE_ERROR`, `Warn` to
+/// `OS_LOG_TYPE_DEFAULT`, `Info` to `OS_LOG_TYPE_INFO`, and `Debug` and `Trace` to
+/// `OS_LOG_TYPE_DEBUG`. Messages are logged as public, so they aren't redacted.
+///
+/// This is only available on macOS, with the `os_log` feature enabled.
+pub struct OsLogLogger {
+    log: os_log_t,
+    subsystem: String,
+    category: String,
+}
+
+// os_log_t objects may be used from any thread.
+unsafe impl Send for OsLogLogger {}
+unsafe impl Sync for OsLogLogger {}
+
+impl OsLogLogger {
+    /// Creates a logger sending messages to the given subsystem, usually a reverse DNS name like
+    /// `com.example.app`, and category.
+    pub fn new(subsystem: &str, category: &str) -> OsLogLogger {
+        let c_subsystem = to_c_string(subsystem);
+        let c_category = to_c_string(category);
+        let log = unsafe { os_log_create(c_subsystem.as_ptr(), c_category.as_ptr()) };
+        return OsLogLogger {
+            log: log,
+            subsystem: subsystem.to_string(),
+            category: category.to_string(),
+        };
+    }
+}
+
+impl Drop for OsLogLogger {
+    fn drop(&mut self) {
+        unsafe { os_release(self.log) };
+    }
+}
+
+/// Converts a message into a C string, replacing any nul bytes, which can't be represented.
+fn to_c_string(msg: &str) -> ffi::CString {
+    return match ffi::CString::new(msg) {
+        Ok(s) => s,
+        Err(_) => ffi::CString::new(msg.replace('\0', "\\0")).unwrap(),
+    };
+}
+
+fn log_type(level: &log::LogLevel) -> u8 {
+    return match *level {
+        log::LogLevel::Error => OS_LOG_TYPE_ERROR,
+        log::LogLevel::Warn => OS_LOG_TYPE_DEFAULT,
+        log::LogLevel::Info => OS_LOG_TYPE_INFO,
+        log::LogLevel::Debug | log::LogLevel::Trace => OS_LOG_TYPE_DEBUG,
+    };
+}
+
+impl api::Logger for OsLogLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, _location: &log::LogLocation)
+            -> Result<(), LogError> {
+        let c_msg = to_c_string(msg);
+        let ptr = (c_msg.as_ptr() as usize as u64).to_ne_bytes();
+        // This is the argument buffer the os_log() C macro builds for the "%{public}s" format:
+        // a summary byte (has non-scalar arguments), the argument count, and then for the string
+        // argument a descriptor byte (string, public), its size, and the pointer itself.
+        let mut buf = [0u8; 12];
+        buf[0] = 0x02;
+        buf[1] = 1;
+        buf[2] = 0x22;
+        buf[3] = 8;
+        buf[4..].copy_from_slice(&ptr);
+        unsafe {
+            _os_log_impl(&__dso_handle, self.log, log_type(level),
+                         b"%{public}s\0".as_ptr() as *const c_char, buf.as_mut_ptr(),
+                         buf.len() as u32);
+        }
+        return Ok(());
+    }
+
+    fn describe(&self) -> String {
+        return format!("os_log ({}, {})", self.subsystem, self.category);
+    }
+}
+
+impl log::Log for OsLogLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        loggers::log_with_fern_logger(self, record);
+    }
+}
@@ -0,0 +1,160 @@
+use std::io;
+use std::mem;
+use std::sync;
+use std::sync::atomic;
+
+use log;
+
+use api;
+use clock;
+use config;
+use errors::LogError;
+use http;
+use loggers;
+
+/// Number of records `OtlpLogger` collects before sending them, unless changed with
+/// `OtlpLogger::batch_size()`.
+const DEFAULT_BATCH_SIZE: usize = 100;
+
+/// Returns the OpenTelemetry severity number and text for a level: `Trace` is 1 (`TRACE`),
+/// `Debug` 5 (`DEBUG`), `Info` 9 (`INFO`), `Warn` 13 (`WARN`) and `Error` 17 (`ERROR`), the
+/// first number of each range given by the OpenTelemetry log data model.
+pub fn otlp_severity(level: &log::LogLevel) -> (u8, &'static str) {
+    return match *level {
+        log::LogLevel::Trace => (1, "TRACE"),
+        log::LogLevel::Debug => (5, "DEBUG"),
+        log::LogLevel::Info => (9, "INFO"),
+        log::LogLevel::Warn => (13, "WARN"),
+        log::LogLevel::Error => (17, "ERROR"),
+    };
+}
+
+fn string_attribute(key: &str, value: &str) -> String {
+    return format!("{{\"key\":\"{}\",\"value\":{{\"stringValue\":\"{}\"}}}}",
+                   key, config::json_escape(value));
+}
+
+/// A logger sending messages to an OpenTelemetry collector as OTLP log records, using the
+/// OTLP/HTTP protocol with JSON encoding.
+///
+/// Each record has the message as its body, a severity from `otlp_severity()`, and the target,
+/// file and line as the `code.namespace`, `code.filepath` and `code.lineno` attributes. Records
+/// are collected into batches, which are sent when full, when `Logger::flush()` is called, and
+/// when the logger is dropped. Sending a batch blocks the thread that logged the last record of
+/// it - wrap this logger in a `ChannelLogger` to send from a background thread instead.
+///
+/// Only plain `http://` endpoints are supported, so this is meant for sending to a collector
+/// running locally or in a trusted network.
+///
+/// This is only available with the `otlp` feature enabled.
+pub struct OtlpLogger {
+    endpoint: String,
+    service_name: String,
+    batch_size: usize,
+    batch: sync::Mutex<Vec<String>>,
+    bytes_written: atomic::AtomicU64,
+}
+
+impl OtlpLogger {
+    /// Creates a logger sending records to the given OTLP/HTTP logs endpoint, such as
+    /// `http://localhost:4318/v1/logs`, with the `service.name` resource attribute set to
+    /// `service_name`.
+    pub fn new(endpoint: &str, service_name: &str) -> OtlpLogger {
+        return OtlpLogger {
+            endpoint: endpoint.to_string(),
+            service_name: service_name.to_string(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            batch: sync::Mutex::new(Vec::new()),
+            bytes_written: atomic::AtomicU64::new(0),
+        };
+    }
+
+    /// Sets how many records are collected before they're sent. This defaults to 100.
+    pub fn batch_size(mut self, batch_size: usize) -> OtlpLogger {
+        self.batch_size = batch_size;
+        return self;
+    }
+
+    /// Returns the number of bytes sent to the endpoint since this logger was created, counting
+    /// the headers of each request as well as the records.
+    pub fn bytes_written(&self) -> u64 {
+        return self.bytes_written.load(atomic::Ordering::SeqCst);
+    }
+
+    /// Sends the given records in one export request.
+    fn send(&self, records: Vec<String>) -> io::Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let body = format!("{{\"resourceLogs\":[{{\"resource\":{{\"attributes\":[{}]}},\
+                            \"scopeLogs\":[{{\"scope\":{{\"name\":\"fern\"}},\
+                            \"logRecords\":[{}]}}]}}]}}",
+                           string_attribute("service.name", &self.service_name),
+                           records.join(","));
+        return http::post(&self.endpoint, "application/json", body.as_bytes(),
+                          &self.bytes_written);
+    }
+}
+
+impl api::Logger for OtlpLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        let now = clock::get_time();
+        let (severity_number, severity_text) = otlp_severity(level);
+        // 64 bit integers are strings in the JSON encoding of OTLP
+        let record = format!("{{\"timeUnixNano\":\"{}{:09}\",\"severityNumber\":{},\
+                              \"severityText\":\"{}\",\"body\":{{\"stringValue\":\"{}\"}},\
+                              \"attributes\":[{},{},{{\"key\":\"code.lineno\",\
+                              \"value\":{{\"intValue\":\"{}\"}}}}]}}",
+                             now.sec, now.nsec, severity_number, severity_text,
+                             config::json_escape(msg), string_attribute("code.namespace", target),
+                             string_attribute("code.filepath", location.__file),
+                             location.__line);
+        let full_batch = {
+            let mut batch = try!(self.batch.lock());
+            batch.push(record);
+            match batch.len() >= self.batch_size {
+                true => mem::replace(&mut *batch, Vec::new()),
+                false => Vec::new(),
+            }
+        };
+        return Ok(try!(self.send(full_batch)));
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        let records = mem::replace(&mut *try!(self.batch.lock()), Vec::new());
+        return Ok(try!(self.send(records)));
+    }
+
+    fn describe(&self) -> String {
+        return format!("otlp ({})", self.endpoint);
+    }
+
+    fn validate(&self) -> Vec<String> {
+        return match http::resolve(&self.endpoint) {
+            Ok(()) => Vec::new(),
+            Err(e) => vec![format!("can't resolve {}: {}", self.endpoint, e)],
+        };
+    }
+}
+
+impl Drop for OtlpLogger {
+    fn drop(&mut self) {
+        let _ = api::Logger::flush(self);
+    }
+}
+
+impl log::Log for OtlpLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        loggers::log_with_fern_logger(self, record);
+    }
+}
@@ -0,0 +1,363 @@
+use std::io;
+use std::cmp;
+use std::sync;
+
+use log;
+
+use api;
+use errors::LogError;
+use loggers;
+
+/// Frames larger than this are rejected by `PipeReader`, as they're most likely garbage.
+const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// Location given to loggers for records read by `PipeReader`, as `LogLocation` can only hold
+/// static strings. The original file and line are in the `PipeRecord`.
+static PIPE_LOCATION: log::LogLocation = log::LogLocation {
+    __module_path: "fern::pipe",
+    __file: file!(),
+    __line: 0,
+};
+
+/// A logger forwarding messages to another process, such as from a worker to the supervisor
+/// which started it, by writing them to a pipe or any other `io::Write` as framed records. The
+/// other end reads them back with a `PipeReader`.
+///
+/// Each record is written as one frame, all integers being big-endian:
+///
+/// - the length of the rest of the frame in bytes, as a `u32`
+/// - the level, as a `u8` from 1 for `Error` to 5 for `Trace`
+/// - the source line, as a `u32`
+/// - the length of the target as a `u16`, followed by the target in UTF-8
+/// - the length of the source file as a `u16`, followed by the file in UTF-8
+/// - the message in UTF-8, taking up the rest of the frame
+///
+/// Targets and files longer than 65535 bytes are cut short. Each frame is written with a single
+/// `write_all()` call, and the writer is flushed after each one.
+///
+/// On Unix, a pipe inherited as a file descriptor can be written to with
+/// `PipeLogger::new(unsafe { fs::File::from_raw_fd(fd) })`.
+pub struct PipeLogger<W: io::Write + Send> {
+    writer: sync::Mutex<W>,
+}
+
+impl <W: io::Write + Send> PipeLogger<W> {
+    /// Creates a logger writing framed records to `writer`.
+    pub fn new(writer: W) -> PipeLogger<W> {
+        return PipeLogger {
+            writer: sync::Mutex::new(writer),
+        };
+    }
+}
+
+/// Appends a length and then as much of `s` as fits in a `u16` length, without splitting a
+/// character.
+fn push_short_str(frame: &mut Vec<u8>, s: &str) {
+    let mut len = cmp::min(s.len(), u16::max_value() as usize);
+    while !s.is_char_boundary(len) {
+        len -= 1;
+    }
+    frame.extend_from_slice(&(len as u16).to_be_bytes());
+    frame.extend_from_slice(&s.as_bytes()[..len]);
+}
+
+impl <W: io::Write + Send> api::Logger for PipeLogger<W> {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        let mut frame = vec![0; 4];
+        frame.push(*level as usize as u8);
+        frame.extend_from_slice(&location.__line.to_be_bytes());
+        push_short_str(&mut frame, target);
+        push_short_str(&mut frame, location.__file);
+        frame.extend_from_slice(msg.as_bytes());
+        let len = frame.len() - 4;
+        if len > MAX_FRAME_LEN {
+            return Err(LogError::Io(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("record of {} bytes is too large to send over a pipe", len))));
+        }
+        frame[..4].copy_from_slice(&(len as u32).to_be_bytes());
+
+        let mut writer = try!(self.writer.lock());
+        try!(writer.write_all(&frame));
+        return Ok(try!(writer.flush()));
+    }
+
+    fn needs_flush(&self) -> bool {
+        return false;
+    }
+
+    fn describe(&self) -> String {
+        return "pipe".to_string();
+    }
+}
+
+impl <W: io::Write + Send> log::Log for PipeLogger<W> {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        loggers::log_with_fern_logger(self, record);
+    }
+}
+
+/// A record read by a `PipeReader`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PipeRecord {
+    /// Level of the message
+    pub level: log::LogLevel,
+    /// Target the message was logged with
+    pub target: String,
+    /// Source file the message was logged from
+    pub file: String,
+    /// Source line the message was logged from
+    pub line: u32,
+    /// The message, as formatted by the sending process
+    pub message: String,
+}
+
+impl PipeRecord {
+    /// Sends this record to `logger`, with its target. Loggers are given a fixed location, since
+    /// `LogLocation` can't refer to the file read from the pipe, so `DispatchLogger` directives
+    /// match every forwarded record against the `fern::pipe` module path.
+    pub fn dispatch(&self, logger: &api::Logger) -> Result<(), LogError> {
+        return logger.log_with_target(&self.message, &self.level, &PIPE_LOCATION, &self.target);
+    }
+}
+
+/// Reads the records written by a `PipeLogger` in another process, for the receiving process to
+/// log with its own loggers:
+///
+/// ```rust,no_run
+/// # extern crate fern;
+/// # fn forward(pipe: std::fs::File, logger: &fern::Logger) -> std::io::Result<()> {
+/// let mut reader = fern::PipeReader::new(pipe);
+/// while let Some(record) = try!(reader.read_record()) {
+///     let _ = record.dispatch(logger);
+/// }
+/// # Ok(())
+/// # }
+/// # fn main() {}
+/// ```
+pub struct PipeReader<R: io::Read> {
+    reader: R,
+}
+
+impl <R: io::Read> PipeReader<R> {
+    /// Creates a reader reading framed records from `reader`.
+    pub fn new(reader: R) -> PipeReader<R> {
+        return PipeReader {
+            reader: reader,
+        };
+    }
+
+    /// Reads the next record, waiting for it if necessary. Returns `None` once the other end has
+    /// been closed, and an `InvalidData` error for malformed frames.
+    pub fn read_record(&mut self) -> io::Result<Option<PipeRecord>> {
+        let mut len = [0; 4];
+        match self.reader.read_exact(&mut len) {
+            Ok(()) => {},
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let len = u32::from_be_bytes(len) as usize;
+        if len > MAX_FRAME_LEN {
+            return Err(invalid_frame(&format!("frame of {} bytes is too large", len)));
+        }
+        let mut frame = vec![0; len];
+        try!(self.reader.read_exact(&mut frame));
+        return parse_frame(&frame).map(Some);
+    }
+}
+
+fn invalid_frame(description: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData,
+                          format!("invalid log record frame: {}", description));
+}
+
+fn parse_frame(frame: &[u8]) -> io::Result<PipeRecord> {
+    if frame.len() < 5 {
+        return Err(invalid_frame("too short"));
+    }
+    let level = match frame[0] {
+        1 => log::LogLevel::Error,
+        2 => log::LogLevel::Warn,
+        3 => log::LogLevel::Info,
+        4 => log::LogLevel::Debug,
+        5 => log::LogLevel::Trace,
+        level => return Err(invalid_frame(&format!("unknown level {}", level))),
+    };
+    let line = u32::from_be_bytes([frame[1], frame[2], frame[3], frame[4]]);
+    let (target, rest) = try!(read_short_str(&frame[5..]));
+    let (file, rest) = try!(read_short_str(rest));
+    let message = match String::from_utf8(rest.to_vec()) {
+        Ok(message) => message,
+        Err(_) => return Err(invalid_frame("message isn't UTF-8")),
+    };
+    return Ok(PipeRecord {
+        level: level,
+        target: target,
+        file: file,
+        line: line,
+        message: message,
+    });
+}
+
+/// Reads a string written by `push_short_str()`, returning it and the bytes after it.
+fn read_short_str(bytes: &[u8]) -> io::Result<(String, &[u8])> {
+    if bytes.len() < 2 {
+        return Err(invalid_frame("too short"));
+    }
+    let len = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+    if bytes.len() < 2 + len {
+        return Err(invalid_frame("too short"));
+    }
+    return match String::from_utf8(bytes[2..2 + len].to_vec()) {
+        Ok(s) => Ok((s, &bytes[2 + len..])),
+        Err(_) => Err(invalid_frame("target or file isn't UTF-8")),
+    };
+}
+
+/// Parses a level marker at the start of a line of output, such as `ERROR: boom` or
+/// `[warn] low on disk space`, returning the level and the rest of the line without the marker.
+/// Level names are matched regardless of case, and may be followed by a colon, or enclosed in
+/// square brackets. `WARNING` is read as `Warn`, and `FATAL` and `CRITICAL` as `Error`.
+///
+/// Lines without a marker are returned whole, at `Info`:
+///
+/// ```rust
+/// # extern crate fern;
+/// # extern crate log;
+/// # fn main() {
+/// assert_eq!(fern::parse_level_prefix("ERROR: boom"), (log::LogLevel::Error, "boom"));
+/// assert_eq!(fern::parse_level_prefix("[debug] x = 1"), (log::LogLevel::Debug, "x = 1"));
+/// assert_eq!(fern::parse_level_prefix("starting up"), (log::LogLevel::Info, "starting up"));
+/// # }
+/// ```
+pub fn parse_level_prefix(line: &str) -> (log::LogLevel, &str) {
+    let (name, rest) = match line.starts_with('[') {
+        true => match line.find(']') {
+            Some(end) => (&line[1..end], &line[end + 1..]),
+            None => return (log::LogLevel::Info, line),
+        },
+        false => match line.find(':') {
+            Some(end) => (&line[..end], &line[end + 1..]),
+            None => return (log::LogLevel::Info, line),
+        },
+    };
+    let level = match &*name.trim().to_ascii_lowercase() {
+        "fatal" | "critical" | "error" => log::LogLevel::Error,
+        "warning" | "warn" => log::LogLevel::Warn,
+        "info" => log::LogLevel::Info,
+        "debug" => log::LogLevel::Debug,
+        "trace" => log::LogLevel::Trace,
+        _ => return (log::LogLevel::Info, line),
+    };
+    return (level, rest.trim_start());
+}
+
+/// An `io::Write` re-logging each line written to it, such as the output of a child process, at
+/// the level of its marker as parsed by `fern::parse_level_prefix()`:
+///
+/// ```rust,no_run
+/// # extern crate fern;
+/// # fn forward(logger: std::sync::Arc<fern::Logger>) -> std::io::Result<()> {
+/// use std::io;
+/// use std::process::{Command, Stdio};
+///
+/// let mut child = try!(Command::new("worker").stdout(Stdio::piped()).spawn());
+/// let mut writer = fern::LevelPrefixWriter::new(logger).target("worker");
+/// try!(io::copy(child.stdout.as_mut().unwrap(), &mut writer));
+/// # Ok(())
+/// # }
+/// # fn main() {}
+/// ```
+///
+/// Lines are logged without their marker, and without the line ending, which may be `\n` or
+/// `\r\n`. Incomplete lines are kept until the rest of the line is written, and the last one is
+/// logged when the writer is dropped. Bytes which aren't valid UTF-8 are replaced.
+///
+/// As with `PipeReader`, loggers are given a fixed location, in the `fern::pipe` module.
+pub struct LevelPrefixWriter {
+    logger: sync::Arc<api::Logger>,
+    target: String,
+    partial: Vec<u8>,
+}
+
+impl LevelPrefixWriter {
+    /// Creates a writer logging each line written to it to `logger`, with the `fern::pipe`
+    /// target.
+    pub fn new(logger: sync::Arc<api::Logger>) -> LevelPrefixWriter {
+        return LevelPrefixWriter {
+            logger: logger,
+            target: PIPE_LOCATION.__module_path.to_string(),
+            partial: Vec::new(),
+        };
+    }
+
+    /// Sets the target lines are logged with, such as the name of the child process.
+    pub fn target(mut self, target: &str) -> LevelPrefixWriter {
+        self.target = target.to_string();
+        return self;
+    }
+
+    fn log_line(&self, line: &[u8]) -> io::Result<()> {
+        let line = match line.last() {
+            Some(&b'\r') => &line[..line.len() - 1],
+            _ => line,
+        };
+        let line = String::from_utf8_lossy(line);
+        let (level, msg) = parse_level_prefix(&line);
+        return match self.logger.log_with_target(msg, &level, &PIPE_LOCATION, &self.target) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(io::Error::new(io::ErrorKind::Other, e.to_string())),
+        };
+    }
+}
+
+impl io::Write for LevelPrefixWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let end = match buf.iter().position(|&byte| byte == b'\n') {
+            Some(end) => end,
+            None => {
+                self.partial.extend_from_slice(buf);
+                return Ok(buf.len());
+            },
+        };
+        // Only one line is logged per call, so that when logging it fails, nothing has been
+        // written and the line can be written again.
+        let partial_len = self.partial.len();
+        self.partial.extend_from_slice(&buf[..end]);
+        let result = self.log_line(&self.partial);
+        match result {
+            Ok(()) => self.partial.clear(),
+            Err(_) => self.partial.truncate(partial_len),
+        }
+        try!(result);
+        return Ok(end + 1);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return match self.logger.needs_flush() {
+            true => match self.logger.flush() {
+                Ok(()) => Ok(()),
+                Err(e) => Err(io::Error::new(io::ErrorKind::Other, e.to_string())),
+            },
+            false => Ok(()),
+        };
+    }
+}
+
+impl Drop for LevelPrefixWriter {
+    fn drop(&mut self) {
+        if !self.partial.is_empty() {
+            let line = ::std::mem::replace(&mut self.partial, Vec::new());
+            let _ = self.log_line(&line);
+        }
+    }
+}
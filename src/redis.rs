@@ -0,0 +1,309 @@
+use std::io::{BufRead, Write};
+use std::io;
+use std::net;
+use std::sync;
+use std::sync::atomic;
+use std::time::Duration;
+
+use log;
+
+use api;
+use errors::LogError;
+use loggers;
+
+/// How long to wait for connecting to, writing to and reading from Redis.
+const TIMEOUT_SECS: u64 = 10;
+
+/// How records are added to the key.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Xadd,
+    Rpush,
+}
+
+/// The connection, and the commands waiting to be sent on it.
+struct RedisState {
+    stream: Option<net::TcpStream>,
+    pending: Vec<u8>,
+    pending_count: usize,
+}
+
+/// A logger adding each message to a Redis stream with `XADD`, or to a list with `RPUSH`, for
+/// pipelines which read their logs from Redis.
+///
+/// Stream entries have the fields `level`, `target`, `file`, `line` and `message`, the last
+/// being the formatted message. Lists get the formatted message alone.
+///
+/// Commands are sent in batches of the size set with `batch_size()`, pipelined over one
+/// connection, and the batch waiting to be sent is sent by `flush()` and when this logger is
+/// dropped. The connection is opened when the first batch is sent. If sending a batch fails,
+/// such as when Redis has been restarted, this reconnects and sends the batch once more, and
+/// returns the error if that fails too, dropping the batch. As a batch may have been partly
+/// added before the connection failed, records may be added twice, but never out of order.
+///
+/// Sending waits for Redis to reply. To keep logging from waiting for it, and to choose between
+/// dropping records and blocking when Redis can't keep up, wrap this in a `ChannelLogger`:
+///
+/// ```rust,no_run
+/// # extern crate fern;
+/// # fn main() {
+/// let redis = fern::RedisLogger::stream("127.0.0.1:6379", "logs").batch_size(100);
+/// let logger = fern::ChannelLogger::new(Box::new(redis), 10000, fern::QueueFullMode::Drop);
+/// # let _ = logger;
+/// # }
+/// ```
+///
+/// Only plain connections without authentication are supported.
+///
+/// This is only available with the `redis` feature enabled.
+pub struct RedisLogger {
+    address: String,
+    key: String,
+    command: Command,
+    batch_size: usize,
+    state: sync::Mutex<RedisState>,
+    bytes_written: atomic::AtomicU64,
+}
+
+impl RedisLogger {
+    /// Creates a logger adding messages to the stream `key` on the Redis server at `address`,
+    /// such as `127.0.0.1:6379`, one at a time.
+    pub fn stream(address: &str, key: &str) -> RedisLogger {
+        return RedisLogger::new(address, key, Command::Xadd);
+    }
+
+    /// Creates a logger pushing messages onto the end of the list `key` on the Redis server at
+    /// `address`, one at a time.
+    pub fn list(address: &str, key: &str) -> RedisLogger {
+        return RedisLogger::new(address, key, Command::Rpush);
+    }
+
+    fn new(address: &str, key: &str, command: Command) -> RedisLogger {
+        return RedisLogger {
+            address: address.to_string(),
+            key: key.to_string(),
+            command: command,
+            batch_size: 1,
+            state: sync::Mutex::new(RedisState {
+                stream: None,
+                pending: Vec::new(),
+                pending_count: 0,
+            }),
+            bytes_written: atomic::AtomicU64::new(0),
+        };
+    }
+
+    /// Sets how many records are collected before they're sent together. This defaults to 1,
+    /// sending each record right away.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `batch_size` is 0.
+    pub fn batch_size(mut self, batch_size: usize) -> RedisLogger {
+        assert!(batch_size > 0, "Redis batches must hold at least one record");
+        self.batch_size = batch_size;
+        return self;
+    }
+
+    /// Returns the number of bytes of commands sent to Redis since this logger was created.
+    /// Batches sent again after reconnecting are counted again.
+    pub fn bytes_written(&self) -> u64 {
+        return self.bytes_written.load(atomic::Ordering::SeqCst);
+    }
+
+    fn lock_state(&self) -> sync::MutexGuard<RedisState> {
+        return match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+    }
+
+    /// Sends the pending commands, reconnecting and trying once more if that fails.
+    fn send_pending(&self, state: &mut RedisState) -> io::Result<()> {
+        if state.pending_count == 0 {
+            return Ok(());
+        }
+        let mut result = Ok(());
+        for _ in 0..2 {
+            if state.stream.is_none() {
+                result = connect(&self.address).map(|stream| state.stream = Some(stream));
+                if result.is_err() {
+                    continue;
+                }
+            }
+            result = match state.stream {
+                Some(ref mut stream) => send_commands(stream, &state.pending,
+                                                      state.pending_count, &self.bytes_written),
+                None => continue,
+            };
+            match result {
+                Err(ref e) if e.kind() != io::ErrorKind::Other => state.stream = None,
+                _ => break,
+            }
+        }
+        state.pending.clear();
+        state.pending_count = 0;
+        return result;
+    }
+}
+
+fn connect(address: &str) -> io::Result<net::TcpStream> {
+    let timeout = Duration::from_secs(TIMEOUT_SECS);
+    let mut last_error = io::Error::new(io::ErrorKind::InvalidInput,
+                                        format!("no addresses found for {}", address));
+    for addr in try!(net::ToSocketAddrs::to_socket_addrs(address)) {
+        match net::TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => {
+                try!(stream.set_read_timeout(Some(timeout)));
+                try!(stream.set_write_timeout(Some(timeout)));
+                return Ok(stream);
+            },
+            Err(e) => last_error = e,
+        }
+    }
+    return Err(last_error);
+}
+
+/// Writes the encoded commands, adding them to `bytes_written`, and reads a reply for each of
+/// them. Error replies are returned with `ErrorKind::Other`, once every reply has been read.
+fn send_commands(stream: &mut net::TcpStream, commands: &[u8], count: usize,
+                 bytes_written: &atomic::AtomicU64) -> io::Result<()> {
+    try!(stream.write_all(commands));
+    bytes_written.fetch_add(commands.len() as u64, atomic::Ordering::SeqCst);
+    let mut reader = io::BufReader::new(stream);
+    let mut result = Ok(());
+    for _ in 0..count {
+        if let Err(message) = try!(read_reply(&mut reader)) {
+            if result.is_ok() {
+                result = Err(io::Error::new(io::ErrorKind::Other,
+                                            format!("Redis replied with an error: {}", message)));
+            }
+        }
+    }
+    return result;
+}
+
+/// Appends a command in the Redis protocol, as an array of bulk strings.
+fn push_command(buf: &mut Vec<u8>, args: &[&[u8]]) {
+    buf.extend_from_slice(format!("*{}\r\n", args.len()).as_bytes());
+    for arg in args {
+        buf.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        buf.extend_from_slice(arg);
+        buf.extend_from_slice(b"\r\n");
+    }
+}
+
+/// Reads one reply, returning the message of an error reply as `Ok(Err(..))`.
+fn read_reply<R: BufRead>(reader: &mut R) -> io::Result<Result<(), String>> {
+    let mut line = String::new();
+    if try!(reader.read_line(&mut line)) == 0 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Redis closed the connection"));
+    }
+    let line = line.trim_end_matches("\r\n");
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData,
+                                    format!("invalid reply from Redis: {:?}", line));
+    let (kind, rest) = match line.chars().next() {
+        Some(kind) => (kind, &line[kind.len_utf8()..]),
+        None => return Err(invalid()),
+    };
+    match kind {
+        '+' | ':' => {},
+        '-' => return Ok(Err(rest.to_string())),
+        '$' => {
+            let len: i64 = try!(rest.parse().map_err(|_| invalid()));
+            if len >= 0 {
+                let mut data = vec![0; len as usize + 2];
+                try!(reader.read_exact(&mut data));
+            }
+        },
+        '*' => {
+            let len: i64 = try!(rest.parse().map_err(|_| invalid()));
+            // Every element is read even after an error, so the next reply starts where expected
+            let mut result = Ok(());
+            for _ in 0..len {
+                let element = try!(read_reply(reader));
+                if result.is_ok() {
+                    result = element;
+                }
+            }
+            return Ok(result);
+        },
+        _ => return Err(invalid()),
+    }
+    return Ok(Ok(()));
+}
+
+impl api::Logger for RedisLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        let mut state = self.lock_state();
+        match self.command {
+            Command::Xadd => {
+                let level = level.to_string();
+                let line = location.__line.to_string();
+                push_command(&mut state.pending, &[
+                    b"XADD", self.key.as_bytes(), b"*",
+                    b"level", level.as_bytes(),
+                    b"target", target.as_bytes(),
+                    b"file", location.__file.as_bytes(),
+                    b"line", line.as_bytes(),
+                    b"message", msg.as_bytes(),
+                ]);
+            },
+            Command::Rpush => {
+                push_command(&mut state.pending, &[b"RPUSH", self.key.as_bytes(), msg.as_bytes()]);
+            },
+        }
+        state.pending_count += 1;
+        if state.pending_count < self.batch_size {
+            return Ok(());
+        }
+        return Ok(try!(self.send_pending(&mut state)));
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        return Ok(try!(self.send_pending(&mut self.lock_state())));
+    }
+
+    fn needs_flush(&self) -> bool {
+        return self.batch_size > 1;
+    }
+
+    fn describe(&self) -> String {
+        let command = match self.command {
+            Command::Xadd => "stream",
+            Command::Rpush => "list",
+        };
+        return format!("redis ({}, {} {})", self.address, command, self.key);
+    }
+
+    fn validate(&self) -> Vec<String> {
+        return match net::ToSocketAddrs::to_socket_addrs(&*self.address)
+                         .map(|mut addrs| addrs.next().is_some()) {
+            Ok(true) => Vec::new(),
+            Ok(false) => vec![format!("can't resolve {}: no addresses found", self.address)],
+            Err(e) => vec![format!("can't resolve {}: {}", self.address, e)],
+        };
+    }
+}
+
+impl Drop for RedisLogger {
+    fn drop(&mut self) {
+        let _ = self.send_pending(&mut self.lock_state());
+    }
+}
+
+impl log::Log for RedisLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        loggers::log_with_fern_logger(self, record);
+    }
+}
@@ -0,0 +1,478 @@
+use std::io::Write;
+use std::io;
+use std::fs;
+use std::mem;
+use std::path;
+use std::sync;
+use std::sync::atomic;
+
+use log;
+use time;
+
+use api;
+use errors::LogError;
+use loggers;
+
+/// A closure returning the name of the current rotation period, such as the current date for
+/// daily rotation. See `RotationCoordinator::new()`.
+pub type Schedule = Fn() -> String + Sync + Send;
+
+/// A closure opening the file with the given name for a `RotatingFileLogger` to write to. See
+/// `RotationCoordinator::open_with_factory()`.
+pub type WriterFactory = Fn(&path::Path) -> io::Result<Box<io::Write + Send>> + Sync + Send;
+
+/// A log file which is rotated by a `RotationCoordinator`.
+struct RotatingFile {
+    path: path::PathBuf,
+    file: sync::Mutex<Box<io::Write + Send>>,
+    // Opens the file for each period instead of renaming, see `open_with_factory()`
+    factory: Option<Box<WriterFactory>>,
+    // Incremented each time the file is rotated
+    generation: atomic::AtomicUsize,
+    // Rotations forced by `RotatingFileLogger::rotate_now()` in the current period, which are
+    // counted in the names given to the factory
+    forced: atomic::AtomicUsize,
+    // Permissions new files are created with, see `RotationCoordinator::open_with_mode()`
+    mode: Option<u32>,
+    // See `RotatingFileLogger::max_total_bytes()`
+    max_total_bytes: sync::Mutex<Option<u64>>,
+}
+
+impl RotatingFile {
+    fn open_file(path: &path::Path, mode: Option<u32>) -> io::Result<fs::File> {
+        return loggers::append_options(mode).open(path);
+    }
+
+    /// Returns the name a factory is called with for the given period.
+    fn period_path(path: &path::Path, period: &str) -> path::PathBuf {
+        return path::PathBuf::from(format!("{}.{}", path.display(), period));
+    }
+
+    /// Moves the current file to `<path>.<ended>`, adding a counter if that already exists, and
+    /// starts a new file at the original path, returning the path it was moved to. Files opened
+    /// by a factory are left alone, and the factory is called with `<path>.<period>` instead,
+    /// with a counter added if the rotation is `forced` within the same period.
+    fn rotate(&self, ended: &str, period: &str, forced: bool)
+            -> io::Result<Option<path::PathBuf>> {
+        let mut file = match self.file.lock() {
+            Ok(file) => file,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        try!(file.flush());
+        if let Some(ref factory) = self.factory {
+            let name = match forced {
+                true => {
+                    let counter = self.forced.fetch_add(1, atomic::Ordering::SeqCst) + 1;
+                    path::PathBuf::from(format!("{}.{}.{}", self.path.display(), period, counter))
+                },
+                false => {
+                    self.forced.store(0, atomic::Ordering::SeqCst);
+                    RotatingFile::period_path(&self.path, period)
+                },
+            };
+            *file = try!(factory(&name));
+            self.generation.fetch_add(1, atomic::Ordering::SeqCst);
+            return Ok(None);
+        }
+        let mut counter = 0;
+        let archive = loop {
+            let name = match counter {
+                0 => format!("{}.{}", self.path.display(), ended),
+                _ => format!("{}.{}.{}", self.path.display(), ended, counter),
+            };
+            let archive = path::PathBuf::from(name);
+            if !archive.exists() {
+                break archive;
+            }
+            counter += 1;
+        };
+        try!(fs::rename(&self.path, &archive));
+        *file = Box::new(try!(RotatingFile::open_file(&self.path, self.mode)));
+        self.generation.fetch_add(1, atomic::Ordering::SeqCst);
+        let max_total_bytes = match self.max_total_bytes.lock() {
+            Ok(max) => *max,
+            Err(poisoned) => *poisoned.into_inner(),
+        };
+        if let Some(max_total_bytes) = max_total_bytes {
+            try!(self.prune(max_total_bytes));
+        }
+        return Ok(Some(archive));
+    }
+
+    /// Deletes the oldest rotated files until the rest take up at most `max_total_bytes`.
+    /// Rotated files are those in the same directory whose name starts with this file's name
+    /// followed by a dot.
+    fn prune(&self, max_total_bytes: u64) -> io::Result<()> {
+        let prefix = match self.path.file_name() {
+            Some(name) => format!("{}.", name.to_string_lossy()),
+            None => return Ok(()),
+        };
+        let dir = match self.path.parent() {
+            Some(dir) if dir != path::Path::new("") => dir,
+            _ => path::Path::new("."),
+        };
+        let mut archives = Vec::new();
+        for entry in try!(fs::read_dir(dir)) {
+            let entry = try!(entry);
+            if !entry.file_name().to_string_lossy().starts_with(&prefix) {
+                continue;
+            }
+            let metadata = try!(entry.metadata());
+            if metadata.is_file() {
+                archives.push((try!(metadata.modified()), entry.path(), metadata.len()));
+            }
+        }
+        // Oldest first, by name when the times are the same
+        archives.sort();
+        let mut total: u64 = archives.iter().map(|archive| archive.2).sum();
+        for (_, path, len) in archives {
+            if total <= max_total_bytes {
+                break;
+            }
+            try!(fs::remove_file(&path));
+            total -= len;
+        }
+        return Ok(());
+    }
+}
+
+struct CoordinatorState {
+    period: String,
+    files: Vec<sync::Weak<RotatingFile>>,
+}
+
+/// Rotates a set of log files together on a shared schedule, such as app, access and error logs
+/// which should all start new files at midnight.
+///
+/// Files are opened with `RotationCoordinator::open()`, which returns a `RotatingFileLogger`.
+/// Whenever any of them logs a message, the coordinator checks whether the rotation period has
+/// changed, and if it has, rotates every one of its files at once, before the message is
+/// written. Each file is rotated by renaming it to its path with the name of the period which
+/// ended appended, such as `app.log.2016-03-01`, and then creating a new, empty file at the
+/// original path.
+///
+/// ```rust,no_run
+/// # extern crate fern;
+/// # fn setup() -> std::io::Result<()> {
+/// let coordinator = fern::RotationCoordinator::daily();
+/// let app = try!(coordinator.open("app.log", "\n"));
+/// let access = try!(coordinator.open("access.log", "\n"));
+/// # Ok(())
+/// # }
+/// # fn main() {}
+/// ```
+pub struct RotationCoordinator {
+    schedule: Box<Schedule>,
+    state: sync::Mutex<CoordinatorState>,
+}
+
+impl RotationCoordinator {
+    /// Creates a coordinator which rotates files whenever the string returned by `schedule`
+    /// changes. The previous string is used to name the rotated files.
+    ///
+    /// `schedule` is called before each message is logged, while the coordinator is locked so
+    /// that each change rotates the files only once, so it should be quick.
+    pub fn new(schedule: Box<Schedule>) -> sync::Arc<RotationCoordinator> {
+        let period = schedule();
+        return sync::Arc::new(RotationCoordinator {
+            schedule: schedule,
+            state: sync::Mutex::new(CoordinatorState {
+                period: period,
+                files: Vec::new(),
+            }),
+        });
+    }
+
+    /// Creates a coordinator which rotates files at local midnight, naming the rotated files
+    /// by date, as in `app.log.2016-03-01`.
+    ///
+    /// The date is given by the system's calendar for the local time zone, so files are
+    /// rotated exactly when the local date changes: leap days and new years are handled, as are
+    /// the 23 and 25 hour days of daylight saving time changes.
+    pub fn daily() -> sync::Arc<RotationCoordinator> {
+        return RotationCoordinator::daily_with_clock(Box::new(time::get_time));
+    }
+
+    /// Like `daily()`, but with `clock` giving the current time instead of the system clock,
+    /// such as for testing rotation at the end of a particular day.
+    pub fn daily_with_clock(clock: Box<Fn() -> time::Timespec + Sync + Send>)
+            -> sync::Arc<RotationCoordinator> {
+        return RotationCoordinator::new(Box::new(move || {
+            time::strftime("%Y-%m-%d", &time::at(clock())).unwrap()
+        }));
+    }
+
+    /// Opens the given path with write(true), append(true) and create(true), and creates a new
+    /// logger writing to it, which this coordinator rotates along with its other files.
+    pub fn open<P: AsRef<path::Path>>(self: &sync::Arc<Self>, path: P, line_sep: &str)
+            -> io::Result<RotatingFileLogger> {
+        return self.open_file(path.as_ref(), line_sep, None);
+    }
+
+    /// Like `open()`, but creates the file with the given permissions if it doesn't exist yet,
+    /// such as `0o600` to only let the owner read it, as with `WriterLogger::with_file_mode()`.
+    /// The new files started by rotating, and the overflow file if there is one, are created
+    /// with the same permissions.
+    ///
+    /// On platforms other than Unix, `mode` is ignored and this is the same as `open()`.
+    pub fn open_with_mode<P: AsRef<path::Path>>(self: &sync::Arc<Self>, path: P, line_sep: &str,
+                                                mode: u32) -> io::Result<RotatingFileLogger> {
+        return self.open_file(path.as_ref(), line_sep, Some(mode));
+    }
+
+    /// Creates a logger writing to files opened by `factory` rather than by fern, for instance to
+    /// open them with special flags, or to write somewhere other than the filesystem in tests.
+    ///
+    /// Rather than renaming the file on rotation, the factory is called with `<path>.<period>`
+    /// for each period, as in `app.log.2016-03-01`, starting with the current period here. As
+    /// nothing is renamed, fern never touches the filesystem itself.
+    pub fn open_with_factory<P: AsRef<path::Path>>(self: &sync::Arc<Self>, path: P,
+                                                   line_sep: &str, factory: Box<WriterFactory>)
+            -> io::Result<RotatingFileLogger> {
+        let mut state = self.lock_state();
+        let writer = try!(factory(&RotatingFile::period_path(path.as_ref(), &state.period)));
+        return Ok(self.add_file(&mut state, path.as_ref(), line_sep, writer, Some(factory), None));
+    }
+
+    fn open_file(self: &sync::Arc<Self>, path: &path::Path, line_sep: &str, mode: Option<u32>)
+            -> io::Result<RotatingFileLogger> {
+        let writer = Box::new(try!(RotatingFile::open_file(path, mode)));
+        let mut state = self.lock_state();
+        return Ok(self.add_file(&mut state, path, line_sep, writer, None, mode));
+    }
+
+    fn add_file(self: &sync::Arc<Self>, state: &mut CoordinatorState, path: &path::Path,
+                line_sep: &str, writer: Box<io::Write + Send>, factory: Option<Box<WriterFactory>>,
+                mode: Option<u32>) -> RotatingFileLogger {
+        let file = sync::Arc::new(RotatingFile {
+            path: path.to_path_buf(),
+            file: sync::Mutex::new(writer),
+            factory: factory,
+            generation: atomic::AtomicUsize::new(0),
+            forced: atomic::AtomicUsize::new(0),
+            mode: mode,
+            max_total_bytes: sync::Mutex::new(None),
+        });
+        state.files.push(sync::Arc::downgrade(&file));
+        return RotatingFileLogger {
+            file: file,
+            line_sep: line_sep.to_string(),
+            coordinator: self.clone(),
+            overflow: None,
+        };
+    }
+
+    /// Returns the number of open files this coordinator rotates.
+    pub fn file_count(&self) -> usize {
+        let mut state = self.lock_state();
+        state.files.retain(|file| file.upgrade().is_some());
+        return state.files.len();
+    }
+
+    fn lock_state(&self) -> sync::MutexGuard<CoordinatorState> {
+        return match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+    }
+
+    /// Rotates every file if the rotation period has changed.
+    fn check(&self) -> io::Result<()> {
+        // The schedule is called with the lock held, as a thread which got the period before
+        // another one rotated would otherwise see it as changed as well, and rotate again.
+        let mut state = self.lock_state();
+        let period = (self.schedule)();
+        if period == state.period {
+            return Ok(());
+        }
+        let ended = mem::replace(&mut state.period, period.clone());
+        return rotate_files(&mut state.files, &ended, &period);
+    }
+}
+
+/// Rotates every file which is still open, dropping those which have been closed. Every file is
+/// rotated even if an earlier one fails, and the first error is returned afterwards.
+fn rotate_files(files: &mut Vec<sync::Weak<RotatingFile>>, ended: &str, period: &str)
+        -> io::Result<()> {
+    files.retain(|file| file.upgrade().is_some());
+    let mut result = Ok(());
+    for file in files.iter().filter_map(|file| file.upgrade()) {
+        if let Err(e) = file.rotate(ended, period, false) {
+            if result.is_ok() {
+                result = Err(e);
+            }
+        }
+    }
+    return result;
+}
+
+/// The overflow file of a `RotatingFileLogger`, see `RotatingFileLogger::overflow()`.
+struct Overflow {
+    path: path::PathBuf,
+    // Whether messages are going to the overflow file, checked before locking `state`
+    overflowing: atomic::AtomicBool,
+    state: sync::Mutex<OverflowState>,
+}
+
+struct OverflowState {
+    file: Option<fs::File>,
+    // The generation of the main file when it filled up, while messages go to the overflow file
+    overflowing: Option<usize>,
+}
+
+/// A logger writing to a file which is rotated by a `RotationCoordinator`. See
+/// `RotationCoordinator::open()`.
+///
+/// If rotating fails, for instance because the file can't be renamed, the error is returned and
+/// messages keep going to the current file.
+pub struct RotatingFileLogger {
+    file: sync::Arc<RotatingFile>,
+    line_sep: String,
+    coordinator: sync::Arc<RotationCoordinator>,
+    overflow: Option<Overflow>,
+}
+
+impl RotatingFileLogger {
+    /// Opens a file which is rotated at local midnight on its own. This is
+    /// `RotationCoordinator::daily().open(path, line_sep)`.
+    pub fn daily<P: AsRef<path::Path>>(path: P, line_sep: &str)
+            -> io::Result<RotatingFileLogger> {
+        return RotationCoordinator::daily().open(path, line_sep);
+    }
+
+    /// Sets a file to write to instead when the disk holding the log file is full, such as one
+    /// on another partition. When writing fails because there's no space left, a note saying so
+    /// is written to the overflow file, followed by the message, and all further messages go to
+    /// the overflow file as well. Once the log file has been rotated, which usually frees up
+    /// space, messages go back to it.
+    pub fn overflow<P: AsRef<path::Path>>(mut self, path: P) -> RotatingFileLogger {
+        self.overflow = Some(Overflow {
+            path: path.as_ref().to_path_buf(),
+            overflowing: atomic::AtomicBool::new(false),
+            state: sync::Mutex::new(OverflowState {
+                file: None,
+                overflowing: None,
+            }),
+        });
+        return self;
+    }
+
+    /// Limits the total size of the rotated files to `max_total_bytes`, deleting the oldest ones
+    /// after each rotation until the rest fit. The file currently being written to doesn't
+    /// count, and a rotated file larger than the limit on its own is deleted as well.
+    ///
+    /// Rotated files are found by name: every file in the same directory whose name starts with
+    /// the log file's name and a dot, such as `app.log.2016-03-01`, counts, so other files
+    /// shouldn't be named that way. Files opened by a factory are never deleted.
+    pub fn max_total_bytes(self, max_total_bytes: u64) -> RotatingFileLogger {
+        match self.file.max_total_bytes.lock() {
+            Ok(mut max) => *max = Some(max_total_bytes),
+            Err(poisoned) => *poisoned.into_inner() = Some(max_total_bytes),
+        }
+        return self;
+    }
+
+    /// Returns the path of the file currently being written to. For files opened by a factory,
+    /// this is the path without the period.
+    pub fn path(&self) -> &path::Path {
+        return &self.file.path;
+    }
+
+    /// Rotates this file right away, without waiting for the period to end, such as to seal it
+    /// before taking a backup. The file is flushed and moved to `<path>.<period>` for the
+    /// current period, with a counter added if that already exists, and a new empty file is
+    /// started. Returns the path the old file was moved to.
+    ///
+    /// Other files of the same coordinator aren't rotated. This can be called while other
+    /// threads are logging to the file, and messages are written either to the old file or the
+    /// new one, never split between them.
+    ///
+    /// For files opened by a factory, the factory is called again with the current period's
+    /// name and a counter instead, as in `app.log.2016-03-01.1`, so that a new file is always
+    /// started, and `None` is returned. The counter starts again from 1 in each period.
+    pub fn rotate_now(&self) -> io::Result<Option<path::PathBuf>> {
+        let period = self.coordinator.lock_state().period.clone();
+        return self.file.rotate(&period, &period, true);
+    }
+
+    fn write_to_file(&self, line: &[u8]) -> Result<(), LogError> {
+        return Ok(try!(try!(self.file.file.lock()).write_all(line)));
+    }
+
+    /// Writes a line to the log file, or the overflow file if the log file is full.
+    fn write_line(&self, line: &[u8]) -> Result<(), LogError> {
+        let overflow = match self.overflow {
+            Some(ref overflow) => overflow,
+            None => return self.write_to_file(line),
+        };
+        // The overflow state is only locked once the log file has filled up
+        if !overflow.overflowing.load(atomic::Ordering::SeqCst) {
+            match self.write_to_file(line) {
+                Err(LogError::Io(ref e)) if e.kind() == io::ErrorKind::StorageFull => {},
+                result => return result,
+            }
+        }
+        let mut state = try!(overflow.state.lock());
+        let generation = self.file.generation.load(atomic::Ordering::SeqCst);
+        if state.overflowing.map_or(false, |full| full != generation) {
+            // The log file has been rotated since it filled up, so try it again
+            state.overflowing = None;
+            overflow.overflowing.store(false, atomic::Ordering::SeqCst);
+            match self.write_to_file(line) {
+                Err(LogError::Io(ref e)) if e.kind() == io::ErrorKind::StorageFull => {},
+                result => return result,
+            }
+        }
+
+        if state.file.is_none() {
+            state.file = Some(try!(RotatingFile::open_file(&overflow.path, self.file.mode)));
+        }
+        let started = state.overflowing.is_none();
+        let file = state.file.as_mut().unwrap();
+        if started {
+            try!(write!(file, "fern: {} is full, logging here until it's rotated{}",
+                        self.file.path.display(), self.line_sep));
+        }
+        try!(file.write_all(line));
+        state.overflowing = Some(generation);
+        overflow.overflowing.store(true, atomic::Ordering::SeqCst);
+        return Ok(());
+    }
+}
+
+impl api::Logger for RotatingFileLogger {
+    fn log(&self, msg: &str, _level: &log::LogLevel, _location: &log::LogLocation)
+            -> Result<(), LogError> {
+        // Checked before locking the file, as rotating locks every file of the coordinator
+        let rotate_result = self.coordinator.check();
+        try!(self.write_line(format!("{}{}", msg, self.line_sep).as_bytes()));
+        return Ok(try!(rotate_result));
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        let mut file = match self.file.file.lock() {
+            Ok(file) => file,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        return Ok(try!(file.flush()));
+    }
+
+    fn needs_flush(&self) -> bool {
+        // Files fern opens itself aren't buffered, but writers from a factory may be
+        return self.file.factory.is_some();
+    }
+
+    fn describe(&self) -> String {
+        return format!("rotating file ({})", self.file.path.display());
+    }
+}
+
+impl log::Log for RotatingFileLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        loggers::log_with_fern_logger(self, record);
+    }
+}
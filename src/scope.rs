@@ -0,0 +1,93 @@
+//! A stack of named scopes on each thread, such as the operations a message was logged in, for
+//! formatters to show as a breadcrumb trail:
+//!
+//! ```rust
+//! # extern crate fern;
+//! # extern crate log;
+//! # fn main() {
+//! let format = Box::new(|msg: &str, level: &log::LogLevel, _location: &log::LogLocation| {
+//!     match fern::scope::current() {
+//!         ref scopes if scopes.is_empty() => format!("[{}] {}", level, msg),
+//!         scopes => format!("[{}] [{}] {}", level, scopes, msg),
+//!     }
+//! });
+//! let location = log::LogLocation { __module_path: "my_app", __file: "main.rs", __line: 1 };
+//! let _request = fern::scope::enter("request");
+//! let _query = fern::scope::enter("query");
+//! assert_eq!(format("slow", &log::LogLevel::Warn, &location), "[WARN] [request > query] slow");
+//! # }
+//! ```
+//!
+//! Unlike the key-value pairs of `fern::context_scope()`, scopes keep their order, and the same
+//! name can be entered more than once, as in `retry > retry`.
+//!
+//! Scopes entered with `timed()` also log how long they took when they're left, for simple
+//! profiling through the logging system.
+
+use std::cell;
+use std::marker;
+use std::time::Instant;
+
+/// Target of the messages logged by timed scopes.
+const SCOPE_TARGET: &'static str = "fern::scope";
+
+thread_local! {
+    // Names of the current thread's entered scopes, outermost first
+    static SCOPES: cell::RefCell<Vec<String>> = cell::RefCell::new(Vec::new());
+}
+
+/// Enters a scope named `name` on the current thread, until the returned guard is dropped.
+pub fn enter(name: &str) -> ScopeGuard {
+    let index = SCOPES.with(|scopes| {
+        let mut scopes = scopes.borrow_mut();
+        scopes.push(name.to_string());
+        scopes.len() - 1
+    });
+    return ScopeGuard {
+        index: index,
+        timed: None,
+        _not_send: marker::PhantomData,
+    };
+}
+
+/// Like `enter()`, but when the returned guard is dropped, also logs how long the scope took at
+/// `Debug` through the global logger, from the `fern::scope` target, as in
+/// `scope 'db_query' took 12.345678ms`. The message is logged after leaving the scope.
+pub fn timed(name: &str) -> ScopeGuard {
+    let mut guard = enter(name);
+    guard.timed = Some((name.to_string(), Instant::now()));
+    return guard;
+}
+
+/// Calls `f` with the names of the current thread's scopes, outermost first.
+pub fn with_scopes<F, R>(f: F) -> R where F: FnOnce(&[String]) -> R {
+    return SCOPES.with(|scopes| f(&scopes.borrow()));
+}
+
+/// Returns the names of the current thread's scopes, outermost first, separated by ` > `, as in
+/// `request > query`. This is empty outside of any scope.
+pub fn current() -> String {
+    return with_scopes(|scopes| scopes.join(" > "));
+}
+
+/// Leaves a scope when dropped. See `enter()`.
+///
+/// Guards are meant to be dropped in the reverse order they were created, as happens with
+/// scopes. Dropping a guard early also leaves any scopes entered after it.
+pub struct ScopeGuard {
+    index: usize,
+    // The name and start time of a scope entered with `timed()`
+    timed: Option<(String, Instant)>,
+    // The scopes are per thread, so guards can't be moved to other threads
+    _not_send: marker::PhantomData<*const ()>,
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        let index = self.index;
+        let _ = SCOPES.try_with(|scopes| scopes.borrow_mut().truncate(index));
+        if let Some((ref name, started)) = self.timed {
+            debug!(target: SCOPE_TARGET, "scope '{}' took {:?}", name, started.elapsed());
+        }
+    }
+}
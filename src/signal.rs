@@ -0,0 +1,107 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::io;
+use std::mem;
+use std::os::raw::{c_int, c_void};
+use std::os::unix::io::FromRawFd;
+use std::ptr;
+use std::sync;
+use std::sync::atomic;
+use std::thread;
+
+use libc;
+
+use loggers::DispatchLogger;
+
+/// Write end of the pipe the signal handler wakes the level thread through, or -1 before the
+/// handler is installed.
+static WAKE_FD: atomic::AtomicIsize = atomic::AtomicIsize::new(-1);
+
+/// Loggers whose level is cycled on each signal.
+static LOGGERS: sync::Mutex<Vec<sync::Arc<DispatchLogger>>> = sync::Mutex::new(Vec::new());
+
+extern "C" fn handle_sigusr1(_signum: c_int) {
+    // Only async-signal-safe calls are allowed here, so the level is changed on another thread.
+    // If the pipe is full, the thread has plenty of wake-ups to handle already.
+    let fd = WAKE_FD.load(atomic::Ordering::SeqCst);
+    if fd >= 0 {
+        let byte = 1u8;
+        unsafe { libc::write(fd as c_int, &byte as *const u8 as *const c_void, 1) };
+    }
+}
+
+/// Makes `SIGUSR1` cycle the level of `logger` with `DispatchLogger::cycle_level()`, so that
+/// running `kill -USR1 <pid>` turns up the verbosity of a running program, such as for a quick
+/// look at its debug messages. Each signal moves the level one step, from `Error` to `Warn`,
+/// `Info`, `Debug` and `Trace`, and back to `Error`, and logs the new level.
+///
+/// The signal handler only wakes a background thread, started by the first call, which then
+/// changes the level. Each call adds another logger to cycle along with the others. This
+/// replaces any other handler for `SIGUSR1`, and installs it with `SA_RESTART`, so system calls
+/// the signal interrupts elsewhere in the program are restarted rather than failing.
+///
+/// Note that the global log level given to `init_global_logger()` still applies, as the `log`
+/// crate filters messages before they reach fern, so it should be set to `Trace` for the level
+/// to go through all of the steps.
+///
+/// This is only available on Unix.
+pub fn cycle_level_on_sigusr1(logger: sync::Arc<DispatchLogger>) -> io::Result<()> {
+    let mut loggers = match LOGGERS.lock() {
+        Ok(loggers) => loggers,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    if WAKE_FD.load(atomic::Ordering::SeqCst) < 0 {
+        let mut fds = [0 as c_int; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let wake = unsafe { fs::File::from_raw_fd(fds[0]) };
+        let notify = unsafe { fs::File::from_raw_fd(fds[1]) };
+        // The handler mustn't block when the pipe is full
+        let flags = unsafe { libc::fcntl(fds[1], libc::F_GETFL) };
+        if flags < 0 ||
+           unsafe { libc::fcntl(fds[1], libc::F_SETFL, flags | libc::O_NONBLOCK) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        WAKE_FD.store(fds[1] as isize, atomic::Ordering::SeqCst);
+        let mut action: libc::sigaction = unsafe { mem::zeroed() };
+        action.sa_sigaction = handle_sigusr1 as extern "C" fn(c_int) as libc::sighandler_t;
+        // Restarts system calls the signal interrupts in the rest of the program
+        action.sa_flags = libc::SA_RESTART;
+        unsafe { libc::sigemptyset(&mut action.sa_mask) };
+        if unsafe { libc::sigaction(libc::SIGUSR1, &action, ptr::null_mut()) } != 0 {
+            let error = io::Error::last_os_error();
+            WAKE_FD.store(-1, atomic::Ordering::SeqCst);
+            return Err(error);
+        }
+        // The handler writes to the pipe for the rest of the program
+        mem::forget(notify);
+        thread::spawn(move || cycle_levels(wake));
+    }
+    loggers.push(logger);
+    return Ok(());
+}
+
+/// Cycles the level of every logger once for each signal received.
+fn cycle_levels(mut wake: fs::File) {
+    let mut buf = [0; 16];
+    loop {
+        let count = match wake.read(&mut buf) {
+            Ok(0) => return,
+            Ok(count) => count,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => return,
+        };
+        let loggers = match LOGGERS.lock() {
+            Ok(loggers) => loggers,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        for _ in 0..count {
+            for logger in loggers.iter() {
+                if let Err(e) = logger.cycle_level() {
+                    let _ = writeln!(&mut io::stderr(), "Error logging level change: {}", e);
+                }
+            }
+        }
+    }
+}
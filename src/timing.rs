@@ -0,0 +1,167 @@
+use std::sync;
+use std::sync::atomic;
+use std::time::{Duration, Instant};
+
+use log;
+
+use api;
+use errors::LogError;
+use loggers;
+
+/// Target, and module, of the stats messages logged by `TimedLogger::report_to()`.
+const TIMING_TARGET: &'static str = "fern::timing";
+
+static TIMING_LOCATION: log::LogLocation = log::LogLocation {
+    __module_path: TIMING_TARGET,
+    __file: file!(),
+    __line: 0,
+};
+
+/// Number of histogram buckets: one for zero, and one for each power of two nanoseconds.
+const BUCKETS: usize = 65;
+
+/// Statistics about the time spent logging, returned by `TimedLogger::stats()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimingStats {
+    /// Number of messages logged
+    pub count: u64,
+    /// Total time spent logging them
+    pub total: Duration,
+    /// Time within which 99% of messages were logged. This is rounded up to a power of two
+    /// nanoseconds, so it's only an estimate.
+    pub p99: Duration,
+}
+
+/// A logger measuring how long another logger takes to log each message, for deciding whether
+/// logging is slow enough to be worth moving to a background thread with a `ChannelLogger`.
+///
+/// The statistics can be read with `stats()`, or logged every so many messages with
+/// `report_to()`. Flushing isn't timed.
+pub struct TimedLogger {
+    output: Box<api::Logger>,
+    count: atomic::AtomicUsize,
+    total_nanos: atomic::AtomicU64,
+    histogram: sync::Mutex<[u64; BUCKETS]>,
+    report: Option<(Box<api::Logger>, usize)>,
+}
+
+impl TimedLogger {
+    /// Creates a logger passing messages on to `output`, and timing it.
+    pub fn new(output: Box<api::Logger>) -> TimedLogger {
+        return TimedLogger {
+            output: output,
+            count: atomic::AtomicUsize::new(0),
+            total_nanos: atomic::AtomicU64::new(0),
+            histogram: sync::Mutex::new([0; BUCKETS]),
+            report: None,
+        };
+    }
+
+    /// Logs the statistics to `sink` after every `every` messages, at `Info` from the
+    /// `fern::timing` target, as in
+    /// `logging stats: 1000 messages, 2.5ms total, p99 8.192µs`. Logging the statistics isn't
+    /// timed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `every` is 0.
+    pub fn report_to(mut self, sink: Box<api::Logger>, every: usize) -> TimedLogger {
+        assert!(every > 0, "stats can't be reported every 0 messages");
+        self.report = Some((sink, every));
+        return self;
+    }
+
+    /// Returns the statistics for every message logged so far.
+    pub fn stats(&self) -> TimingStats {
+        let histogram = match self.histogram.lock() {
+            Ok(histogram) => *histogram,
+            Err(poisoned) => *poisoned.into_inner(),
+        };
+        let count: u64 = histogram.iter().sum();
+        let target = (count * 99 + 99) / 100;
+        let mut seen = 0;
+        let mut p99 = Duration::from_secs(0);
+        for (bucket, &n) in histogram.iter().enumerate() {
+            seen += n;
+            if n > 0 && seen >= target {
+                p99 = bucket_limit(bucket);
+                break;
+            }
+        }
+        return TimingStats {
+            count: count,
+            total: Duration::from_nanos(self.total_nanos.load(atomic::Ordering::SeqCst)),
+            p99: p99,
+        };
+    }
+
+    fn record(&self, elapsed: Duration) -> usize {
+        let nanos = elapsed.as_secs().saturating_mul(1_000_000_000)
+                        .saturating_add(elapsed.subsec_nanos() as u64);
+        let bucket = 64 - nanos.leading_zeros() as usize;
+        {
+            let mut histogram = match self.histogram.lock() {
+                Ok(histogram) => histogram,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            histogram[bucket] += 1;
+        }
+        self.total_nanos.fetch_add(nanos, atomic::Ordering::SeqCst);
+        return self.count.fetch_add(1, atomic::Ordering::SeqCst) + 1;
+    }
+}
+
+/// Returns the upper limit of the times in a histogram bucket.
+fn bucket_limit(bucket: usize) -> Duration {
+    return match bucket {
+        0 => Duration::from_secs(0),
+        64 => Duration::from_nanos(u64::max_value()),
+        bucket => Duration::from_nanos(1 << bucket),
+    };
+}
+
+impl api::Logger for TimedLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation,
+            target: &str) -> Result<(), LogError> {
+        let start = Instant::now();
+        let result = self.output.log_with_target(msg, level, location, target);
+        let count = self.record(start.elapsed());
+        if let Some((ref sink, every)) = self.report {
+            if count % every == 0 {
+                let stats = self.stats();
+                let report = format!("logging stats: {} messages, {:?} total, p99 {:?}",
+                                     stats.count, stats.total, stats.p99);
+                let _ = sink.log_with_target(&report, &log::LogLevel::Info, &TIMING_LOCATION,
+                                             TIMING_TARGET);
+            }
+        }
+        return result;
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        return self.output.flush();
+    }
+
+    fn needs_flush(&self) -> bool {
+        return self.output.needs_flush();
+    }
+
+    fn describe(&self) -> String {
+        return format!("timed ({})", self.output.describe());
+    }
+}
+
+impl log::Log for TimedLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        loggers::log_with_fern_logger(self, record);
+    }
+}
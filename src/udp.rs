@@ -0,0 +1,171 @@
+use std::io;
+use std::net;
+use std::sync::atomic;
+
+use log;
+use time;
+
+use api;
+use errors::LogError;
+use loggers;
+
+/// Magic bytes starting every chunk of a chunked GELF message.
+const GELF_CHUNK_MAGIC: [u8; 2] = [0x1e, 0x0f];
+
+/// Size of the header of each chunk: the magic bytes, the 8 byte message id, the sequence number
+/// and the sequence count.
+const GELF_CHUNK_HEADER_LEN: usize = 12;
+
+/// Graylog discards chunked messages with more chunks than this.
+const GELF_MAX_CHUNKS: usize = 128;
+
+/// A logger sending each message in its own UDP datagram, without a line separator. Messages are
+/// sent as-is, so this is mostly useful with formatters such as `fern::gelf_formatter()`.
+///
+/// With `gelf_chunking()`, messages too large for one datagram are split into chunked GELF
+/// messages, which Graylog reassembles.
+pub struct UdpLogger {
+    socket: net::UdpSocket,
+    address: String,
+    chunk_size: Option<usize>,
+    next_message_id: atomic::AtomicUsize,
+    io_error_policy: loggers::IoErrorPolicy,
+    bytes_written: atomic::AtomicU64,
+}
+
+impl UdpLogger {
+    /// Creates a logger sending messages to the given address, such as `graylog:12201`, from a
+    /// new socket bound to an ephemeral port.
+    pub fn new<A: net::ToSocketAddrs>(address: A) -> io::Result<UdpLogger> {
+        let mut last_error = io::Error::new(io::ErrorKind::InvalidInput,
+                                            "no addresses to send log messages to");
+        for addr in try!(address.to_socket_addrs()) {
+            let bind_address = match addr {
+                net::SocketAddr::V4(_) => "0.0.0.0:0",
+                net::SocketAddr::V6(_) => "[::]:0",
+            };
+            let socket = try!(net::UdpSocket::bind(bind_address));
+            match socket.connect(addr) {
+                Ok(()) => return Ok(UdpLogger {
+                    socket: socket,
+                    address: addr.to_string(),
+                    chunk_size: None,
+                    next_message_id: atomic::AtomicUsize::new(0),
+                    io_error_policy: loggers::IoErrorPolicy::new(),
+                    bytes_written: atomic::AtomicU64::new(0),
+                }),
+                Err(e) => last_error = e,
+            }
+        }
+        return Err(last_error);
+    }
+
+    /// Splits messages larger than `chunk_size` bytes into chunked GELF messages of at most
+    /// `chunk_size` bytes each, including the 12 byte chunk header. Graylog recommends 1420
+    /// bytes when sending over the internet, and up to 8154 bytes within a local network.
+    ///
+    /// Messages needing more than 128 chunks, the most Graylog accepts, aren't sent, and an
+    /// error is returned instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` isn't larger than the chunk header.
+    pub fn gelf_chunking(mut self, chunk_size: usize) -> UdpLogger {
+        assert!(chunk_size > GELF_CHUNK_HEADER_LEN,
+                "GELF chunks must be larger than their {} byte header", GELF_CHUNK_HEADER_LEN);
+        self.chunk_size = Some(chunk_size);
+        return self;
+    }
+
+    /// Sets what this logger does when sending a datagram fails, by the kind of error - such as
+    /// `ConnectionRefused`, which is returned for an earlier datagram when nothing listens on
+    /// the port, or `WouldBlock`. Retries send the whole datagram again. For chunked messages,
+    /// dropping a chunk drops the rest of the message too. This defaults to
+    /// `IoErrorPolicy::new()`, retrying on `Interrupted` and failing otherwise.
+    pub fn io_error_policy(mut self, policy: loggers::IoErrorPolicy) -> UdpLogger {
+        self.io_error_policy = policy;
+        return self;
+    }
+
+    /// Returns the number of bytes sent since this logger was created, counting the headers of
+    /// chunked GELF messages.
+    pub fn bytes_written(&self) -> u64 {
+        return self.bytes_written.load(atomic::Ordering::SeqCst);
+    }
+
+    /// Returns an id for a chunked message. These only need to be unique among the messages
+    /// Graylog is reassembling at once, so the time is combined with a counter.
+    fn message_id(&self) -> [u8; 8] {
+        let now = time::get_time();
+        let counter = self.next_message_id.fetch_add(1, atomic::Ordering::SeqCst) as u64;
+        let id = ((now.sec as u64) << 32 | now.nsec as u64) ^ counter.rotate_right(16);
+        return id.to_be_bytes();
+    }
+
+    fn send(&self, bytes: &[u8]) -> io::Result<()> {
+        let chunk_size = match self.chunk_size {
+            Some(chunk_size) if bytes.len() > chunk_size => chunk_size,
+            _ => return self.send_datagram(bytes).map(|_| ()),
+        };
+        let data_len = chunk_size - GELF_CHUNK_HEADER_LEN;
+        let count = (bytes.len() + data_len - 1) / data_len;
+        if count > GELF_MAX_CHUNKS {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("message of {} bytes needs {} GELF chunks, more than the maximum of {}",
+                        bytes.len(), count, GELF_MAX_CHUNKS)));
+        }
+        let id = self.message_id();
+        let mut chunk = Vec::with_capacity(chunk_size);
+        for (sequence, data) in bytes.chunks(data_len).enumerate() {
+            chunk.clear();
+            chunk.extend_from_slice(&GELF_CHUNK_MAGIC);
+            chunk.extend_from_slice(&id);
+            chunk.push(sequence as u8);
+            chunk.push(count as u8);
+            chunk.extend_from_slice(data);
+            if !try!(self.send_datagram(&chunk)) {
+                return Ok(());
+            }
+        }
+        return Ok(());
+    }
+
+    /// Sends one datagram as the policy says, returning `Ok(false)` if it was dropped.
+    fn send_datagram(&self, datagram: &[u8]) -> io::Result<bool> {
+        return self.io_error_policy.run(|| {
+            let sent = try!(self.socket.send(datagram));
+            self.bytes_written.fetch_add(sent as u64, atomic::Ordering::SeqCst);
+            Ok(())
+        });
+    }
+}
+
+impl api::Logger for UdpLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_bytes(msg.as_bytes(), level, location);
+    }
+
+    fn log_bytes(&self, bytes: &[u8], _level: &log::LogLevel, _location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return Ok(try!(self.send(bytes)));
+    }
+
+    fn needs_flush(&self) -> bool {
+        return false;
+    }
+
+    fn describe(&self) -> String {
+        return format!("udp ({})", self.address);
+    }
+}
+
+impl log::Log for UdpLogger {
+    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        loggers::log_with_fern_logger(self, record);
+    }
+}
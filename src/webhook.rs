@@ -0,0 +1,183 @@
+use std::io::Write;
+use std::io;
+use std::sync;
+use std::sync::atomic;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log;
+
+use api;
+use config;
+use errors::LogError;
+use http;
+use loggers;
+
+/// Most messages sent in one webhook request. Others collected for the same request are only
+/// counted.
+const MAX_BATCH: usize = 20;
+
+/// A logger posting messages at or above a level, such as errors, to a webhook as alerts.
+///
+/// Each request has a JSON body of the form `{"text":"..."}`, as accepted by Slack's incoming
+/// webhooks and compatible services, with the formatted messages separated by newlines.
+/// Requests are rate-limited: after one is sent, messages are collected for the interval set
+/// with `min_interval()`, and sent together in the next one. At most 20 messages are included
+/// in each request, followed by a count of the others, as in `(and 3 more)`.
+///
+/// Requests are sent from a background thread, started when the first alert is logged, so
+/// logging never waits for the webhook. Requests which fail are reported on stderr, and never
+/// return an error to the logging thread. Messages below the level are ignored, so this is
+/// meant to be added as an extra output alongside the regular ones. Dropping this logger sends
+/// any collected messages right away, and waits for the request to finish.
+///
+/// Only plain `http://` URLs are supported - HTTPS webhooks, such as Slack's, need a relay
+/// running locally or in a trusted network.
+///
+/// This is only available with the `webhook` feature enabled.
+pub struct WebhookLogger {
+    url: String,
+    level: log::LogLevel,
+    min_interval: Duration,
+    // The channel to the background thread, and the thread, once started
+    thread: sync::Mutex<Option<(mpsc::Sender<String>, thread::JoinHandle<()>)>>,
+    // Shared with the background thread, which does the sending
+    bytes_written: sync::Arc<atomic::AtomicU64>,
+}
+
+impl WebhookLogger {
+    /// Creates a logger posting messages at `level` and above to `url`, at most once a minute.
+    pub fn new(url: &str, level: log::LogLevel) -> WebhookLogger {
+        return WebhookLogger {
+            url: url.to_string(),
+            level: level,
+            min_interval: Duration::from_secs(60),
+            thread: sync::Mutex::new(None),
+            bytes_written: sync::Arc::new(atomic::AtomicU64::new(0)),
+        };
+    }
+
+    /// Sets the least time between two requests. This defaults to one minute.
+    pub fn min_interval(mut self, interval: Duration) -> WebhookLogger {
+        self.min_interval = interval;
+        return self;
+    }
+
+    /// Returns the number of bytes posted to the webhook since this logger was created,
+    /// counting the headers of each request as well as the alerts. Requests are sent from a
+    /// background thread, so this doesn't include alerts which haven't been sent yet.
+    pub fn bytes_written(&self) -> u64 {
+        return self.bytes_written.load(atomic::Ordering::SeqCst);
+    }
+}
+
+/// Collects messages from the channel and posts them, until the logger is dropped.
+fn send_alerts(url: String, min_interval: Duration, messages: mpsc::Receiver<String>,
+               bytes_written: sync::Arc<atomic::AtomicU64>) {
+    let mut last_sent: Option<Instant> = None;
+    while let Ok(first) = messages.recv() {
+        let mut batch = vec![first];
+        let mut disconnected = false;
+        if let Some(last_sent) = last_sent {
+            let due = last_sent + min_interval;
+            loop {
+                let now = Instant::now();
+                if now >= due {
+                    break;
+                }
+                match messages.recv_timeout(due - now) {
+                    Ok(msg) => batch.push(msg),
+                    Err(mpsc::RecvTimeoutError::Timeout) => break,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => {
+                        disconnected = true;
+                        break;
+                    },
+                }
+            }
+        }
+        batch.extend(messages.try_iter());
+
+        let mut text = batch[..batch.len().min(MAX_BATCH)].join("\n");
+        if batch.len() > MAX_BATCH {
+            text.push_str(&format!("\n(and {} more)", batch.len() - MAX_BATCH));
+        }
+        let body = format!("{{\"text\":\"{}\"}}", config::json_escape(&text));
+        if let Err(e) = http::post(&url, "application/json", body.as_bytes(), &bytes_written) {
+            let _ = writeln!(&mut io::stderr(), "Error sending log alert to {}: {}", url, e);
+        }
+        last_sent = Some(Instant::now());
+        if disconnected {
+            return;
+        }
+    }
+}
+
+impl api::Logger for WebhookLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), LogError> {
+        return self.log_with_target(msg, level, location, location.__module_path);
+    }
+
+    fn log_with_target(&self, msg: &str, level: &log::LogLevel, _location: &log::LogLocation,
+            _target: &str) -> Result<(), LogError> {
+        if *level > self.level {
+            return Ok(());
+        }
+        let mut state = match self.thread.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if state.is_none() {
+            let (sender, messages) = mpsc::channel();
+            let url = self.url.clone();
+            let min_interval = self.min_interval;
+            let bytes_written = self.bytes_written.clone();
+            *state = Some((sender, thread::spawn(move || {
+                send_alerts(url, min_interval, messages, bytes_written)
+            })));
+        }
+        if let Some((ref sender, _)) = *state {
+            let _ = sender.send(msg.to_string());
+        }
+        return Ok(());
+    }
+
+    fn needs_flush(&self) -> bool {
+        return false;
+    }
+
+    fn describe(&self) -> String {
+        return format!("webhook ({}, level: {})", self.url, self.level);
+    }
+
+    fn validate(&self) -> Vec<String> {
+        return match http::resolve(&self.url) {
+            Ok(()) => Vec::new(),
+            Err(e) => vec![format!("can't resolve {}: {}", self.url, e)],
+        };
+    }
+}
+
+impl Drop for WebhookLogger {
+    fn drop(&mut self) {
+        let state = match self.thread.lock() {
+            Ok(mut state) => state.take(),
+            Err(poisoned) => poisoned.into_inner().take(),
+        };
+        if let Some((sender, thread)) = state {
+            drop(sender);
+            let _ = thread.join();
+        }
+    }
+}
+
+impl log::Log for WebhookLogger {
+    fn enabled(&self, metadata: &log::LogMetadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &log::LogRecord) {
+        loggers::log_with_fern_logger(self, record);
+    }
+}
@@ -0,0 +1,40 @@
+#![cfg(all(target_os = "android", feature = "android"))]
+extern crate log;
+extern crate fern;
+
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::sync::Mutex;
+
+static WRITES: Mutex<Vec<(c_int, String, String)>> = Mutex::new(Vec::new());
+
+unsafe extern "C" fn record_write(prio: c_int, tag: *const c_char, text: *const c_char) -> c_int {
+    let tag = CStr::from_ptr(tag).to_string_lossy().into_owned();
+    let text = CStr::from_ptr(text).to_string_lossy().into_owned();
+    WRITES.lock().unwrap().push((prio, tag, text));
+    return 1;
+}
+
+#[test]
+fn android_priority_and_tag_test() {
+    let logger = fern::AndroidLogger::with_write_fn(record_write);
+    let location = log::LogLocation {
+        __module_path: "my_app::net",
+        __file: "src/net.rs",
+        __line: 1,
+    };
+    let levels = [log::LogLevel::Error, log::LogLevel::Warn, log::LogLevel::Info,
+                  log::LogLevel::Debug, log::LogLevel::Trace];
+    for level in &levels {
+        fern::Logger::log(&logger, "message", level, &location).unwrap();
+    }
+    fern::Logger::log_with_target(&logger, "targeted", &log::LogLevel::Info, &location, "db")
+        .unwrap();
+
+    let writes = WRITES.lock().unwrap();
+    let priorities: Vec<c_int> = writes.iter().map(|&(prio, _, _)| prio).collect();
+    assert_eq!(priorities, vec![6, 5, 4, 3, 2, 4]);
+    assert_eq!(writes[0].1, "my_app::net");
+    assert_eq!(writes[0].2, "message");
+    assert_eq!(writes[5].1, "db");
+}
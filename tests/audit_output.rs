@@ -0,0 +1,43 @@
+#![cfg(not(feature = "static_max_level"))]
+#[macro_use]
+extern crate log;
+extern crate fern;
+extern crate tempdir;
+
+use std::io::prelude::*;
+use std::fs;
+
+#[test]
+fn audit_output_test() {
+    // Create a temporary directory to put the log files into for testing
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let log_file = temp_log_dir.path().join("test.log");
+    let audit_file = temp_log_dir.path().join("audit.log");
+
+    let audit = fern::WriterLogger::<fs::File>::with_file(&audit_file, "\n").unwrap();
+    let logger = fern::DispatchLogger::new(
+        Box::new(|msg, level, _location| format!("[{}] {}", level, msg)),
+        vec![fern::OutputConfig::file(&log_file)],
+        // Only log errors, apart from audit messages
+        log::LogLevelFilter::Error, vec![]).unwrap().audit(Box::new(audit));
+
+    if let Err(e) = fern::init_global_logger(logger, log::LogLevelFilter::Trace) {
+        panic!("Failed to initialize global logger: {}", e);
+    }
+
+    info!("SHOULD NOT DISPLAY");
+    info!(target: "audit", "User logged in");
+    error!("Test error message");
+
+    let read = |path: &std::path::Path| {
+        let mut log_read = fs::File::open(path).unwrap();
+        let mut buf = String::new();
+        log_read.read_to_string(&mut buf).unwrap();
+        buf
+    };
+    assert_eq!(read(&audit_file), "[INFO] User logged in\n");
+    assert_eq!(read(&log_file), "[ERROR] Test error message\n");
+
+    temp_log_dir.close().ok().expect("Failed to clean up temporary directory");
+}
@@ -0,0 +1,40 @@
+#![cfg(not(feature = "static_max_level"))]
+#[macro_use]
+extern crate log;
+extern crate fern;
+
+use std::panic;
+use std::thread;
+
+#[test]
+fn capture_test() {
+    let memory = fern::MemoryLogger::new();
+    // Capturing works through the global logger, which is why this test has its own file
+    fern::init_global_logger(fern::DispatchConfig {
+        format: Box::new(|msg, level, _location| format!("[{}] {}", level, msg)),
+        output: vec![fern::OutputConfig::custom(Box::new(memory.clone()))],
+        level: log::LogLevelFilter::Info,
+        directives: vec![],
+    }, log::LogLevelFilter::Trace).unwrap();
+
+    info!("before");
+    let lines = fern::capture(|| {
+        info!("inside");
+        debug!("below the level");
+        let inner = fern::capture(|| warn!("nested"));
+        assert_eq!(inner, vec!["[WARN] nested"]);
+        // Other threads aren't captured
+        thread::spawn(|| info!("other thread")).join().unwrap();
+        error!("failed");
+    });
+    info!("after");
+
+    assert_eq!(lines, vec!["[INFO] inside", "[ERROR] failed"]);
+    assert_eq!(memory.lines(), vec!["[INFO] before", "[INFO] other thread", "[INFO] after"]);
+
+    // A panic inside the closure doesn't leave the capture active
+    assert!(panic::catch_unwind(|| fern::capture(|| panic!("failed test"))).is_err());
+    info!("not captured");
+    assert_eq!(fern::capture(|| info!("again")), vec!["[INFO] again"]);
+    assert_eq!(memory.lines().last().unwrap(), "[INFO] not captured");
+}
@@ -0,0 +1,44 @@
+extern crate log;
+extern crate fern;
+extern crate time;
+
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn clock_cache_test() {
+    // The cached clock is global, which is why this test has its own file
+    let format = fern::Preset::Detailed.formatter();
+    let location = log::LogLocation {
+        __module_path: "my_app",
+        __file: "src/main.rs",
+        __line: 1,
+    };
+
+    // By default, every timestamp reads the clock
+    let before = fern::clock::counted_reads();
+    for _ in 0..100 {
+        format("message", &log::LogLevel::Info, &location);
+    }
+    assert_eq!(fern::clock::counted_reads() - before, 100);
+
+    fern::clock::cache_clock(Some(Duration::from_millis(5)));
+    let before = fern::clock::counted_reads();
+    let first = fern::clock::get_time();
+    for _ in 0..100000 {
+        format("message", &log::LogLevel::Info, &location);
+    }
+    thread::sleep(Duration::from_millis(50));
+    let reads = fern::clock::counted_reads() - before;
+    assert!(reads < 1000, "{} clock reads for 100000 messages", reads);
+    // The cached time is still refreshed
+    let later = fern::clock::get_time();
+    assert!(later > first, "{:?} isn't after {:?}", later, first);
+    assert!(later - first >= time::Duration::milliseconds(40), "{:?}", later - first);
+
+    fern::clock::cache_clock(None);
+    let before = fern::clock::counted_reads();
+    fern::clock::get_time();
+    fern::clock::get_time();
+    assert_eq!(fern::clock::counted_reads() - before, 2);
+}
@@ -0,0 +1,33 @@
+//! Helpers shared by the integration tests.
+
+use std::io::prelude::*;
+use std::io;
+use std::net;
+
+/// A fake HTTP server: accepts `count` requests, responding to each with 200 OK, and returns
+/// their bodies, and the total length of the requests.
+pub fn receive_requests(listener: net::TcpListener, count: usize) -> (Vec<String>, usize) {
+    let mut bodies = Vec::new();
+    let mut requests_len = 0;
+    for _ in 0..count {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = io::BufReader::new(stream);
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            requests_len += reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            if line.to_lowercase().starts_with("content-length:") {
+                content_length = line["content-length:".len()..].trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+        reader.get_mut().write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+        bodies.push(String::from_utf8(body).unwrap());
+        requests_len += content_length;
+    }
+    return (bodies, requests_len);
+}
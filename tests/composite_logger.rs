@@ -0,0 +1,42 @@
+#[macro_use]
+extern crate log;
+extern crate fern;
+
+use std::sync;
+
+/// Logger which keeps every message it receives.
+struct CapturingLogger {
+    messages: sync::Arc<sync::Mutex<Vec<String>>>,
+}
+
+impl fern::Logger for CapturingLogger {
+    fn log(&self, msg: &str, _level: &log::LogLevel, _location: &log::LogLocation)
+            -> Result<(), fern::LogError> {
+        self.messages.lock().unwrap().push(msg.to_string());
+        return Ok(());
+    }
+}
+
+#[test]
+fn composite_logger_test() {
+    let composite = sync::Arc::new(fern::CompositeLogger::new());
+    fern::init_global_logger(composite.clone(), log::LogLevelFilter::Info).unwrap();
+    info!("before any handler");
+
+    // Handlers registered after the global logger was set still receive messages
+    let first = sync::Arc::new(sync::Mutex::new(Vec::new()));
+    let second = sync::Arc::new(sync::Mutex::new(Vec::new()));
+    let first_id = composite.register(Box::new(CapturingLogger { messages: first.clone() }));
+    info!("one handler");
+    composite.register(Box::new(CapturingLogger { messages: second.clone() }));
+    info!("two handlers");
+    assert_eq!(composite.handler_count(), 2);
+
+    assert!(composite.unregister(first_id).is_some());
+    assert!(composite.unregister(first_id).is_none());
+    info!("first unregistered");
+    debug!("below the global level");
+
+    assert_eq!(*first.lock().unwrap(), vec!["one handler", "two handlers"]);
+    assert_eq!(*second.lock().unwrap(), vec!["two handlers", "first unregistered"]);
+}
@@ -0,0 +1,52 @@
+#![cfg(not(feature = "static_max_level"))]
+extern crate log;
+extern crate fern;
+extern crate tempdir;
+
+use std::io::prelude::*;
+use std::fs;
+use std::thread;
+
+fn construct_fake_log_location() -> log::LogLocation {
+    return log::LogLocation {
+        __module_path: "test",
+        __file: "tests.rs",
+        __line: 0,
+    };
+}
+
+#[test]
+fn crash_report_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+
+    let ring = fern::RingBufferLogger::new(2);
+    let logger = fern::DispatchLogger::new(Box::new(|msg, level, _location| {
+        format!("[{}] {}", level, msg)
+    }), vec![fern::OutputConfig::custom(Box::new(ring.clone()))], log::LogLevelFilter::Trace,
+        vec![]).unwrap();
+    let location = construct_fake_log_location();
+    for msg in &["starting", "connecting to the database", "connection refused"] {
+        fern::Logger::log(&logger, msg, &log::LogLevel::Info, &location).unwrap();
+    }
+
+    // This installs a panic hook for the whole process, which is why this test has its own file
+    ring.report_crashes(temp_log_dir.path());
+    let crashed = thread::Builder::new().name("worker".to_string())
+        .spawn(|| panic!("no database")).unwrap().join();
+    assert!(crashed.is_err());
+
+    let reports: Vec<_> = fs::read_dir(temp_log_dir.path()).unwrap()
+        .map(|entry| entry.unwrap().path()).collect();
+    assert_eq!(reports.len(), 1);
+    let name = reports[0].file_name().unwrap().to_string_lossy().into_owned();
+    assert!(name.starts_with("crash-") && name.ends_with(".log"), "{}", name);
+
+    let mut buf = String::new();
+    fs::File::open(&reports[0]).unwrap().read_to_string(&mut buf).unwrap();
+    let lines: Vec<&str> = buf.lines().collect();
+    assert_eq!(lines[..2], ["[INFO] connecting to the database", "[INFO] connection refused"]);
+    assert!(lines[2].starts_with("--- thread 'worker' panicked at tests/crash_report.rs:"),
+            "{}", buf);
+    assert_eq!(lines[3..], ["no database"]);
+}
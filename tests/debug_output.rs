@@ -0,0 +1,36 @@
+#![cfg(all(windows, feature = "debug_output"))]
+extern crate log;
+extern crate fern;
+
+use std::sync::Mutex;
+
+static OUTPUTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+unsafe extern "system" fn record_output(output: *const u16) {
+    let mut len = 0;
+    while *output.offset(len) != 0 {
+        len += 1;
+    }
+    let wide = std::slice::from_raw_parts(output, len as usize);
+    OUTPUTS.lock().unwrap().push(String::from_utf16(wide).unwrap());
+}
+
+#[test]
+fn debug_output_test() {
+    let logger = fern::DispatchLogger::new(
+        Box::new(|msg, level, _location| format!("[{}] {}", level, msg)),
+        vec![fern::OutputConfig::custom(Box::new(
+            fern::DebugOutputLogger::with_output_fn(record_output)))],
+        log::LogLevelFilter::Info, vec![]).unwrap();
+    let location = log::LogLocation {
+        __module_path: "my_app::ui",
+        __file: "src/ui.rs",
+        __line: 1,
+    };
+    fern::Logger::log(&logger, "window opened", &log::LogLevel::Info, &location).unwrap();
+    fern::Logger::log(&logger, "ignored", &log::LogLevel::Debug, &location).unwrap();
+    fern::Logger::log(&logger, "nul\0byte", &log::LogLevel::Warn, &location).unwrap();
+
+    assert_eq!(*OUTPUTS.lock().unwrap(),
+               vec!["[INFO] window opened\n".to_string(), "[WARN] nul\u{FFFD}byte\n".to_string()]);
+}
@@ -0,0 +1,15 @@
+#[macro_use]
+extern crate log;
+extern crate fern;
+
+#[test]
+fn init_test() {
+    fern::init().unwrap();
+    info!("Test information message");
+
+    // The global logger can only be initialized once
+    match fern::init() {
+        Err(fern::InitError::SetLoggerError(_)) => {},
+        other => panic!("Expected SetLoggerError, found {:?}", other),
+    }
+}
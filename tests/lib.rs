@@ -1,12 +1,23 @@
+// Most tests are compiled out with the max_level_* features, leaving some helpers unused
+#![cfg_attr(feature = "static_max_level", allow(unused_imports, dead_code))]
 #[macro_use]
 extern crate log;
+#[macro_use]
 extern crate fern;
 extern crate tempdir;
 
 use std::io::prelude::*;
+use std::io;
 use std::fs;
+use std::env;
+use std::net;
+use std::process;
+use std::sync;
+use std::thread;
+use std::time::Duration;
 
 #[test]
+#[cfg(not(feature = "static_max_level"))]
 fn basic_usage_test() {
     // Create a temporary directory to put a log file into for testing
     let temp_log_dir = tempdir::TempDir::new("fern").ok()
@@ -55,6 +66,7 @@ fn basic_usage_test() {
 }
 
 #[test]
+#[cfg(not(feature = "static_max_level"))]
 fn custom_line_sep_test() {
     // Create a temporary directory to put a log file into for testing
     let temp_log_dir = tempdir::TempDir::new("fern").ok()
@@ -101,6 +113,3390 @@ fn custom_line_sep_test() {
     temp_log_dir.close().ok().expect("Failed to clean up temporary directory");
 }
 
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn output_helpers_test() {
+    // Create a temporary directory to put a log file into for testing
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let log_file = temp_log_dir.path().join("test_output_helpers.log");
+
+    {
+        let logger_config = fern::DispatchConfig {
+            format: Box::new(|msg, level, _location| {
+                format!("[{}] {}", level, msg)
+            }),
+            // The file helper opens the file right away, so errors show up here
+            output: outputs![fern::file(&log_file).unwrap()],
+            level: log::LogLevelFilter::Trace,
+            directives: vec![]
+        };
+        let fern_logger = fern::IntoLog::into_fern_logger(logger_config).unwrap();
+
+        let location = construct_fake_log_location();
+        fern_logger.log("message1", &log::LogLevel::Info, &location).unwrap();
+    }
+    {
+        let mut log_read = fs::File::open(&log_file).unwrap();
+        let mut buf = String::new();
+        log_read.read_to_string(&mut buf).unwrap();
+        assert_eq!(&buf, "[INFO] message1\n");
+    }
+
+    // Opening a file in a directory which doesn't exist should fail immediately
+    assert!(fern::file(&temp_log_dir.path().join("missing").join("test.log")).is_err());
+
+    temp_log_dir.close().ok().expect("Failed to clean up temporary directory");
+}
+
+/// Writer which acts like a pipe whose reading end has been closed.
+struct ClosedPipe {
+    attempts: sync::Arc<sync::atomic::AtomicUsize>,
+}
+
+impl io::Write for ClosedPipe {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        self.attempts.fetch_add(1, sync::atomic::Ordering::SeqCst);
+        return Err(io::Error::new(io::ErrorKind::BrokenPipe, "pipe closed"));
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return Ok(());
+    }
+}
+
+#[test]
+fn broken_pipe_test() {
+    let location = construct_fake_log_location();
+    let level = log::LogLevel::Info;
+
+    // By default, a broken pipe is an error like any other
+    let attempts = sync::Arc::new(sync::atomic::AtomicUsize::new(0));
+    let logger = fern::WriterLogger::new(ClosedPipe { attempts: attempts.clone() }, "\n");
+    assert!(fern::Logger::log(&logger, "message1", &level, &location).is_err());
+
+    // When ignored, logging stops silently after the first failure
+    let attempts = sync::Arc::new(sync::atomic::AtomicUsize::new(0));
+    let logger = fern::WriterLogger::new(ClosedPipe { attempts: attempts.clone() }, "\n")
+                    .ignore_broken_pipe(true);
+    for _ in 0..10 {
+        fern::Logger::log(&logger, "message", &level, &location).unwrap();
+    }
+    assert_eq!(attempts.load(sync::atomic::Ordering::SeqCst), 1);
+}
+
+/// Writer which fails with the given errors, in order, before writing at most 4 bytes at a time.
+struct FailingWriter {
+    errors: Vec<io::ErrorKind>,
+    written: sync::Arc<sync::Mutex<Vec<u8>>>,
+}
+
+impl io::Write for FailingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.errors.is_empty() {
+            return Err(io::Error::new(self.errors.remove(0), "failing writer"));
+        }
+        let count = buf.len().min(4);
+        self.written.lock().unwrap().extend_from_slice(&buf[..count]);
+        return Ok(count);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return Ok(());
+    }
+}
+
+#[test]
+fn io_error_policy_test() {
+    let location = construct_fake_log_location();
+    let level = log::LogLevel::Info;
+    let failing = |errors: Vec<io::ErrorKind>| {
+        let written = sync::Arc::new(sync::Mutex::new(Vec::new()));
+        return (FailingWriter { errors: errors, written: written.clone() }, written);
+    };
+
+    // By default, interrupted writes are retried, without writing any part twice
+    let (writer, written) = failing(vec![io::ErrorKind::Interrupted, io::ErrorKind::Interrupted]);
+    let logger = fern::WriterLogger::new(writer, "\n");
+    fern::Logger::log(&logger, "first message", &level, &location).unwrap();
+    assert_eq!(&*written.lock().unwrap(), b"first message\n");
+
+    // Other errors are returned
+    let (writer, written) = failing(vec![io::ErrorKind::PermissionDenied]);
+    let logger = fern::WriterLogger::new(writer, "\n");
+    match fern::Logger::log(&logger, "denied", &level, &location) {
+        Err(fern::LogError::Io(ref e)) => assert_eq!(e.kind(), io::ErrorKind::PermissionDenied),
+        other => panic!("expected a permission error, got {:?}", other),
+    }
+    fern::Logger::log(&logger, "second", &level, &location).unwrap();
+    assert_eq!(&*written.lock().unwrap(), b"second\n");
+
+    // Retries give up after the maximum
+    let (writer, _) = failing(vec![io::ErrorKind::Interrupted; 3]);
+    let logger = fern::WriterLogger::new(writer, "\n")
+        .io_error_policy(fern::IoErrorPolicy::new().max_retries(2));
+    assert!(fern::Logger::log(&logger, "interrupted", &level, &location).is_err());
+
+    // Dropped messages are counted, and retried kinds can be added
+    let policy = fern::IoErrorPolicy::new()
+        .on(io::ErrorKind::WouldBlock, fern::IoErrorAction::Retry)
+        .otherwise(fern::IoErrorAction::Drop);
+    let (writer, written) = failing(vec![io::ErrorKind::WouldBlock, io::ErrorKind::Other]);
+    let logger = fern::WriterLogger::new(writer, "\n").io_error_policy(policy);
+    fern::Logger::log(&logger, "dropped", &level, &location).unwrap();
+    fern::Logger::log(&logger, "kept", &level, &location).unwrap();
+    assert_eq!(&*written.lock().unwrap(), b"kept\n");
+    assert_eq!(logger.dropped_count(), 1);
+}
+
+#[test]
+fn flush_targets_test() {
+    let files = sync::Arc::new(sync::Mutex::new(vec![("buffered".to_string(), Vec::new())]));
+    let writer = NamedBufferWriter { name: "buffered".to_string(), files: files.clone() };
+    let logger = fern::WriterLogger::new(io::BufWriter::new(writer), "\n")
+        .flush_targets(&["audit"]);
+    let written = || String::from_utf8(files.lock().unwrap()[0].1.clone()).unwrap();
+
+    let location = construct_fake_log_location();
+    let level = log::LogLevel::Info;
+    fern::Logger::log(&logger, "buffered", &level, &location).unwrap();
+    assert_eq!(written(), "");
+
+    // The audit message is flushed right away, along with what was buffered before it
+    fern::Logger::log_with_target(&logger, "audited", &level, &location, "audit").unwrap();
+    assert_eq!(written(), "buffered\naudited\n");
+
+    fern::Logger::log_with_target(&logger, "not audited", &level, &location, "audit::sub")
+        .unwrap();
+    assert_eq!(written(), "buffered\naudited\n");
+    fern::Logger::flush(&logger).unwrap();
+    assert_eq!(written(), "buffered\naudited\nnot audited\n");
+}
+
+#[test]
+fn bytes_written_test() {
+    let location = construct_fake_log_location();
+    let level = log::LogLevel::Info;
+
+    // Partial writes and the line separator are counted
+    let (writer, written) = {
+        let written = sync::Arc::new(sync::Mutex::new(Vec::new()));
+        (FailingWriter { errors: vec![io::ErrorKind::Interrupted], written: written.clone() },
+         written)
+    };
+    let logger = fern::WriterLogger::new(writer, "\r\n");
+    for msg in &["first", "second message", "ünïcode"] {
+        fern::Logger::log(&logger, msg, &level, &location).unwrap();
+    }
+    fern::Logger::log_bytes(&logger, &[0xff, 0x00], &level, &location).unwrap();
+    assert_eq!(logger.bytes_written(), written.lock().unwrap().len() as u64);
+    assert_eq!(logger.bytes_written(), (5 + 14 + 9 + 2 + 4 * 2) as u64);
+
+    let receiver = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let logger = fern::UdpLogger::new(receiver.local_addr().unwrap()).unwrap();
+    fern::Logger::log(&logger, "datagram", &level, &location).unwrap();
+    fern::Logger::log(&logger, "another", &level, &location).unwrap();
+    assert_eq!(logger.bytes_written(), 15);
+}
+
+#[test]
+fn dispatch_introspection_test() {
+    let child = fern::DispatchConfig {
+        format: Box::new(|msg, _level, _location| msg.to_string()),
+        output: vec![fern::OutputConfig::null()],
+        level: log::LogLevelFilter::Warn,
+        directives: vec![]
+    };
+    let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+        vec![fern::OutputConfig::stdout(), fern::OutputConfig::stderr(),
+             fern::OutputConfig::child(child)],
+        log::LogLevelFilter::Info, vec![]).unwrap();
+
+    assert_eq!(logger.output_count(), 3);
+    assert_eq!(logger.level(), log::LogLevelFilter::Info);
+    assert_eq!(logger.output_descriptions(), vec![
+        "stdout".to_string(),
+        "stderr".to_string(),
+        "dispatch (level: WARN, outputs: [null])".to_string(),
+    ]);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn instance_tag_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let log_file = temp_log_dir.path().join("test_instance_tag.log");
+
+    {
+        let logger = fern::DispatchLogger::new(
+            Box::new(|msg, level, _location| format!("[{}] {}", level, msg)),
+            vec![fern::OutputConfig::file(&log_file)],
+            log::LogLevelFilter::Trace, vec![]).unwrap().instance_tag("v1.2.3");
+
+        let location = construct_fake_log_location();
+        fern::Logger::log(&logger, "message1", &log::LogLevel::Info, &location).unwrap();
+        fern::Logger::log(&logger, "message2", &log::LogLevel::Warn, &location).unwrap();
+    }
+    {
+        let mut log_read = fs::File::open(&log_file).unwrap();
+        let mut buf = String::new();
+        log_read.read_to_string(&mut buf).unwrap();
+        assert_eq!(&buf, "[v1.2.3] [INFO] message1\n[v1.2.3] [WARN] message2\n");
+    }
+
+    temp_log_dir.close().ok().expect("Failed to clean up temporary directory");
+}
+
+#[test]
+fn thread_local_writer_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let log_file = temp_log_dir.path().join("test_thread_local.log");
+
+    {
+        let logger = sync::Arc::new(
+            fern::ThreadLocalWriterLogger::new(&log_file, "\n", 4096).unwrap());
+
+        let threads: Vec<_> = (0..4).map(|thread_num| {
+            let logger = logger.clone();
+            thread::spawn(move || {
+                let location = construct_fake_log_location();
+                for i in 0..100 {
+                    fern::Logger::log(&*logger, &format!("thread {} message {}", thread_num, i),
+                                      &log::LogLevel::Info, &location).unwrap();
+                }
+                // Buffers are written when the thread exits
+            })
+        }).collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+    {
+        let mut log_read = fs::File::open(&log_file).unwrap();
+        let mut buf = String::new();
+        log_read.read_to_string(&mut buf).unwrap();
+        assert_eq!(buf.lines().count(), 400);
+        // Each thread's messages stay in order relative to each other
+        for thread_num in 0..4 {
+            let prefix = format!("thread {} message ", thread_num);
+            let numbers: Vec<usize> = buf.lines().filter(|line| line.starts_with(&prefix))
+                .map(|line| line[prefix.len()..].parse().unwrap()).collect();
+            assert_eq!(numbers, (0..100).collect::<Vec<_>>());
+        }
+    }
+
+    temp_log_dir.close().ok().expect("Failed to clean up temporary directory");
+}
+
+#[test]
+fn thread_local_writer_flush_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let log_file = temp_log_dir.path().join("test_thread_local_flush.log");
+    let read_lines = || {
+        let mut buf = String::new();
+        fs::File::open(&log_file).unwrap().read_to_string(&mut buf).unwrap();
+        buf.lines().count()
+    };
+
+    let logger = sync::Arc::new(fern::ThreadLocalWriterLogger::new(&log_file, "\n", 4096).unwrap());
+    let (logged_send, logged_recv) = sync::mpsc::channel();
+    let (threads, releases): (Vec<_>, Vec<_>) = (0..4).map(|thread_num| {
+        let logger = logger.clone();
+        let logged_send = logged_send.clone();
+        let (release_send, release_recv) = sync::mpsc::channel::<()>();
+        let thread = thread::spawn(move || {
+            let location = construct_fake_log_location();
+            fern::Logger::log(&*logger, &format!("thread {} first", thread_num),
+                              &log::LogLevel::Info, &location).unwrap();
+            logged_send.send(()).unwrap();
+            let _ = release_recv.recv();
+            fern::Logger::log(&*logger, &format!("thread {} second", thread_num),
+                              &log::LogLevel::Info, &location).unwrap();
+            drop(logger);
+            logged_send.send(()).unwrap();
+            // Stay alive, so that only dropping the logger writes the second message
+            let _ = release_recv.recv();
+        });
+        (thread, release_send)
+    }).unzip();
+
+    for _ in 0..4 {
+        logged_recv.recv().unwrap();
+    }
+    assert_eq!(read_lines(), 0);
+    // Flushing from this thread writes out the buffers of the others
+    fern::Logger::flush(&*logger).unwrap();
+    assert_eq!(read_lines(), 4);
+
+    for release in &releases {
+        release.send(()).unwrap();
+        logged_recv.recv().unwrap();
+    }
+    drop(sync::Arc::try_unwrap(logger).ok().expect("logger still shared"));
+    assert_eq!(read_lines(), 8);
+
+    drop(releases);
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    temp_log_dir.close().ok().expect("Failed to clean up temporary directory");
+}
+
+/// Logger which tells the test when it receives its first message, and then waits for the test
+/// to let it continue.
+struct BlockingLogger {
+    started: sync::Mutex<sync::mpsc::Sender<()>>,
+    release: sync::Mutex<sync::mpsc::Receiver<()>>,
+}
+
+impl fern::Logger for BlockingLogger {
+    fn log(&self, _msg: &str, _level: &log::LogLevel, _location: &log::LogLocation)
+            -> Result<(), fern::LogError> {
+        let _ = self.started.lock().unwrap().send(());
+        let _ = self.release.lock().unwrap().recv();
+        return Ok(());
+    }
+}
+
+#[test]
+fn channel_queue_metrics_test() {
+    let (started_sender, started) = sync::mpsc::channel();
+    let (release, release_receiver) = sync::mpsc::channel();
+    let blocking = BlockingLogger {
+        started: sync::Mutex::new(started_sender),
+        release: sync::Mutex::new(release_receiver),
+    };
+    let logger = fern::ChannelLogger::new(Box::new(blocking), 5, fern::QueueFullMode::Drop);
+
+    let location = construct_fake_log_location();
+    let level = log::LogLevel::Info;
+
+    // The first message is taken off the queue right away, and then blocks the background thread
+    fern::Logger::log(&logger, "first", &level, &location).unwrap();
+    started.recv().unwrap();
+
+    // The rest fill the queue, and anything past its capacity is dropped
+    for _ in 0..8 {
+        fern::Logger::log(&logger, "message", &level, &location).unwrap();
+    }
+    let (queue_len, high_water_mark, dropped_count) =
+        (logger.queue_len(), logger.high_water_mark(), logger.dropped_count());
+
+    // Let the background thread finish before checking, so a failure doesn't hang the test
+    for _ in 0..6 {
+        release.send(()).unwrap();
+    }
+    drop(logger);
+
+    assert_eq!(queue_len, 5);
+    assert_eq!(high_water_mark, 5);
+    assert_eq!(dropped_count, 3);
+}
+
+#[test]
+fn once_logger_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let log_file = temp_log_dir.path().join("test_once.log");
+
+    {
+        let output = fern::WriterLogger::<fs::File>::with_file(&log_file, "\n").unwrap();
+        let logger = fern::OnceLogger::new(Box::new(output));
+
+        let level = log::LogLevel::Info;
+        let location = construct_fake_log_location();
+        let other_location = log::LogLocation { __line: 1, .. location };
+        for i in 0..10 {
+            fern::Logger::log(&logger, &format!("loop {}", i), &level, &location).unwrap();
+        }
+        fern::Logger::log(&logger, "other", &level, &other_location).unwrap();
+    }
+    {
+        let mut log_read = fs::File::open(&log_file).unwrap();
+        let mut buf = String::new();
+        log_read.read_to_string(&mut buf).unwrap();
+        assert_eq!(&buf, "loop 0\nother\n");
+    }
+
+    temp_log_dir.close().ok().expect("Failed to clean up temporary directory");
+}
+
+#[test]
+fn logger_ext_test() {
+    use fern::LoggerExt;
+
+    let memory = fern::MemoryLogger::new();
+    let tee = fern::MemoryLogger::new();
+    let logger = memory.clone()
+        .truncated(5)
+        .deduplicated()
+        .filtered(Box::new(|msg| !msg.contains("skip")))
+        .tee(Box::new(tee.clone()));
+    assert_eq!(fern::Logger::describe(&logger),
+               "tee (filtered (deduplicated (truncated (5 chars, memory))), memory)");
+
+    let location = construct_fake_log_location();
+    for msg in &["hello world", "hello there", "hello there", "skip me", "hi"] {
+        fern::Logger::log(&logger, msg, &log::LogLevel::Info, &location).unwrap();
+    }
+    // The last combinator sees messages first, so repeats are found before truncating
+    assert_eq!(memory.lines(), vec!["hello", "hello", "hi"]);
+    assert_eq!(tee.lines().len(), 5);
+
+    let memory = fern::MemoryLogger::new();
+    let logger = memory.clone().rate_limited(Duration::from_secs(60));
+    for _ in 0..3 {
+        fern::Logger::log(&logger, "from one call site", &log::LogLevel::Info, &location)
+            .unwrap();
+    }
+    assert_eq!(memory.lines().len(), 1);
+}
+
+#[test]
+fn sampling_logger_test() {
+    let memory = fern::MemoryLogger::new();
+    let logger = fern::SamplingLogger::new(Box::new(memory.clone()), 10, 100);
+    assert_eq!(fern::Logger::describe(&logger), "sampled (first 10, then 1 in 100, memory)");
+
+    let level = log::LogLevel::Info;
+    let location = construct_fake_log_location();
+    let other_location = log::LogLocation { __line: 1, .. location };
+    for i in 1..311 {
+        fern::Logger::log(&logger, &format!("loop {}", i), &level, &location).unwrap();
+    }
+    // Each call site has its own head
+    fern::Logger::log(&logger, "other", &level, &other_location).unwrap();
+
+    let mut expected: Vec<String> = (1..11).map(|i| format!("loop {}", i)).collect();
+    expected.extend(vec!["loop 110".to_string(), "loop 210".to_string(),
+                         "loop 310".to_string(), "other".to_string()]);
+    assert_eq!(memory.lines(), expected);
+}
+
+#[test]
+fn storm_logger_test() {
+    let memory = fern::MemoryLogger::new();
+    let logger = fern::StormLogger::new(Box::new(memory.clone()), 3,
+                                        Duration::from_millis(500));
+    let location = construct_fake_log_location();
+    let error = log::LogLevel::Error;
+
+    // Errors at a normal rate are passed on, repeats included
+    for _ in 0..3 {
+        fern::Logger::log(&logger, "disk failed", &error, &location).unwrap();
+    }
+    assert!(!logger.in_storm());
+
+    // A burst sets off the storm, after which each distinct error is passed on once
+    for _ in 0..10 {
+        fern::Logger::log(&logger, "disk failed", &error, &location).unwrap();
+    }
+    fern::Logger::log(&logger, "retrying", &log::LogLevel::Info, &location).unwrap();
+    fern::Logger::log(&logger, "controller failed", &error, &location).unwrap();
+    assert!(logger.in_storm());
+    assert_eq!(memory.records(), vec![
+        (error, "disk failed".to_string()),
+        (error, "disk failed".to_string()),
+        (error, "disk failed".to_string()),
+        (error, "log storm detected: more than 3 ERROR messages in 0.5s, collapsing repeats"
+                .to_string()),
+        (error, "disk failed".to_string()),
+        (log::LogLevel::Info, "retrying".to_string()),
+        (error, "controller failed".to_string()),
+    ]);
+    memory.clear();
+
+    // The storm ends once a window passes without a burst
+    thread::sleep(Duration::from_millis(600));
+    fern::Logger::log(&logger, "disk failed", &error, &location).unwrap();
+    thread::sleep(Duration::from_millis(600));
+    fern::Logger::flush(&logger).unwrap();
+    assert!(!logger.in_storm());
+    assert_eq!(memory.lines(), vec![
+        "log storm continuing: 9 repeated ERROR messages suppressed",
+        "log storm ended: 1 repeated ERROR messages suppressed",
+    ]);
+}
+
+#[test]
+fn summarizing_logger_test() {
+    let memory = fern::MemoryLogger::new();
+    let logger = fern::SummarizingLogger::new(Box::new(memory.clone()), log::LogLevel::Trace,
+                                              Duration::from_millis(100));
+    let location = construct_fake_log_location();
+    let log = |msg, level| fern::Logger::log(&logger, msg, &level, &location).unwrap();
+
+    for _ in 0..3 {
+        log("trace", log::LogLevel::Trace);
+    }
+    log("debug", log::LogLevel::Debug);
+    thread::sleep(Duration::from_millis(120));
+    // The first message after the interval passes on the summary
+    log("trace", log::LogLevel::Trace);
+    for _ in 0..4 {
+        log("trace", log::LogLevel::Trace);
+    }
+    thread::sleep(Duration::from_millis(120));
+    log("info", log::LogLevel::Info);
+    // Nothing was counted in this interval, so there's no summary
+    thread::sleep(Duration::from_millis(120));
+    log("warn", log::LogLevel::Warn);
+    log("trace", log::LogLevel::Trace);
+    drop(logger);
+
+    let records = memory.records();
+    assert_eq!(records.iter().map(|&(_, ref msg)| msg.split(" in the last ").next().unwrap())
+                      .collect::<Vec<_>>(),
+               vec!["debug", "4 TRACE messages", "4 TRACE messages", "info", "warn",
+                    "1 TRACE messages"]);
+    // [1] "4 TRACE messages in the last 0.1s"
+    assert!(records[1].1.ends_with("s"), "{}", records[1].1);
+    assert!(records[1].1["4 TRACE messages in the last ".len()..].trim_end_matches('s')
+                      .parse::<f64>().unwrap() >= 0.1);
+    assert_eq!(records[1].0, log::LogLevel::Info);
+}
+
+#[test]
+fn timed_logger_test() {
+    let memory = fern::MemoryLogger::new();
+    let reports = fern::MemoryLogger::new();
+    let logger = fern::TimedLogger::new(Box::new(memory.clone()))
+                    .report_to(Box::new(reports.clone()), 4);
+    assert_eq!(fern::TimedLogger::new(Box::new(memory.clone())).stats(),
+               fern::TimingStats { count: 0, total: Duration::from_secs(0),
+                                   p99: Duration::from_secs(0) });
+
+    let location = construct_fake_log_location();
+    for i in 0..10 {
+        fern::Logger::log(&logger, &format!("message {}", i), &log::LogLevel::Info, &location)
+            .unwrap();
+    }
+    let stats = logger.stats();
+    assert_eq!(stats.count, 10);
+    assert_eq!(memory.lines().len(), 10);
+    assert!(stats.p99 > Duration::from_secs(0) && stats.p99 < Duration::from_secs(1));
+
+    let reports = reports.lines();
+    assert_eq!(reports.len(), 2);
+    assert!(reports[0].starts_with("logging stats: 4 messages, "), "{}", reports[0]);
+    assert!(reports[1].starts_with("logging stats: 8 messages, "), "{}", reports[1]);
+}
+
+#[test]
+fn cef_formatter_test() {
+    let format = fern::cef_formatter("Security", "threat|manager", "1.0");
+    let location = construct_fake_log_location();
+
+    // Examples from the CEF specification
+    assert_eq!(format("detected a = sign in the message", &log::LogLevel::Warn, &location),
+               "CEF:0|Security|threat\\|manager|1.0|test|WARN|6|\
+                msg=detected a \\= sign in the message");
+    assert_eq!(format("path C:\\windows\nnext line", &log::LogLevel::Error, &location),
+               "CEF:0|Security|threat\\|manager|1.0|test|ERROR|9|\
+                msg=path C:\\\\windows\\nnext line");
+}
+
+#[test]
+fn gelf_formatter_test() {
+    let format = fern::gelf_formatter("web-\"1\"");
+    let location = construct_fake_log_location();
+
+    let message = format("connection\nlost", &log::LogLevel::Warn, &location);
+    let prefix = "{\"version\":\"1.1\",\"host\":\"web-\\\"1\\\"\",\
+                  \"short_message\":\"connection\\nlost\",\"timestamp\":";
+    let suffix = ",\"level\":4,\"_target\":\"test\",\"_file\":\"tests.rs\",\"_line\":0}";
+    assert!(message.starts_with(prefix), "{}", message);
+    assert!(message.ends_with(suffix), "{}", message);
+    // GELF timestamps are seconds since the epoch, with optional decimal milliseconds
+    let timestamp = &message[prefix.len()..message.len() - suffix.len()];
+    assert!(timestamp.parse::<f64>().unwrap() > 1_000_000_000.0, "{}", timestamp);
+    assert_eq!(timestamp.split('.').nth(1).map(|ms| ms.len()), Some(3));
+
+    for &(level, severity) in &[(log::LogLevel::Error, 3), (log::LogLevel::Info, 6),
+                                (log::LogLevel::Debug, 7), (log::LogLevel::Trace, 7)] {
+        assert!(format("m", &level, &location).contains(&format!(",\"level\":{},", severity)));
+    }
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn single_line_test() {
+    let location = construct_fake_log_location();
+    let escaped = fern::single_line(Box::new(|msg, level, _location| {
+        format!("[{}] {}", level, msg)
+    }), "\\n");
+    assert_eq!(escaped("panicked\n  at main.rs:3\r\n  at lib.rs:7\r", &log::LogLevel::Error,
+                       &location),
+               "[ERROR] panicked\\n  at main.rs:3\\n  at lib.rs:7\\n");
+    assert_eq!(escaped("one line", &log::LogLevel::Info, &location), "[INFO] one line");
+
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let log_file = temp_log_dir.path().join("single_line.log");
+    {
+        let logger = fern::DispatchLogger::new(
+            fern::single_line(Box::new(|msg, _level, _location| msg.to_string()), " | "),
+            vec![fern::OutputConfig::file(&log_file)], log::LogLevelFilter::Info,
+            vec![]).unwrap();
+        fern::Logger::log(&logger, "first\nsecond", &log::LogLevel::Info, &location).unwrap();
+        fern::Logger::log(&logger, "third", &log::LogLevel::Info, &location).unwrap();
+    }
+    let mut contents = String::new();
+    fs::File::open(&log_file).unwrap().read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "first | second\nthird\n");
+}
+
+#[test]
+fn timestamped_file_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+
+    // These are almost certainly created in the same second, but must not share a file
+    let first = fern::WriterLogger::<fs::File>::with_timestamped_file(
+        temp_log_dir.path(), "app.", ".log", "\n").unwrap();
+    let second = fern::WriterLogger::<fs::File>::with_timestamped_file(
+        temp_log_dir.path(), "app.", ".log", "\n").unwrap();
+    assert!(fern::Logger::describe(&first) != fern::Logger::describe(&second));
+
+    let location = construct_fake_log_location();
+    fern::Logger::log(&first, "first run", &log::LogLevel::Info, &location).unwrap();
+    fern::Logger::log(&second, "second run", &log::LogLevel::Info, &location).unwrap();
+
+    let mut contents: Vec<String> = fs::read_dir(temp_log_dir.path()).unwrap().map(|entry| {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_str().unwrap().to_string();
+        assert!(name.starts_with("app.") && name.ends_with(".log"));
+        let mut buf = String::new();
+        fs::File::open(&path).unwrap().read_to_string(&mut buf).unwrap();
+        buf
+    }).collect();
+    contents.sort();
+    assert_eq!(contents, vec!["first run\n".to_string(), "second run\n".to_string()]);
+
+    temp_log_dir.close().ok().expect("Failed to clean up temporary directory");
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn empty_output_warning_test() {
+    // The warning goes to stderr, so this test runs itself in a child process to capture it.
+    if env::var("FERN_EMPTY_OUTPUT_TEST_CHILD").is_ok() {
+        let location = construct_fake_log_location();
+        let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+            vec![], log::LogLevelFilter::Info, vec![]).unwrap();
+        for _ in 0..5 {
+            fern::Logger::log(&logger, "message", &log::LogLevel::Info, &location).unwrap();
+        }
+        let quiet = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+            vec![], log::LogLevelFilter::Info, vec![]).unwrap()
+            .on_empty_output(fern::EmptyOutput::Discard);
+        fern::Logger::log(&quiet, "message", &log::LogLevel::Info, &location).unwrap();
+        return;
+    }
+
+    let output = process::Command::new(env::current_exe().unwrap())
+        .args(&["--exact", "empty_output_warning_test", "--nocapture", "--test-threads=1"])
+        .env("FERN_EMPTY_OUTPUT_TEST_CHILD", "1")
+        .output().unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stderr.matches("fern logger has no outputs").count(), 1);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn log_bytes_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let raw_file = temp_log_dir.path().join("test_raw.log");
+    let dispatch_file = temp_log_dir.path().join("test_dispatch.log");
+
+    let location = construct_fake_log_location();
+    let level = log::LogLevel::Info;
+    let payload = b"binary \xff\x00 payload";
+    {
+        // Writer loggers write the bytes unchanged
+        let raw = fern::WriterLogger::<fs::File>::with_file(&raw_file, "\n").unwrap();
+        fern::Logger::log_bytes(&raw, payload, &level, &location).unwrap();
+
+        // Other loggers see the payload converted lossily
+        let dispatch = fern::DispatchLogger::new(
+            Box::new(|msg, _level, _location| format!("<{}>", msg)),
+            vec![fern::OutputConfig::file(&dispatch_file)],
+            log::LogLevelFilter::Trace, vec![]).unwrap();
+        fern::Logger::log_bytes(&dispatch, payload, &level, &location).unwrap();
+    }
+
+    let mut buf = Vec::new();
+    fs::File::open(&raw_file).unwrap().read_to_end(&mut buf).unwrap();
+    assert_eq!(&buf[..], &b"binary \xff\x00 payload\n"[..]);
+
+    let mut buf = String::new();
+    fs::File::open(&dispatch_file).unwrap().read_to_string(&mut buf).unwrap();
+    assert_eq!(&buf, "<binary \u{fffd}\u{0} payload>\n");
+
+    temp_log_dir.close().ok().expect("Failed to clean up temporary directory");
+}
+
+#[test]
+fn test_tee_logger_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let log_file = temp_log_dir.path().join("test_tee.log");
+
+    {
+        let capture = fern::WriterLogger::<fs::File>::with_file(&log_file, "\n").unwrap();
+        let logger = fern::TestTeeLogger::new(Box::new(capture)).mirror_to_stdout(true);
+        assert!(fern::Logger::describe(&logger).starts_with("test tee (stdout, file ("));
+
+        let location = construct_fake_log_location();
+        fern::Logger::log(&logger, "mirrored message", &log::LogLevel::Info, &location).unwrap();
+    }
+
+    // Mirroring doesn't affect what the capture sink receives
+    let mut buf = String::new();
+    fs::File::open(&log_file).unwrap().read_to_string(&mut buf).unwrap();
+    assert_eq!(&buf, "mirrored message\n");
+
+    temp_log_dir.close().ok().expect("Failed to clean up temporary directory");
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn dispatch_line_sep_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let default_file = temp_log_dir.path().join("default.log");
+    let explicit_file = temp_log_dir.path().join("explicit.log");
+    let child_file = temp_log_dir.path().join("child.log");
+
+    {
+        let child = fern::DispatchConfig {
+            format: Box::new(|msg, _level, _location| msg.to_string()),
+            output: vec![fern::OutputConfig::file(&child_file)],
+            level: log::LogLevelFilter::Info,
+            directives: vec![],
+        };
+        let logger = fern::DispatchLogger::with_line_sep(
+            Box::new(|msg, _level, _location| msg.to_string()),
+            vec![fern::OutputConfig::file(&default_file),
+                 fern::OutputConfig::file_with_line_sep(&explicit_file, "\n"),
+                 fern::OutputConfig::child(child)],
+            log::LogLevelFilter::Info, vec![], "\r\n").unwrap();
+        let location = construct_fake_log_location();
+        fern::Logger::log(&logger, "message", &log::LogLevel::Info, &location).unwrap();
+    }
+
+    let read = |path: &::std::path::Path| {
+        let mut buf = String::new();
+        fs::File::open(path).unwrap().read_to_string(&mut buf).unwrap();
+        buf
+    };
+    // Only outputs without their own separator use the dispatcher's
+    assert_eq!(read(&default_file), "message\r\n");
+    assert_eq!(read(&explicit_file), "message\n");
+    assert_eq!(read(&child_file), "message\r\n");
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn matching_output_test() {
+    let all = fern::MemoryLogger::new();
+    let alerts = fern::MemoryLogger::new();
+    let logger = fern::DispatchLogger::new(
+        Box::new(|msg, level, _location| format!("[{}] {}", level, msg)),
+        vec![fern::OutputConfig::custom(Box::new(all.clone()))], log::LogLevelFilter::Info,
+        vec![]).unwrap()
+        .matching_output(Box::new(|msg| msg.contains("PANIC") || msg.starts_with("[ERROR]")),
+                         Box::new(alerts.clone()));
+    assert_eq!(logger.output_count(), 2);
+
+    let location = construct_fake_log_location();
+    fern::Logger::log(&logger, "all good", &log::LogLevel::Info, &location).unwrap();
+    fern::Logger::log(&logger, "PANIC in worker", &log::LogLevel::Warn, &location).unwrap();
+    fern::Logger::log(&logger, "disk failed", &log::LogLevel::Error, &location).unwrap();
+
+    assert_eq!(all.lines(), vec!["[INFO] all good", "[WARN] PANIC in worker",
+                                 "[ERROR] disk failed"]);
+    assert_eq!(alerts.lines(), vec!["[WARN] PANIC in worker", "[ERROR] disk failed"]);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn preformatted_test() {
+    let nested_output = fern::MemoryLogger::new();
+    let nested = fern::DispatchLogger::new(
+        Box::new(|msg, level, _location| format!("[{}] {}", level, msg)),
+        vec![fern::OutputConfig::custom(Box::new(nested_output.clone()))],
+        log::LogLevelFilter::Warn, vec![]).unwrap()
+        .instance_tag("nested")
+        .preformatted(true);
+    let top_output = fern::MemoryLogger::new();
+    let logger = fern::DispatchLogger::new(
+        Box::new(|msg, level, _location| format!("[{}] {}", level, msg)),
+        vec![fern::OutputConfig::custom(Box::new(top_output.clone())),
+             fern::OutputConfig::custom(Box::new(nested))],
+        log::LogLevelFilter::Info, vec![]).unwrap()
+        .uptime_prefix(0);
+
+    let location = construct_fake_log_location();
+    fern::Logger::log(&logger, "started", &log::LogLevel::Info, &location).unwrap();
+    fern::Logger::log(&logger, "disk full", &log::LogLevel::Warn, &location).unwrap();
+
+    let top = top_output.lines();
+    assert_eq!(top.len(), 2);
+    assert!(top[1].starts_with("[") && top[1].ends_with("s] [WARN] disk full"), "{}", top[1]);
+    // The nested logger's level still applies, but the message is only formatted once
+    assert_eq!(nested_output.lines(), vec![top[1].clone()]);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn formatted_output_test() {
+    let shared_calls = sync::Arc::new(sync::atomic::AtomicUsize::new(0));
+    let verbose_calls = sync::Arc::new(sync::atomic::AtomicUsize::new(0));
+    let console = fern::MemoryLogger::new();
+    let verbose = fern::MemoryLogger::new();
+    let (shared, own) = (shared_calls.clone(), verbose_calls.clone());
+    let logger = fern::DispatchLogger::new(Box::new(move |msg, _level, _location| {
+            shared.fetch_add(1, sync::atomic::Ordering::SeqCst);
+            msg.to_string()
+        }), vec![], log::LogLevelFilter::Trace, vec![]).unwrap()
+        .formatted_output(Box::new(|msg, _level, _location| format!("console: {}", msg)),
+                          log::LogLevelFilter::Warn, Box::new(console.clone()))
+        .formatted_output(Box::new(move |msg, level, _location| {
+            own.fetch_add(1, sync::atomic::Ordering::SeqCst);
+            format!("[{}] {}", level, msg)
+        }), log::LogLevelFilter::Debug, Box::new(verbose.clone()));
+
+    let location = construct_fake_log_location();
+    fern::Logger::log(&logger, "details", &log::LogLevel::Trace, &location).unwrap();
+    fern::Logger::log(&logger, "progress", &log::LogLevel::Debug, &location).unwrap();
+    fern::Logger::log(&logger, "trouble", &log::LogLevel::Warn, &location).unwrap();
+
+    assert_eq!(console.lines(), vec!["console: trouble"]);
+    assert_eq!(verbose.lines(), vec!["[DEBUG] progress", "[WARN] trouble"]);
+    // Formatters are only called for outputs accepting the message, and the shared one isn't
+    // needed at all
+    assert_eq!(verbose_calls.load(sync::atomic::Ordering::SeqCst), 2);
+    assert_eq!(shared_calls.load(sync::atomic::Ordering::SeqCst), 0);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn split_output_test() {
+    let endpoint = || {
+        let socket = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+        socket
+    };
+    let (alerts, collector) = (endpoint(), endpoint());
+    let logger = fern::DispatchLogger::new(Box::new(|msg, level, _location| {
+            format!("[{}] {}", level, msg)
+        }), vec![], log::LogLevelFilter::Info, vec![]).unwrap()
+        .split_output(log::LogLevel::Error,
+                      Box::new(fern::UdpLogger::new(alerts.local_addr().unwrap()).unwrap()),
+                      Box::new(fern::UdpLogger::new(collector.local_addr().unwrap()).unwrap()));
+    assert_eq!(logger.output_count(), 2);
+
+    let location = construct_fake_log_location();
+    fern::Logger::log(&logger, "disk failed", &log::LogLevel::Error, &location).unwrap();
+    fern::Logger::log(&logger, "request served", &log::LogLevel::Info, &location).unwrap();
+    fern::Logger::log(&logger, "too detailed", &log::LogLevel::Debug, &location).unwrap();
+
+    let receive = |socket: &net::UdpSocket| {
+        let mut received = Vec::new();
+        let mut buf = [0; 100];
+        while let Ok(len) = socket.recv(&mut buf) {
+            received.push(String::from_utf8_lossy(&buf[..len]).into_owned());
+        }
+        received
+    };
+    assert_eq!(receive(&alerts), vec!["[ERROR] disk failed"]);
+    assert_eq!(receive(&collector), vec!["[INFO] request served"]);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn on_record_test() {
+    let counts = sync::Arc::new(sync::Mutex::new(Vec::new()));
+    let total = sync::Arc::new(sync::atomic::AtomicUsize::new(0));
+    let (level_counts, total_count) = (counts.clone(), total.clone());
+    let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+        vec![], log::LogLevelFilter::Info, fern::directives(&[("noisy", log::LogLevelFilter::Off)]))
+        .unwrap()
+        .on_record(Box::new(move |level| level_counts.lock().unwrap().push(*level)))
+        .on_record(Box::new(move |_level| {
+            total_count.fetch_add(1, sync::atomic::Ordering::SeqCst);
+        }))
+        .on_empty_output(fern::EmptyOutput::Discard);
+
+    let location = construct_fake_log_location();
+    for level in &[log::LogLevel::Error, log::LogLevel::Info, log::LogLevel::Debug,
+                   log::LogLevel::Warn] {
+        fern::Logger::log(&logger, "message", level, &location).unwrap();
+    }
+    let noisy = log::LogLocation {
+        __module_path: "noisy",
+        .. construct_fake_log_location()
+    };
+    fern::Logger::log(&logger, "dropped", &log::LogLevel::Error, &noisy).unwrap();
+    assert_eq!(*counts.lock().unwrap(),
+               vec![log::LogLevel::Error, log::LogLevel::Info, log::LogLevel::Warn]);
+    assert_eq!(total.load(sync::atomic::Ordering::SeqCst), 3);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn blob_store_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let blob_dir = temp_log_dir.path().join("blobs");
+    let blobs = fern::BlobStore::new(&blob_dir).unwrap();
+    let memory = fern::MemoryLogger::new();
+    let logger = fern::DispatchLogger::new(
+        Box::new(|msg, level, _location| format!("[{}] {}", level, msg)),
+        vec![fern::OutputConfig::custom(Box::new(memory.clone()))], log::LogLevelFilter::Info,
+        vec![]).unwrap();
+
+    let location = construct_fake_log_location();
+    blobs.log(&logger, "captured packet", b"abc", &log::LogLevel::Warn, &location).unwrap();
+    // Named by the SHA-256 hash of the contents
+    let path = blob_dir.join("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    assert_eq!(memory.lines(), vec![format!("[WARN] captured packet [blob {}]", path.display())]);
+    let mut stored = Vec::new();
+    fs::File::open(&path).unwrap().read_to_end(&mut stored).unwrap();
+    assert_eq!(stored, b"abc");
+
+    // The same bytes are only stored once
+    assert_eq!(blobs.store(b"abc").unwrap(), path);
+    let large = vec![0x5a; 1000];
+    blobs.store(&large).unwrap();
+    blobs.store(b"").unwrap();
+    let mut names: Vec<String> = fs::read_dir(&blob_dir).unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned()).collect();
+    names.sort();
+    assert_eq!(names, vec!["8fe15844cfeedd35f5dc30a9fa5ed38afd849dbe4f8dcae5642d934be0afb13d",
+                           "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+                           "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"]);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn memory_ring_logger_test() {
+    let ring = fern::MemoryRingLogger::new(32, "\n");
+    let logger = fern::DispatchLogger::new(
+        Box::new(|msg, level, _location| format!("[{}] {}", level, msg)),
+        vec![fern::OutputConfig::custom(Box::new(ring.clone()))], log::LogLevelFilter::Info,
+        vec![]).unwrap();
+    let location = construct_fake_log_location();
+
+    fern::Logger::log(&logger, "one", &log::LogLevel::Info, &location).unwrap();
+    assert_eq!(ring.snapshot(), b"[INFO] one\n");
+    for i in 0..20 {
+        fern::Logger::log(&logger, &format!("message {}", i), &log::LogLevel::Info, &location)
+            .unwrap();
+        assert!(ring.len() <= 32);
+    }
+    // The oldest message kept is cut off at the start
+    assert_eq!(ring.snapshot(), &b"O] message 18\n[INFO] message 19\n"[..]);
+    assert_eq!(ring.len(), 32);
+
+    // Longer messages than the whole buffer only keep their end
+    fern::Logger::log(&logger, &"x".repeat(100), &log::LogLevel::Info, &location).unwrap();
+    let mut expected = vec![b'x'; 31];
+    expected.push(b'\n');
+    assert_eq!(ring.snapshot(), expected);
+    fern::Logger::log_bytes(&ring, b"\xff\x00", &log::LogLevel::Info, &location).unwrap();
+    assert_eq!(&ring.snapshot()[29..], b"\xff\x00\n");
+
+    ring.clear();
+    assert_eq!(ring.len(), 0);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn broadcast_logger_test() {
+    let broadcast = fern::BroadcastLogger::new(2);
+    let logger = fern::DispatchLogger::new(
+        Box::new(|msg, level, _location| format!("[{}] {}", level, msg)),
+        vec![fern::OutputConfig::custom(Box::new(broadcast.clone()))], log::LogLevelFilter::Info,
+        vec![]).unwrap();
+    let location = construct_fake_log_location();
+
+    let fast = broadcast.subscribe();
+    let slow = broadcast.subscribe();
+    let gone = broadcast.subscribe();
+    drop(gone);
+    assert_eq!(broadcast.subscriber_count(), 3);
+
+    for msg in &["first", "second", "third"] {
+        fern::Logger::log(&logger, msg, &log::LogLevel::Info, &location).unwrap();
+        assert_eq!(fast.try_recv().unwrap(), format!("[INFO] {}", msg));
+    }
+    // The slow subscriber's queue only holds two messages, so it misses the third
+    assert_eq!(slow.try_iter().collect::<Vec<_>>(), vec!["[INFO] first", "[INFO] second"]);
+    assert_eq!(broadcast.dropped_count(), 1);
+    assert_eq!(broadcast.subscriber_count(), 2);
+    assert_eq!(fern::Logger::describe(&broadcast), "broadcast (2 subscribers)");
+
+    fern::Logger::log(&logger, "fourth", &log::LogLevel::Info, &location).unwrap();
+    assert_eq!(slow.try_recv().unwrap(), "[INFO] fourth");
+    assert_eq!(fast.try_recv().unwrap(), "[INFO] fourth");
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn memory_logger_test() {
+    let memory = fern::MemoryLogger::new();
+    let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+        vec![fern::OutputConfig::custom(Box::new(memory.clone()))],
+        log::LogLevelFilter::Info, vec![]).unwrap();
+
+    let location = construct_fake_log_location();
+    fern::Logger::log(&logger, "connecting", &log::LogLevel::Info, &location).unwrap();
+    fern::Logger::log(&logger, "connection refused", &log::LogLevel::Error, &location).unwrap();
+    fern::Logger::log(&logger, "retrying", &log::LogLevel::Debug, &location).unwrap();
+
+    assert_eq!(memory.lines(), vec!["connecting", "connection refused"]);
+    assert_eq!(memory.count_at_level(log::LogLevel::Error), 1);
+    assert_eq!(memory.count_at_level(log::LogLevel::Warn), 0);
+    memory.assert_contains(log::LogLevel::Error, "refused");
+    memory.assert_not_contains("retrying");
+
+    // Failed assertions show what was captured
+    let result = ::std::panic::catch_unwind(|| {
+        memory.assert_contains(log::LogLevel::Warn, "refused");
+    });
+    let error = result.unwrap_err();
+    let message = error.downcast_ref::<String>().unwrap();
+    assert!(message.contains("expected a WARN message containing \"refused\""), "{}", message);
+    assert!(message.contains("ERROR connection refused"), "{}", message);
+
+    memory.clear();
+    assert!(memory.records().is_empty());
+}
+
+/// Logger which always fails.
+struct FailingLogger;
+
+impl fern::Logger for FailingLogger {
+    fn log(&self, _msg: &str, _level: &log::LogLevel, _location: &log::LogLocation)
+            -> Result<(), fern::LogError> {
+        return Err(fern::LogError::Io(io::Error::new(io::ErrorKind::Other, "failing output")));
+    }
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn failing_output_fanout_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let log_files: Vec<_> = (0..3).map(|i| {
+        temp_log_dir.path().join(format!("test_fanout_{}.log", i))
+    }).collect();
+
+    {
+        // Succeeding outputs are interleaved with failing ones
+        let logger = sync::Arc::new(fern::DispatchLogger::new(
+            Box::new(|msg, level, _location| format!("[{}] {}", level, msg)),
+            vec![fern::OutputConfig::custom(Box::new(FailingLogger)),
+                 fern::OutputConfig::file(&log_files[0]),
+                 fern::OutputConfig::custom(Box::new(FailingLogger)),
+                 fern::OutputConfig::file(&log_files[1]),
+                 fern::OutputConfig::custom(Box::new(FailingLogger)),
+                 fern::OutputConfig::file(&log_files[2])],
+            log::LogLevelFilter::Trace, vec![]).unwrap());
+
+        let threads: Vec<_> = (0..4).map(|thread_num| {
+            let logger = logger.clone();
+            thread::spawn(move || {
+                let location = construct_fake_log_location();
+                for i in 0..200 {
+                    let msg = format!("thread {} message {} {}", thread_num, i, "x".repeat(i));
+                    let result = fern::Logger::log(&*logger, &msg, &log::LogLevel::Info,
+                                                   &location);
+                    assert!(result.is_err());
+                }
+            })
+        }).collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+
+    for log_file in &log_files {
+        let mut buf = String::new();
+        fs::File::open(log_file).unwrap().read_to_string(&mut buf).unwrap();
+        assert_eq!(buf.lines().count(), 800);
+        for line in buf.lines() {
+            // Every line must be complete: the trailing run of x's has the length given by the
+            // message number.
+            let parts: Vec<&str> = line.split(' ').collect();
+            assert_eq!(parts.len(), 6, "corrupted line: {:?}", line);
+            assert_eq!(parts[0], "[INFO]");
+            let i: usize = parts[4].parse().unwrap();
+            assert_eq!(parts[5], "x".repeat(i));
+        }
+    }
+
+    temp_log_dir.close().ok().expect("Failed to clean up temporary directory");
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn directive_level_semantics_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let log_file = temp_log_dir.path().join("test_directives.log");
+
+    {
+        let logger = fern::DispatchLogger::new(
+            Box::new(|msg, level, _location| format!("[{}] {}", level, msg)),
+            vec![fern::OutputConfig::file(&log_file)],
+            log::LogLevelFilter::Trace,
+            vec![fern::LogDirective::new("plain".to_string(), log::LogLevelFilter::Warn),
+                 fern::LogDirective::inclusive("inclusive".to_string(), log::LogLevelFilter::Warn),
+                 fern::LogDirective::exact("exact".to_string(), log::LogLevelFilter::Warn)],
+        ).unwrap();
+
+        for module in &["plain", "inclusive", "exact"] {
+            let location = log::LogLocation {
+                __module_path: module,
+                .. construct_fake_log_location()
+            };
+            for level in &[log::LogLevel::Error, log::LogLevel::Warn, log::LogLevel::Info] {
+                fern::Logger::log(&logger, module, level, &location).unwrap();
+            }
+        }
+    }
+
+    let mut buf = String::new();
+    fs::File::open(&log_file).unwrap().read_to_string(&mut buf).unwrap();
+    // A plain directive only passes levels above its own, an inclusive one its own level too,
+    // and an exact one only its own level
+    assert_eq!(&buf, "[ERROR] plain\n[ERROR] inclusive\n[WARN] inclusive\n[WARN] exact\n");
+
+    temp_log_dir.close().ok().expect("Failed to clean up temporary directory");
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn directives_match_module_path_test() {
+    let memory = fern::MemoryLogger::new();
+    let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+        vec![fern::OutputConfig::custom(Box::new(memory.clone()))], log::LogLevelFilter::Trace,
+        fern::directives(&[("my_app::db", log::LogLevelFilter::Off),
+                           ("requests", log::LogLevelFilter::Off)])).unwrap();
+    let location = log::LogLocation {
+        __module_path: "my_app::db",
+        .. construct_fake_log_location()
+    };
+    fern::Logger::log(&logger, "module path", &log::LogLevel::Error, &location).unwrap();
+    fern::Logger::log_with_target(&logger, "other target", &log::LogLevel::Error, &location,
+                                  "other").unwrap();
+    let net = log::LogLocation {
+        __module_path: "my_app::net",
+        .. construct_fake_log_location()
+    };
+    fern::Logger::log_with_target(&logger, "named target", &log::LogLevel::Error, &net,
+                                  "requests").unwrap();
+    // The module path is matched whatever the target is
+    assert_eq!(memory.lines(), vec!["named target"]);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn matches_test() {
+    let directives = vec![
+        fern::LogDirective::new("my_app".to_string(), log::LogLevelFilter::Info),
+        fern::LogDirective::new("my_app::db".to_string(), log::LogLevelFilter::Off),
+        fern::LogDirective::inclusive("my_app::db::pool".to_string(), log::LogLevelFilter::Trace),
+        fern::LogDirective::exact("hyper".to_string(), log::LogLevelFilter::Warn),
+        // The last of two directives with the same name applies
+        fern::LogDirective::new("tokio".to_string(), log::LogLevelFilter::Error),
+        fern::LogDirective::new("tokio".to_string(), log::LogLevelFilter::Debug),
+    ];
+    let memory = fern::MemoryLogger::new();
+    let logger = fern::DispatchLogger::new(
+        Box::new(|msg, level, _location| format!("{} {}", level, msg)),
+        vec![fern::OutputConfig::custom(Box::new(memory.clone()))], log::LogLevelFilter::Trace,
+        directives.clone()).unwrap();
+
+    let levels = [log::LogLevel::Error, log::LogLevel::Warn, log::LogLevel::Info,
+                  log::LogLevel::Debug, log::LogLevel::Trace];
+    let modules = ["my_app", "my_app::net", "my_app::db", "my_app::db::pool",
+                   "my_app::db::pool::conn", "hyper", "hyper::client", "tokio", "other", ""];
+    let mut expected = Vec::new();
+    for module in &modules {
+        let location = log::LogLocation {
+            __module_path: module,
+            .. construct_fake_log_location()
+        };
+        for level in &levels {
+            fern::Logger::log(&logger, module, level, &location).unwrap();
+            if fern::matches(&directives, level, module) {
+                expected.push(format!("{} {}", level, module));
+            }
+        }
+    }
+    // The dispatcher's level is Trace, so only the directives filter
+    assert_eq!(memory.lines(), expected);
+    assert!(!fern::matches(&directives, &log::LogLevel::Error, "my_app::db::x"));
+    assert!(fern::matches(&directives, &log::LogLevel::Trace, "my_app::db::pool::conn"));
+    assert!(!fern::matches(&directives, &log::LogLevel::Error, "hyper"));
+    assert!(!fern::matches(&directives, &log::LogLevel::Debug, "tokio"));
+    assert!(fern::matches(&directives, &log::LogLevel::Info, "tokio"));
+    assert!(fern::matches(&directives, &log::LogLevel::Trace, "other"));
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn banner_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let log_files: Vec<_> = (0..2).map(|i| {
+        temp_log_dir.path().join(format!("test_banner_{}.log", i))
+    }).collect();
+
+    {
+        let logger = fern::DispatchLogger::new(
+            Box::new(|msg, level, location| {
+                format!("[{} {}] {}", level, location.module_path(), msg)
+            }),
+            vec![fern::OutputConfig::file(&log_files[0]), fern::OutputConfig::file(&log_files[1])],
+            // The banner is logged regardless of level
+            log::LogLevelFilter::Error, vec![]).unwrap();
+        logger.log_banner().unwrap();
+
+        let location = construct_fake_log_location();
+        fern::Logger::log(&logger, "message", &log::LogLevel::Error, &location).unwrap();
+    }
+
+    for log_file in &log_files {
+        let mut buf = String::new();
+        fs::File::open(log_file).unwrap().read_to_string(&mut buf).unwrap();
+        let lines: Vec<&str> = buf.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("[INFO fern::banner] logging started (fern "));
+        assert!(lines[0].ends_with(&format!("level: ERROR, outputs: [file ({}), file ({})])",
+                                            log_files[0].display(), log_files[1].display())));
+        assert_eq!(lines[1], "[ERROR test] message");
+    }
+
+    temp_log_dir.close().ok().expect("Failed to clean up temporary directory");
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn lifecycle_events_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let log_file = temp_log_dir.path().join("test_lifecycle.log");
+
+    {
+        let logger = fern::DispatchLogger::new(
+            Box::new(|msg, level, location| {
+                format!("[{} {}] {}", level, location.module_path(), msg)
+            }),
+            vec![fern::OutputConfig::file(&log_file)],
+            log::LogLevelFilter::Warn, vec![]).unwrap()
+            .lifecycle_events().unwrap();
+
+        let location = construct_fake_log_location();
+        fern::Logger::log(&logger, "message", &log::LogLevel::Warn, &location).unwrap();
+        logger.shutdown().unwrap();
+        assert!(logger.is_shut_down());
+
+        // Nothing is written after the stopped event, and shutting down again does nothing
+        fern::Logger::log(&logger, "too late", &log::LogLevel::Warn, &location).unwrap();
+        logger.log_banner().unwrap();
+        logger.shutdown().unwrap();
+    }
+
+    let mut buf = String::new();
+    fs::File::open(&log_file).unwrap().read_to_string(&mut buf).unwrap();
+    let lines: Vec<&str> = buf.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("[INFO lifecycle] logger started (time: "));
+    assert!(lines[0].ends_with(&format!("level: WARN, outputs: [file ({})])",
+                                        log_file.display())));
+    assert_eq!(lines[1], "[WARN test] message");
+    assert!(lines[2].starts_with("[INFO lifecycle] logger stopped (time: "));
+    assert!(lines[2].contains("Z, uptime: "));
+
+    temp_log_dir.close().ok().expect("Failed to clean up temporary directory");
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn diagnostic_tap_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let tap_file = temp_log_dir.path().join("test_tap.log");
+
+    let memory = fern::MemoryLogger::new();
+    let logger = fern::DispatchLogger::new(
+        Box::new(|msg, level, _location| format!("[{}] {}", level, msg)),
+        vec![fern::OutputConfig::custom(Box::new(memory.clone())),
+             fern::OutputConfig::custom(Box::new(FailingLogger))],
+        log::LogLevelFilter::Info, vec![]).unwrap()
+        .output_error_policy(1, fern::ErrorPolicy::Ignore)
+        .matching_output(Box::new(|msg| msg.contains("alert")), Box::new(fern::NullLogger))
+        .diagnostic_tap(&tap_file).unwrap();
+
+    let location = construct_fake_log_location();
+    fern::Logger::log(&logger, "hello\nthere", &log::LogLevel::Info, &location).unwrap();
+    fern::Logger::log(&logger, "details", &log::LogLevel::Debug, &location).unwrap();
+    assert_eq!(memory.lines(), vec!["[INFO] hello\nthere"]);
+
+    // The tap shows each record before formatting, and what each output did with it
+    let mut buf = String::new();
+    fs::File::open(&tap_file).unwrap().read_to_string(&mut buf).unwrap();
+    let lines: Vec<&str> = buf.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", buf);
+    assert!(lines[0].ends_with("Z INFO test (tests.rs:0) \"hello\\nthere\" -> [#0 memory: ok, \
+                                #1 custom: error: IO Error: failing output, \
+                                #2 null: skipped by predicate]"), "{}", lines[0]);
+    assert!(lines[1].ends_with("Z DEBUG test (tests.rs:0) \"details\" -> filtered out"),
+            "{}", lines[1]);
+
+    temp_log_dir.close().ok().expect("Failed to clean up temporary directory");
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn short_module_paths_test() {
+    assert_eq!(fern::module_path_tail("my_app::net::http::client::conn", 2), "client::conn");
+    assert_eq!(fern::module_path_tail("my_app::net::http::client::conn", 1), "conn");
+    assert_eq!(fern::module_path_tail("my_app::net", 5), "my_app::net");
+    assert_eq!(fern::module_path_tail("my_app::net", 0), "my_app::net");
+
+    let memory = fern::MemoryLogger::new();
+    let logger = fern::DispatchLogger::new(
+        Box::new(|msg, level, location| format!("[{} {}] {}", level, location.module_path(), msg)),
+        vec![fern::OutputConfig::custom(Box::new(memory.clone()))], log::LogLevelFilter::Trace,
+        vec![fern::LogDirective::inclusive("my_app::net::http".to_string(),
+                                           log::LogLevelFilter::Debug)])
+        .unwrap()
+        .short_module_paths(log::LogLevel::Debug, 2);
+    let location = log::LogLocation {
+        __module_path: "my_app::net::http::client::conn",
+        .. construct_fake_log_location()
+    };
+    fern::Logger::log(&logger, "connected", &log::LogLevel::Info, &location).unwrap();
+    fern::Logger::log(&logger, "handshake", &log::LogLevel::Debug, &location).unwrap();
+    // Directives still match the whole target
+    fern::Logger::log(&logger, "bytes", &log::LogLevel::Trace, &location).unwrap();
+    assert_eq!(memory.lines(), vec!["[INFO my_app::net::http::client::conn] connected",
+                                    "[DEBUG client::conn] handshake"]);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn catch_format_panics_test() {
+    let memory = fern::MemoryLogger::new();
+    let logger = fern::DispatchLogger::new(
+        Box::new(|msg, level, _location| format!("{} <{}>", level, &msg[..msg.find(';').unwrap()])),
+        vec![fern::OutputConfig::custom(Box::new(memory.clone()))], log::LogLevelFilter::Trace,
+        vec![]).unwrap()
+        .catch_format_panics(true);
+    let location = construct_fake_log_location();
+    fern::Logger::log(&logger, "parsed; rest", &log::LogLevel::Info, &location).unwrap();
+    fern::Logger::log(&logger, "no separator", &log::LogLevel::Warn, &location).unwrap();
+    fern::Logger::log(&logger, "again no separator", &log::LogLevel::Warn, &location).unwrap();
+    assert_eq!(memory.lines(), vec!["INFO <parsed>", "[WARN] no separator",
+                                    "[WARN] again no separator"]);
+
+    // Formatters writing into a buffer lose whatever they wrote before panicking
+    memory.clear();
+    let logger = logger.format_into(Box::new(|buf: &mut String, msg: &str, _level: &log::LogLevel,
+                                              _location: &log::LogLocation| {
+        buf.push_str("partial ");
+        buf.push_str(&msg[..msg.find(';').unwrap()]);
+    }));
+    fern::Logger::log(&logger, "parsed; rest", &log::LogLevel::Info, &location).unwrap();
+    fern::Logger::log(&logger, "no separator", &log::LogLevel::Error, &location).unwrap();
+    assert_eq!(memory.lines(), vec!["partial parsed", "[ERROR] no separator"]);
+
+    // Without catching, the panic reaches the caller
+    let logger = fern::DispatchLogger::new(
+        Box::new(|_msg, _level, _location| panic!("formatter failed")),
+        vec![fern::OutputConfig::custom(Box::new(memory.clone()))], log::LogLevelFilter::Trace,
+        vec![]).unwrap();
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let _ = fern::Logger::log(&logger, "message", &log::LogLevel::Info, &location);
+    }));
+    assert!(result.is_err());
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn only_threads_test() {
+    let memory = fern::MemoryLogger::new();
+    let logger = sync::Arc::new(fern::DispatchLogger::new(
+        Box::new(|msg, _level, _location| {
+            format!("[{}] {}", thread::current().name().unwrap_or("<unnamed>"), msg)
+        }),
+        vec![fern::OutputConfig::custom(Box::new(memory.clone()))], log::LogLevelFilter::Info,
+        vec![]).unwrap()
+        .only_threads(fern::ThreadFilter::Named(vec!["worker-*-db".to_string(),
+                                                     "main".to_string()])));
+    let spawn_logging = |name: Option<&str>| {
+        let logger = logger.clone();
+        let builder = match name {
+            Some(name) => thread::Builder::new().name(name.to_string()),
+            None => thread::Builder::new(),
+        };
+        builder.spawn(move || {
+            let location = construct_fake_log_location();
+            fern::Logger::log(&*logger, "working", &log::LogLevel::Info, &location).unwrap();
+        }).unwrap().join().unwrap();
+    };
+    spawn_logging(Some("worker-1-db"));
+    spawn_logging(Some("worker-2-http"));
+    spawn_logging(Some("worker-db"));
+    spawn_logging(Some("main"));
+    spawn_logging(None);
+    assert_eq!(memory.lines(), vec!["[worker-1-db] working", "[main] working"]);
+
+    memory.clear();
+    let location = construct_fake_log_location();
+    let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+        vec![fern::OutputConfig::custom(Box::new(memory.clone()))], log::LogLevelFilter::Info,
+        vec![]).unwrap()
+        .only_threads(fern::ThreadFilter::Ids(vec![thread::current().id()]));
+    fern::Logger::log(&logger, "this thread", &log::LogLevel::Info, &location).unwrap();
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            let location = construct_fake_log_location();
+            fern::Logger::log(&logger, "other thread", &log::LogLevel::Info, &location).unwrap();
+        });
+    });
+    assert_eq!(memory.lines(), vec!["this thread"]);
+}
+
+#[test]
+fn preset_formatter_test() {
+    let location = construct_fake_log_location();
+    let level = log::LogLevel::Warn;
+
+    let simple = fern::Preset::Simple.formatter();
+    assert_eq!(simple("message", &level, &location), "[WARN] message");
+
+    // The thread name is the name of the test
+    let detailed = fern::Preset::Detailed.formatter()("message", &level, &location);
+    // [2016-03-01 12:00:00.123]
+    assert_eq!(detailed.chars().filter(|c| c.is_digit(10)).take(17).count(), 17);
+    assert_eq!(&detailed[..1], "[");
+    assert_eq!(&detailed[24..], "][WARN][preset_formatter_test][test tests.rs:0] message");
+
+    let json = fern::Preset::Json.formatter()("a \"quoted\"\nmessage", &level, &location);
+    // {"timestamp":"2016-03-01T12:00:00.123Z",
+    assert_eq!(&json[..14], "{\"timestamp\":\"");
+    assert_eq!(&json[24..25], "T");
+    assert_eq!(&json[37..40], "Z\",");
+    assert_eq!(&json[40..], "\"level\":\"WARN\",\"target\":\"test\",\"file\":\"tests.rs\",\"line\":0,\
+                             \"thread\":\"preset_formatter_test\",\
+                             \"message\":\"a \\\"quoted\\\"\\nmessage\"}");
+}
+
+#[test]
+fn dev_pretty_preset_test() {
+    let location = log::LogLocation {
+        __module_path: "my_app::net",
+        __file: "src/net/client.rs",
+        __line: 42,
+    };
+    let plain = fern::dev_pretty_formatter(false)("connected", &log::LogLevel::Info, &location);
+    // [   0.000s] INFO  client.rs:42         connected
+    assert_eq!(&plain[..3], "[  ");
+    assert_eq!(&plain[9..], "s] INFO  client.rs:42         connected");
+    let colored = fern::dev_pretty_formatter(true)("connected", &log::LogLevel::Info, &location);
+    assert_eq!(&colored[9..], "s] \x1b[32mINFO \x1b[0m client.rs:42         connected");
+
+    // The preset only colors when writing to a terminal
+    let preset = fern::Preset::DevPretty.formatter()("connected", &log::LogLevel::Info,
+                                                     &location);
+    if !io::IsTerminal::is_terminal(&io::stdout()) || !io::IsTerminal::is_terminal(&io::stderr()) {
+        assert_eq!(&preset[9..], &plain[9..]);
+    }
+}
+
+#[test]
+fn env_logger_preset_test() {
+    let location = construct_fake_log_location();
+    let env = fern::Preset::EnvLogger.formatter();
+    // [2016-03-01T12:00:00Z
+    let formatted = env("message", &log::LogLevel::Info, &location);
+    assert_eq!(&formatted[..1], "[");
+    assert_eq!(&formatted[11..12], "T");
+    assert_eq!(&formatted[20..], "Z INFO  test] message");
+    let formatted = env("message", &log::LogLevel::Error, &location);
+    assert_eq!(&formatted[20..], "Z ERROR test] message");
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn init_env_logger_test() {
+    // The messages go to stderr, so this test runs itself in a child process to capture them
+    if env::var("FERN_INIT_ENV_LOGGER_TEST_CHILD").is_ok() {
+        fern::init_env_logger().unwrap();
+        error!("error");
+        info!("info");
+        debug!("debug");
+        trace!("trace");
+        info!(target: "other", "other info");
+        return;
+    }
+
+    let run_child = |rust_log: Option<&str>| {
+        let mut command = process::Command::new(env::current_exe().unwrap());
+        command.args(&["--exact", "init_env_logger_test", "--nocapture", "--test-threads=1"])
+               .env("FERN_INIT_ENV_LOGGER_TEST_CHILD", "1").env_remove("RUST_LOG");
+        if let Some(rust_log) = rust_log {
+            command.env("RUST_LOG", rust_log);
+        }
+        let output = command.output().unwrap();
+        assert!(output.status.success());
+        // Leave out the timestamps, and the test harness's own output
+        String::from_utf8_lossy(&output.stderr).lines().filter(|line| line.starts_with('['))
+            .map(|line| line[22..].to_string()).collect::<Vec<_>>()
+    };
+    // Messages are filtered by module path, which is `lib` here, whatever their target
+    assert_eq!(run_child(None), vec!["ERROR lib] error"]);
+    assert_eq!(run_child(Some("lib=debug,other=off,bad=loud")),
+               vec!["ERROR lib] error", "INFO  lib] info", "DEBUG lib] debug",
+                    "INFO  lib] other info"]);
+    assert_eq!(run_child(Some("info")),
+               vec!["ERROR lib] error", "INFO  lib] info", "INFO  lib] other info"]);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn scope_test() {
+    let memory = fern::MemoryLogger::new();
+    let logger = fern::DispatchLogger::new(
+        Box::new(|msg, _level, _location| format!("[{}] {}", fern::scope::current(), msg)),
+        vec![fern::OutputConfig::custom(Box::new(memory.clone()))], log::LogLevelFilter::Info,
+        vec![]).unwrap();
+    let location = construct_fake_log_location();
+    let log = |msg| fern::Logger::log(&logger, msg, &log::LogLevel::Info, &location).unwrap();
+
+    log("before");
+    {
+        let _outer = fern::scope::enter("outer");
+        log("in outer");
+        {
+            let _inner = fern::scope::enter("inner");
+            let _again = fern::scope::enter("inner");
+            log("in inner");
+            fern::scope::with_scopes(|scopes| assert_eq!(scopes, &["outer", "inner", "inner"]));
+        }
+        log("back in outer");
+        // Dropping a guard early leaves the scopes entered after it too
+        let first = fern::scope::enter("first");
+        let _second = fern::scope::enter("second");
+        drop(first);
+        log("after early drop");
+    }
+    log("after");
+    assert_eq!(memory.lines(), vec!["[] before", "[outer] in outer",
+                                    "[outer > inner > inner] in inner", "[outer] back in outer",
+                                    "[outer] after early drop", "[] after"]);
+
+    // Scopes are per thread
+    let _scope = fern::scope::enter("main");
+    thread::spawn(|| assert_eq!(fern::scope::current(), "")).join().unwrap();
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn output_context_test() {
+    let json = fern::MemoryLogger::new();
+    let console = fern::MemoryLogger::new();
+    let hidden = fern::MemoryLogger::new();
+    let fields = |_msg: &str, _level: &log::LogLevel, _location: &log::LogLocation| {
+        fern::with_context(|fields| {
+            fields.iter().map(|&(ref key, ref value)| format!("{}={}", key, value))
+                  .collect::<Vec<_>>().join(" ")
+        })
+    };
+    let logger = fern::DispatchLogger::new(fern::Preset::Json.formatter(),
+        vec![fern::OutputConfig::custom(Box::new(json.clone()))], log::LogLevelFilter::Info,
+        vec![]).unwrap()
+        .formatted_output(Box::new(fields), log::LogLevelFilter::Info, Box::new(console.clone()))
+        .output_context(1, fern::ContextFilter::Only(vec!["request_id".to_string()]))
+        .formatted_output(Box::new(fields), log::LogLevelFilter::Info, Box::new(hidden.clone()))
+        .output_context(2, fern::ContextFilter::Except(vec!["request_id".to_string()]));
+
+    let location = construct_fake_log_location();
+    let _request = fern::context_scope("request_id", "a1b2");
+    let _user = fern::context_scope("user", "alice");
+    fern::Logger::log(&logger, "message", &log::LogLevel::Info, &location).unwrap();
+
+    let json = json.lines();
+    assert!(json[0].contains("\"request_id\":\"a1b2\",\"user\":\"alice\","), "{}", json[0]);
+    assert_eq!(console.lines(), vec!["request_id=a1b2"]);
+    assert_eq!(hidden.lines(), vec!["user=alice"]);
+    // The filter only applies while formatting for the output
+    fern::with_context(|fields| assert_eq!(fields.len(), 2));
+}
+
+#[test]
+fn json_context_test() {
+    let location = construct_fake_log_location();
+    let json = fern::Preset::Json.formatter();
+    let format = || {
+        let formatted = json("message", &log::LogLevel::Info, &location);
+        formatted[formatted.find("\"thread\"").unwrap()..].to_string()
+    };
+
+    {
+        let _request = fern::context_scope("request_id", "a1b2");
+        {
+            let _user = fern::context_scope("user", "al\"ice");
+            // Context keys can't replace the preset's own fields
+            let _level = fern::context_scope("level", "none");
+            assert_eq!(format(), "\"thread\":\"json_context_test\",\"request_id\":\"a1b2\",\
+                                  \"user\":\"al\\\"ice\",\"message\":\"message\"}");
+            let _retry = fern::context_scope("request_id", "c3d4");
+            assert_eq!(format(), "\"thread\":\"json_context_test\",\"request_id\":\"c3d4\",\
+                                  \"user\":\"al\\\"ice\",\"message\":\"message\"}");
+        }
+        assert_eq!(format(), "\"thread\":\"json_context_test\",\"request_id\":\"a1b2\",\
+                              \"message\":\"message\"}");
+    }
+    assert_eq!(format(), "\"thread\":\"json_context_test\",\"message\":\"message\"}");
+
+    // The context is per thread
+    let _request = fern::context_scope("request_id", "a1b2");
+    thread::spawn(|| {
+        fern::with_context(|fields| assert!(fields.is_empty()));
+    }).join().unwrap();
+}
+
+#[test]
+fn json_keys_test() {
+    let location = construct_fake_log_location();
+    let json = fern::json_formatter(&fern::JsonKeys {
+        timestamp: "@timestamp",
+        level: "severity",
+        message: "msg",
+        target: "logger",
+    });
+    // Context keys can't replace the renamed fields either
+    let _severity = fern::context_scope("severity", "none");
+    let _level = fern::context_scope("level", "kept");
+    let formatted = json("disk full", &log::LogLevel::Warn, &location);
+    assert!(formatted.starts_with("{\"@timestamp\":\""), "{}", formatted);
+    let rest = &formatted[formatted.find("Z\",").unwrap() + 3..];
+    assert_eq!(rest, "\"severity\":\"WARN\",\"logger\":\"test\",\"file\":\"tests.rs\",\"line\":0,\
+                      \"thread\":\"json_keys_test\",\"level\":\"kept\",\"msg\":\"disk full\"}");
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn quiet_verbose_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let log_file = temp_log_dir.path().join("test_quiet_verbose.log");
+
+    {
+        let logger = sync::Arc::new(fern::DispatchLogger::new(
+            Box::new(|msg, level, _location| format!("[{}] {}", level, msg)),
+            vec![fern::OutputConfig::file(&log_file)],
+            log::LogLevelFilter::Info, vec![]).unwrap());
+        // A clone is kept to reconfigure the logger, like one which was set as the global logger
+        let installed: Box<fern::Logger> = Box::new(logger.clone());
+
+        let location = construct_fake_log_location();
+        let log_all = |label: &str| {
+            for level in &[log::LogLevel::Error, log::LogLevel::Info, log::LogLevel::Debug] {
+                installed.log(label, level, &location).unwrap();
+            }
+        };
+
+        log_all("normal");
+        logger.set_verbose(true);
+        assert_eq!(logger.effective_level(), log::LogLevelFilter::Debug);
+        log_all("verbose");
+        // Quiet takes precedence over verbose
+        logger.set_quiet(true);
+        assert_eq!(logger.effective_level(), log::LogLevelFilter::Error);
+        log_all("quiet");
+        logger.set_quiet(false);
+        logger.set_verbose(false);
+        logger.set_level(log::LogLevelFilter::Error);
+        log_all("error level");
+    }
+
+    let mut buf = String::new();
+    fs::File::open(&log_file).unwrap().read_to_string(&mut buf).unwrap();
+    assert_eq!(&buf, "[ERROR] normal\n[INFO] normal\n\
+                      [ERROR] verbose\n[INFO] verbose\n[DEBUG] verbose\n\
+                      [ERROR] quiet\n\
+                      [ERROR] error level\n");
+
+    temp_log_dir.close().ok().expect("Failed to clean up temporary directory");
+}
+
+/// Logger which counts how many times it was flushed.
+struct FlushCountingLogger {
+    flushes: sync::Arc<sync::atomic::AtomicUsize>,
+    needs_flush: bool,
+}
+
+impl fern::Logger for FlushCountingLogger {
+    fn log(&self, _msg: &str, _level: &log::LogLevel, _location: &log::LogLocation)
+            -> Result<(), fern::LogError> {
+        return Ok(());
+    }
+
+    fn flush(&self) -> Result<(), fern::LogError> {
+        self.flushes.fetch_add(1, sync::atomic::Ordering::SeqCst);
+        return Ok(());
+    }
+
+    fn needs_flush(&self) -> bool {
+        return self.needs_flush;
+    }
+}
+
+#[test]
+fn channel_flush_interval_test() {
+    let fast_flushes = sync::Arc::new(sync::atomic::AtomicUsize::new(0));
+    let slow_flushes = sync::Arc::new(sync::atomic::AtomicUsize::new(0));
+    let fast = fern::ChannelLogger::with_flush_interval(
+        Box::new(FlushCountingLogger { flushes: fast_flushes.clone(), needs_flush: true }),
+        10, fern::QueueFullMode::Block, Duration::from_millis(50));
+    let slow = fern::ChannelLogger::with_flush_interval(
+        Box::new(FlushCountingLogger { flushes: slow_flushes.clone(), needs_flush: true }),
+        10, fern::QueueFullMode::Block, Duration::from_millis(400));
+
+    let location = construct_fake_log_location();
+    for _ in 0..10 {
+        fern::Logger::log(&fast, "message", &log::LogLevel::Info, &location).unwrap();
+        fern::Logger::log(&slow, "message", &log::LogLevel::Info, &location).unwrap();
+        thread::sleep(Duration::from_millis(50));
+    }
+    thread::sleep(Duration::from_millis(100));
+
+    // About 12 and 1 flushes, which is loosely checked as timing varies
+    let fast_count = fast_flushes.load(sync::atomic::Ordering::SeqCst);
+    let slow_count = slow_flushes.load(sync::atomic::Ordering::SeqCst);
+    assert!(fast_count >= 5, "fast output flushed {} times", fast_count);
+    assert!(slow_count >= 1 && slow_count <= 2, "slow output flushed {} times", slow_count);
+
+    // Dropping flushes once more
+    drop(slow);
+    assert_eq!(slow_flushes.load(sync::atomic::Ordering::SeqCst), slow_count + 1);
+    drop(fast);
+}
+
+/// Logger which takes a while to log each message.
+struct SlowLogger {
+    output: fern::MemoryLogger,
+}
+
+impl fern::Logger for SlowLogger {
+    fn log(&self, msg: &str, level: &log::LogLevel, location: &log::LogLocation)
+            -> Result<(), fern::LogError> {
+        thread::sleep(Duration::from_millis(20));
+        return self.output.log(msg, level, location);
+    }
+}
+
+#[test]
+fn channel_flush_on_error_test() {
+    let memory = fern::MemoryLogger::new();
+    let logger = fern::ChannelLogger::new(Box::new(SlowLogger { output: memory.clone() }), 10,
+                                          fern::QueueFullMode::Drop)
+        .flush_on_error(true);
+
+    let location = construct_fake_log_location();
+    for _ in 0..5 {
+        fern::Logger::log(&logger, "message", &log::LogLevel::Info, &location).unwrap();
+    }
+    assert!(logger.queue_len() > 0);
+    fern::Logger::log(&logger, "crashing", &log::LogLevel::Error, &location).unwrap();
+    // Everything queued before the error has been logged as well
+    assert_eq!(logger.queue_len(), 0);
+    assert_eq!(memory.lines().len(), 6);
+    assert_eq!(memory.lines()[5], "crashing");
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn channel_flush_test() {
+    let flushes = sync::Arc::new(sync::atomic::AtomicUsize::new(0));
+    let memory = fern::MemoryLogger::new();
+    let output = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+        vec![fern::OutputConfig::custom(Box::new(SlowLogger { output: memory.clone() })),
+             fern::OutputConfig::custom(Box::new(FlushCountingLogger {
+                 flushes: flushes.clone(),
+                 needs_flush: true,
+             }))],
+        log::LogLevelFilter::Trace, vec![]).unwrap();
+    let logger = fern::ChannelLogger::new(Box::new(output), 10, fern::QueueFullMode::Block);
+    assert!(fern::Logger::needs_flush(&logger));
+
+    let location = construct_fake_log_location();
+    for _ in 0..5 {
+        fern::Logger::log(&logger, "message", &log::LogLevel::Info, &location).unwrap();
+    }
+    fern::Logger::flush(&logger).unwrap();
+    // Everything queued before the flush has been logged, and then the output flushed
+    assert_eq!(logger.queue_len(), 0);
+    assert_eq!(memory.lines().len(), 5);
+    assert_eq!(flushes.load(sync::atomic::Ordering::SeqCst), 1);
+
+    let unbuffered = fern::ChannelLogger::new(Box::new(fern::NullLogger), 10,
+                                              fern::QueueFullMode::Block);
+    assert!(!fern::Logger::needs_flush(&unbuffered));
+}
+
+#[test]
+fn needs_flush_test() {
+    let buffered_flushes = sync::Arc::new(sync::atomic::AtomicUsize::new(0));
+    let unbuffered_flushes = sync::Arc::new(sync::atomic::AtomicUsize::new(0));
+    let buffered = FlushCountingLogger { flushes: buffered_flushes.clone(), needs_flush: true };
+    let unbuffered = FlushCountingLogger {
+        flushes: unbuffered_flushes.clone(),
+        needs_flush: false,
+    };
+
+    let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+        vec![fern::OutputConfig::null(), fern::OutputConfig::custom(Box::new(unbuffered))],
+        log::LogLevelFilter::Trace, vec![]).unwrap();
+    assert!(!fern::Logger::needs_flush(&logger));
+    fern::Logger::flush(&logger).unwrap();
+    assert_eq!(unbuffered_flushes.load(sync::atomic::Ordering::SeqCst), 0);
+    assert!(!fern::Logger::needs_flush(&fern::NullLogger));
+
+    let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+        vec![fern::OutputConfig::null(), fern::OutputConfig::custom(Box::new(buffered))],
+        log::LogLevelFilter::Trace, vec![]).unwrap();
+    assert!(fern::Logger::needs_flush(&logger));
+    fern::Logger::flush(&logger).unwrap();
+    assert_eq!(buffered_flushes.load(sync::atomic::Ordering::SeqCst), 1);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn boost_target_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let log_file = temp_log_dir.path().join("test_boost_target.log");
+
+    {
+        let logger = fern::DispatchLogger::new(
+            Box::new(|msg, level, _location| format!("[{}] {}", level, msg)),
+            vec![fern::OutputConfig::file(&log_file)],
+            log::LogLevelFilter::Info,
+            vec![fern::LogDirective::new("db".to_string(), log::LogLevelFilter::Warn)]).unwrap();
+        let log_all = |label: &str| {
+            for target in &["db::pool::conn", "db::query", "net"] {
+                // Logged from the module of the same name, as the `log` macros do by default
+                let location = log::LogLocation {
+                    __module_path: target,
+                    .. construct_fake_log_location()
+                };
+                for level in &[log::LogLevel::Info, log::LogLevel::Trace] {
+                    let msg = format!("{} {}", label, target);
+                    fern::Logger::log_with_target(&logger, &msg, level, &location, target)
+                        .unwrap();
+                }
+            }
+        };
+
+        log_all("before");
+        logger.boost_target("db::pool", log::LogLevelFilter::Trace, Duration::from_millis(200));
+        log_all("boosted");
+        thread::sleep(Duration::from_millis(300));
+        log_all("after");
+    }
+
+    let mut buf = String::new();
+    fs::File::open(&log_file).unwrap().read_to_string(&mut buf).unwrap();
+    assert_eq!(&buf, "[INFO] before net\n\
+                      [INFO] boosted db::pool::conn\n[TRACE] boosted db::pool::conn\n\
+                      [INFO] boosted net\n\
+                      [INFO] after net\n");
+}
+
+/// Writer which counts how many times it was synced.
+struct SyncCountingWriter {
+    syncs: sync::Arc<sync::atomic::AtomicUsize>,
+}
+
+impl io::Write for SyncCountingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        return Ok(buf.len());
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return Ok(());
+    }
+}
+
+impl fern::SyncAll for SyncCountingWriter {
+    fn sync_all(&self) -> io::Result<()> {
+        self.syncs.fetch_add(1, sync::atomic::Ordering::SeqCst);
+        return Ok(());
+    }
+}
+
+#[test]
+fn sync_level_test() {
+    let syncs = sync::Arc::new(sync::atomic::AtomicUsize::new(0));
+    let logger = fern::WriterLogger::new(SyncCountingWriter { syncs: syncs.clone() }, "\n")
+                    .sync_level(log::LogLevelFilter::Error);
+    let location = construct_fake_log_location();
+
+    fern::Logger::log(&logger, "info", &log::LogLevel::Info, &location).unwrap();
+    assert_eq!(syncs.load(sync::atomic::Ordering::SeqCst), 0);
+    fern::Logger::log(&logger, "error", &log::LogLevel::Error, &location).unwrap();
+    assert_eq!(syncs.load(sync::atomic::Ordering::SeqCst), 1);
+    fern::Logger::log_bytes(&logger, b"error", &log::LogLevel::Error, &location).unwrap();
+    assert_eq!(syncs.load(sync::atomic::Ordering::SeqCst), 2);
+
+    // Syncing is off by default
+    let logger = fern::WriterLogger::new(SyncCountingWriter { syncs: syncs.clone() }, "\n");
+    fern::Logger::log(&logger, "error", &log::LogLevel::Error, &location).unwrap();
+    assert_eq!(syncs.load(sync::atomic::Ordering::SeqCst), 2);
+}
+
+#[test]
+fn durability_test() {
+    let syncs = sync::Arc::new(sync::atomic::AtomicUsize::new(0));
+    let location = construct_fake_log_location();
+
+    let logger = fern::WriterLogger::new(SyncCountingWriter { syncs: syncs.clone() }, "\n")
+                    .durability(fern::Durability::FsyncEachLine);
+    fern::Logger::log(&logger, "info", &log::LogLevel::Info, &location).unwrap();
+    fern::Logger::log(&logger, "trace", &log::LogLevel::Trace, &location).unwrap();
+    assert_eq!(syncs.load(sync::atomic::Ordering::SeqCst), 2);
+
+    let logger = logger.durability(fern::Durability::FsyncEachError);
+    fern::Logger::log(&logger, "info", &log::LogLevel::Info, &location).unwrap();
+    fern::Logger::log(&logger, "error", &log::LogLevel::Error, &location).unwrap();
+    assert_eq!(syncs.load(sync::atomic::Ordering::SeqCst), 3);
+
+    // Periodic syncs happen once per interval while messages keep coming in, and not at all
+    // once they stop
+    syncs.store(0, sync::atomic::Ordering::SeqCst);
+    let logger = logger.durability(fern::Durability::PeriodicFsync(Duration::from_millis(50)));
+    let start = std::time::Instant::now();
+    for _ in 0..30 {
+        fern::Logger::log(&logger, "info", &log::LogLevel::Info, &location).unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+    let elapsed = start.elapsed();
+    let periodic = syncs.load(sync::atomic::Ordering::SeqCst);
+    let intervals = (elapsed.as_secs() * 1000 + elapsed.subsec_millis() as u64) as usize / 50;
+    assert!(periodic >= 3 && periodic <= intervals, "{} syncs in {:?}", periodic, elapsed);
+    thread::sleep(Duration::from_millis(120));
+    let settled = syncs.load(sync::atomic::Ordering::SeqCst);
+    thread::sleep(Duration::from_millis(120));
+    assert_eq!(syncs.load(sync::atomic::Ordering::SeqCst), settled);
+
+    let logger = logger.durability(fern::Durability::None);
+    fern::Logger::log(&logger, "error", &log::LogLevel::Error, &location).unwrap();
+    thread::sleep(Duration::from_millis(120));
+    assert_eq!(syncs.load(sync::atomic::Ordering::SeqCst), settled);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn message_hook_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let first_log = temp_log_dir.path().join("test_message_hook_1.log");
+    let second_log = temp_log_dir.path().join("test_message_hook_2.log");
+
+    {
+        let logger = fern::DispatchLogger::new(
+            Box::new(|msg, level, _location| format!("[{}] {}", level, msg)),
+            vec![fern::OutputConfig::file(&first_log), fern::OutputConfig::file(&second_log)],
+            log::LogLevelFilter::Info, vec![]).unwrap()
+            .message_hook(Box::new(|msg| {
+                // Masks everything after "token=" up to the next space
+                match msg.find("token=") {
+                    Some(start) => {
+                        let value_start = start + "token=".len();
+                        let value_end = msg[value_start..].find(' ')
+                                                          .map_or(msg.len(), |i| value_start + i);
+                        format!("{}***{}", &msg[..value_start], &msg[value_end..])
+                    },
+                    None => msg.to_string(),
+                }
+            }))
+            .message_hook(Box::new(|msg| format!("{} (checked)", msg)));
+        let location = construct_fake_log_location();
+        fern::Logger::log(&logger, "connecting with token=abc123 to host", &log::LogLevel::Info,
+                          &location).unwrap();
+        fern::Logger::log(&logger, "no secrets here", &log::LogLevel::Info, &location).unwrap();
+    }
+
+    for path in &[first_log, second_log] {
+        let mut buf = String::new();
+        fs::File::open(path).unwrap().read_to_string(&mut buf).unwrap();
+        assert_eq!(&buf, "[INFO] connecting with token=*** to host (checked)\n\
+                          [INFO] no secrets here (checked)\n");
+    }
+}
+
+#[test]
+fn output_tags_test() {
+    let mut logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+        vec![fern::OutputConfig::null()], log::LogLevelFilter::Info, vec![]).unwrap()
+        .tagged_output(Box::new(fern::NullLogger), &[("kind", "audit"), ("team", "sec")])
+        .tagged_output(Box::new(fern::WriterLogger::<io::Stderr>::with_stderr()),
+                       &[("kind", "console")]);
+
+    assert_eq!(logger.output_tags(0).unwrap().len(), 0);
+    assert_eq!(logger.output_tags(1).unwrap().get("team").map(|s| &**s), Some("sec"));
+    assert!(logger.output_tags(3).is_none());
+    assert_eq!(logger.find_outputs("kind", "audit"), vec![1]);
+    assert_eq!(logger.find_outputs("kind", "other"), Vec::<usize>::new());
+
+    let removed = logger.remove_outputs("kind", "audit");
+    assert_eq!(removed.len(), 1);
+    assert_eq!(removed[0].describe(), "null");
+    assert_eq!(logger.output_descriptions(), vec!["null", "stderr"]);
+    assert_eq!(logger.find_outputs("kind", "console"), vec![1]);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn format_into_test() {
+    use std::fmt::Write;
+
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let log_file = temp_log_dir.path().join("test_format_into.log");
+
+    {
+        let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+            vec![fern::OutputConfig::file(&log_file)], log::LogLevelFilter::Info, vec![]).unwrap()
+            .instance_tag("v1")
+            .format_into(Box::new(|buf: &mut String, msg: &str, level: &log::LogLevel,
+                                   _location: &log::LogLocation| {
+                write!(buf, "[{}] {}", level, msg).unwrap();
+            }));
+        let location = construct_fake_log_location();
+        fern::Logger::log(&logger, "first", &log::LogLevel::Info, &location).unwrap();
+        fern::Logger::log(&logger, "second", &log::LogLevel::Warn, &location).unwrap();
+        let logger = logger.message_hook(Box::new(|msg| msg.to_uppercase()));
+        fern::Logger::log(&logger, "hooked", &log::LogLevel::Info, &location).unwrap();
+    }
+
+    let mut buf = String::new();
+    fs::File::open(&log_file).unwrap().read_to_string(&mut buf).unwrap();
+    assert_eq!(&buf, "[v1] [INFO] first\n[v1] [WARN] second\n[V1] [INFO] HOOKED\n");
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn max_buffer_capacity_test() {
+    use std::fmt::Write;
+
+    // Run on a new thread, so the buffer isn't shared with other tests
+    thread::spawn(|| {
+        let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+            vec![fern::OutputConfig::null()], log::LogLevelFilter::Info, vec![]).unwrap()
+            .format_into(Box::new(|buf: &mut String, msg: &str, level: &log::LogLevel,
+                                   _location: &log::LogLocation| {
+                write!(buf, "[{}] {}", level, msg).unwrap();
+            }))
+            .max_buffer_capacity(1024);
+        let location = construct_fake_log_location();
+
+        fern::Logger::log(&logger, "normal", &log::LogLevel::Info, &location).unwrap();
+        let normal_capacity = logger.format_buffer_capacity();
+        assert!(normal_capacity > 0);
+        fern::Logger::log(&logger, "again", &log::LogLevel::Info, &location).unwrap();
+        // Normal messages keep reusing the same allocation
+        assert_eq!(logger.format_buffer_capacity(), normal_capacity);
+
+        let giant = "x".repeat(100_000);
+        fern::Logger::log(&logger, &giant, &log::LogLevel::Info, &location).unwrap();
+        let after_giant = logger.format_buffer_capacity();
+        assert!(after_giant >= normal_capacity && after_giant <= 1024, "{}", after_giant);
+        fern::Logger::log(&logger, "normal", &log::LogLevel::Info, &location).unwrap();
+        assert_eq!(logger.format_buffer_capacity(), after_giant);
+    }).join().unwrap();
+}
+
+/// Compares formatting with `format_into()` against the allocating formatter. Run with
+/// `cargo test --release -- --ignored format_into_benchmark --nocapture`.
+#[test]
+#[ignore]
+fn format_into_benchmark() {
+    use std::fmt::Write;
+    use std::time::Instant;
+
+    let iterations = 1000000;
+    let location = construct_fake_log_location();
+    let allocating = fern::DispatchLogger::new(
+        Box::new(|msg, level, location| format!("[{}][{}] {}", level, location.module_path(), msg)),
+        vec![fern::OutputConfig::null()], log::LogLevelFilter::Info, vec![]).unwrap();
+    let reusing = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+        vec![fern::OutputConfig::null()], log::LogLevelFilter::Info, vec![]).unwrap()
+        .format_into(Box::new(|buf: &mut String, msg: &str, level: &log::LogLevel,
+                               location: &log::LogLocation| {
+            write!(buf, "[{}][{}] {}", level, location.module_path(), msg).unwrap();
+        }));
+
+    for &(name, ref logger) in &[("format", &allocating), ("format_into", &reusing)] {
+        let start = Instant::now();
+        for _ in 0..iterations {
+            fern::Logger::log(*logger, "benchmark message", &log::LogLevel::Info, &location)
+                .unwrap();
+        }
+        println!("{}: {:?} for {} messages", name, start.elapsed(), iterations);
+    }
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn trim_trailing_whitespace_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let log_file = temp_log_dir.path().join("test_trim_trailing_whitespace.log");
+
+    {
+        let location = construct_fake_log_location();
+        let trimmed = fern::DispatchLogger::new(
+            Box::new(|msg, _level, _location| format!("{}\t \t", msg)),
+            vec![fern::OutputConfig::file(&log_file)], log::LogLevelFilter::Info, vec![]).unwrap()
+            .trim_trailing_whitespace(true);
+        fern::Logger::log(&trimmed, "a\t b", &log::LogLevel::Info, &location).unwrap();
+        let untrimmed = fern::DispatchLogger::new(
+            Box::new(|msg, _level, _location| format!("{}\t \t", msg)),
+            vec![fern::OutputConfig::file(&log_file)], log::LogLevelFilter::Info, vec![]).unwrap();
+        fern::Logger::log(&untrimmed, "a\t b", &log::LogLevel::Info, &location).unwrap();
+    }
+
+    let mut buf = String::new();
+    fs::File::open(&log_file).unwrap().read_to_string(&mut buf).unwrap();
+    assert_eq!(&buf, "a\t b\na\t b\t \t\n");
+}
+
+/// Logger which keeps every message it receives.
+struct CapturingLogger {
+    lines: sync::Arc<sync::Mutex<Vec<String>>>,
+}
+
+impl fern::Logger for CapturingLogger {
+    fn log(&self, msg: &str, _level: &log::LogLevel, _location: &log::LogLocation)
+            -> Result<(), fern::LogError> {
+        self.lines.lock().unwrap().push(msg.to_string());
+        return Ok(());
+    }
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn uptime_test() {
+    assert_eq!(fern::format_uptime(Duration::new(12, 345678901), 6), "[  12.345679s]");
+    assert_eq!(fern::format_uptime(Duration::new(1, 500000000), 3), "[   1.500s]");
+    assert_eq!(fern::format_uptime(Duration::new(12345, 0), 0), "[12345s]");
+
+    let lines = sync::Arc::new(sync::Mutex::new(Vec::new()));
+    let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+        vec![fern::OutputConfig::custom(Box::new(CapturingLogger { lines: lines.clone() }))],
+        log::LogLevelFilter::Info, vec![]).unwrap()
+        .uptime_prefix(9)
+        .instance_tag("v1");
+    let location = construct_fake_log_location();
+    for _ in 0..3 {
+        thread::sleep(Duration::from_millis(2));
+        fern::Logger::log(&logger, "tick", &log::LogLevel::Info, &location).unwrap();
+    }
+
+    let lines = lines.lock().unwrap();
+    let uptimes: Vec<f64> = lines.iter().map(|line| {
+        assert!(line.ends_with("s] [v1] tick"), "unexpected line {:?}", line);
+        line[1..line.find('s').unwrap()].trim().parse().unwrap()
+    }).collect();
+    assert!(uptimes[0] > 0.0);
+    assert!(uptimes[0] < uptimes[1] && uptimes[1] < uptimes[2], "{:?}", uptimes);
+    assert!(logger.uptime().as_secs() < 60);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn output_error_policy_test() {
+    let make_logger = |policy: fern::ErrorPolicy| {
+        fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+            vec![fern::OutputConfig::custom(Box::new(FailingLogger)),
+                 fern::OutputConfig::null()],
+            log::LogLevelFilter::Info, vec![]).unwrap()
+            .output_error_policy(0, policy)
+    };
+    let location = construct_fake_log_location();
+
+    let propagating = make_logger(fern::ErrorPolicy::Propagate);
+    assert!(fern::Logger::log(&propagating, "message", &log::LogLevel::Info, &location).is_err());
+
+    let ignoring = make_logger(fern::ErrorPolicy::Ignore);
+    fern::Logger::log(&ignoring, "message", &log::LogLevel::Info, &location).unwrap();
+
+    let errors = sync::Arc::new(sync::atomic::AtomicUsize::new(0));
+    let callback_errors = errors.clone();
+    let calling_back = make_logger(fern::ErrorPolicy::Callback(Box::new(move |_error| {
+        callback_errors.fetch_add(1, sync::atomic::Ordering::SeqCst);
+    })));
+    fern::Logger::log(&calling_back, "message", &log::LogLevel::Info, &location).unwrap();
+    fern::Logger::log(&calling_back, "message", &log::LogLevel::Info, &location).unwrap();
+    assert_eq!(errors.load(sync::atomic::Ordering::SeqCst), 2);
+}
+
+#[test]
+fn exclusive_file_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let log_file = temp_log_dir.path().join("test_exclusive_file.log");
+
+    let first = fern::WriterLogger::<fs::File>::with_file(&log_file, "\n").unwrap()
+                    .exclusive(true).unwrap();
+    // Without the lock, the file can still be opened
+    let unlocked = fern::WriterLogger::<fs::File>::with_file(&log_file, "\n").unwrap();
+    assert!(unlocked.exclusive(true).is_err());
+
+    drop(first);
+    fern::WriterLogger::<fs::File>::with_file(&log_file, "\n").unwrap().exclusive(true).unwrap();
+}
+
+#[test]
+fn parse_level_test() {
+    for s in &["DEBUG", "debug", "Debug", "dEbUg"] {
+        assert_eq!(fern::parse_level(s), Some(log::LogLevelFilter::Debug));
+    }
+    assert_eq!(fern::parse_level("Trace"), Some(log::LogLevelFilter::Trace));
+    assert_eq!(fern::parse_level("WARN"), Some(log::LogLevelFilter::Warn));
+    assert_eq!(fern::parse_level("off"), Some(log::LogLevelFilter::Off));
+    assert_eq!(fern::parse_level("None"), Some(log::LogLevelFilter::Off));
+    assert_eq!(fern::parse_level("warning"), None);
+    assert_eq!(fern::parse_level(""), None);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn directives_string_test() {
+    let memory = fern::MemoryLogger::new();
+    let (level, directives) =
+        fern::parse_directives(" Warn, my_app=debug,my_app::net==INFO,noisy=off").unwrap();
+    assert_eq!(level, Some(log::LogLevelFilter::Warn));
+    let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+        vec![fern::OutputConfig::custom(Box::new(memory.clone()))], level.unwrap(), directives)
+        .unwrap();
+    let config = logger.directives_string();
+    assert_eq!(config, "warn,noisy=off,my_app=debug,my_app::net==info");
+
+    // Parsing the string back gives a logger letting through the same messages
+    let parsed_memory = fern::MemoryLogger::new();
+    let (level, directives) = fern::parse_directives(&config).unwrap();
+    let parsed = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+        vec![fern::OutputConfig::custom(Box::new(parsed_memory.clone()))], level.unwrap(),
+        directives).unwrap();
+    assert_eq!(parsed.directives_string(), config);
+    let levels = [log::LogLevel::Error, log::LogLevel::Warn, log::LogLevel::Info,
+                  log::LogLevel::Debug, log::LogLevel::Trace];
+    for module in &["other", "noisy", "my_app", "my_app::db", "my_app::net"] {
+        let location = log::LogLocation {
+            __module_path: module,
+            .. construct_fake_log_location()
+        };
+        for level in &levels {
+            let msg = format!("{} {}", module, level);
+            fern::Logger::log(&logger, &msg, level, &location).unwrap();
+            fern::Logger::log(&parsed, &msg, level, &location).unwrap();
+        }
+    }
+    assert_eq!(memory.lines(), parsed_memory.lines());
+    assert_eq!(memory.lines().len(), 6);
+
+    // A bare name lets everything through for it
+    let (level, directives) = fern::parse_directives("my_app").unwrap();
+    assert_eq!(level, None);
+    assert_eq!((&*directives[0].name, directives[0].level), ("my_app", log::LogLevelFilter::Trace));
+    assert!(fern::parse_directives("my_app=loud").is_none());
+
+    // The lenient version keeps the good entries, and describes the bad ones
+    let (level, directives, errors) =
+        fern::parse_directives_lenient("warn,my_app=loud,my_app::net==info, noisy = off ");
+    assert_eq!(level, Some(log::LogLevelFilter::Warn));
+    assert_eq!(directives.iter().map(|d| (&*d.name, d.level, d.inclusive, d.exact))
+                         .collect::<Vec<_>>(),
+               vec![("my_app::net", log::LogLevelFilter::Info, false, true),
+                    ("noisy", log::LogLevelFilter::Off, true, false)]);
+    assert_eq!(errors, vec!["invalid level `loud` in `my_app=loud`"]);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn directives_helper_test() {
+    let memory = fern::MemoryLogger::new();
+    // Listed shortest last, to check that the longest prefix wins regardless of order
+    let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+        vec![fern::OutputConfig::custom(Box::new(memory.clone()))], log::LogLevelFilter::Trace,
+        fern::directives(&[("my_app::db", log::LogLevelFilter::Trace),
+                           ("my_app", log::LogLevelFilter::Warn),
+                           ("hyper", log::LogLevelFilter::Off)])).unwrap();
+
+    for &module in &["my_app::db::pool", "my_app::net", "hyper::client", "other"] {
+        let location = log::LogLocation {
+            __module_path: module,
+            .. construct_fake_log_location()
+        };
+        fern::Logger::log(&logger, &format!("{} debug", module), &log::LogLevel::Debug,
+                          &location).unwrap();
+        fern::Logger::log(&logger, &format!("{} warn", module), &log::LogLevel::Warn, &location)
+            .unwrap();
+    }
+    // A Warn directive only lets through more severe messages
+    assert_eq!(memory.lines(), vec!["my_app::db::pool debug", "my_app::db::pool warn",
+                                    "other debug", "other warn"]);
+}
+
+#[test]
+fn level_colors_test() {
+    let error = log::LogLevel::Error;
+    let info = log::LogLevel::Info;
+    let colors = fern::LevelColors::new().level(info, fern::Rgb(95, 175, 255));
+    assert_eq!(colors.current_mode(), fern::ColorMode::Basic);
+    assert_eq!(colors.paint_level(&error), "\x1b[31mERROR\x1b[0m");
+    assert_eq!(colors.escape(&info), "\x1b[36m");
+
+    let colors = colors.mode(fern::ColorMode::Ansi256);
+    assert_eq!(colors.escape(&error), "\x1b[38;5;160m");
+    assert_eq!(colors.escape(&info), "\x1b[38;5;75m");
+
+    // Truecolor is only used when the terminal says it supports it
+    env::set_var("COLORTERM", "truecolor");
+    let colors = colors.mode(fern::ColorMode::TrueColor);
+    assert_eq!(colors.current_mode(), fern::ColorMode::TrueColor);
+    assert_eq!(colors.paint(&info, "message"), "\x1b[38;2;95;175;255mmessage\x1b[0m");
+    env::remove_var("COLORTERM");
+    let colors = colors.mode(fern::ColorMode::TrueColor);
+    assert_eq!(colors.current_mode(), fern::ColorMode::Basic);
+    assert_eq!(colors.escape(&info), "\x1b[36m");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn journald_logger_test() {
+    use std::os::unix::net::UnixDatagram;
+
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let socket_path = temp_log_dir.path().join("journal.socket");
+    let journal = UnixDatagram::bind(&socket_path).unwrap();
+    let logger = fern::JournaldLogger::with_socket(&socket_path).unwrap();
+
+    let location = construct_fake_log_location();
+    fern::Logger::log_with_target(&logger, "disk full\nretrying", &log::LogLevel::Warn,
+                                  &location, "my_app::db").unwrap();
+    let mut buf = [0; 256];
+    let len = journal.recv(&mut buf).unwrap();
+    // Values with a newline are written with their length
+    assert_eq!(&buf[..len], &b"MESSAGE\n\x12\0\0\0\0\0\0\0disk full\nretrying\n\
+                               PRIORITY=4\nCODE_FILE=tests.rs\nCODE_LINE=0\n\
+                               TARGET=my_app::db\n"[..]);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn auto_output_test() {
+    use std::os::unix::fs::MetadataExt;
+
+    let describe = || {
+        fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+            vec![fern::auto_output()], log::LogLevelFilter::Info, vec![]).unwrap()
+            .output_descriptions()
+    };
+    // Only a JOURNAL_STREAM matching stderr selects the journal
+    let stderr = fs::metadata("/proc/self/fd/2").unwrap();
+    env::set_var("JOURNAL_STREAM", format!("{}:{}", stderr.dev(), stderr.ino()));
+    assert_eq!(describe(), vec!["journald"]);
+    env::set_var("JOURNAL_STREAM", format!("{}:{}", stderr.dev(), stderr.ino() + 1));
+    assert_eq!(describe(), vec!["stderr"]);
+    env::remove_var("JOURNAL_STREAM");
+    assert_eq!(describe(), vec!["stderr"]);
+}
+
+#[test]
+fn level_str_test() {
+    for level in &[log::LogLevel::Error, log::LogLevel::Warn, log::LogLevel::Info,
+                   log::LogLevel::Debug, log::LogLevel::Trace] {
+        assert_eq!(fern::level_str(level), level.to_string());
+    }
+}
+
+#[test]
+fn rotation_coordinator_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let period = sync::Arc::new(sync::Mutex::new("day1".to_string()));
+    let schedule_period = period.clone();
+    let coordinator = fern::RotationCoordinator::new(Box::new(move || {
+        schedule_period.lock().unwrap().clone()
+    }));
+    let names = ["app.log", "access.log", "error.log"];
+    let loggers: Vec<fern::RotatingFileLogger> = names.iter().map(|name| {
+        coordinator.open(temp_log_dir.path().join(name), "\n").unwrap()
+    }).collect();
+    assert_eq!(coordinator.file_count(), 3);
+
+    let location = construct_fake_log_location();
+    for logger in &loggers {
+        fern::Logger::log(logger, "first day", &log::LogLevel::Info, &location).unwrap();
+    }
+    *period.lock().unwrap() = "day2".to_string();
+    // Logging to any one of the files rotates all of them
+    fern::Logger::log(&loggers[0], "second day", &log::LogLevel::Info, &location).unwrap();
+
+    let read = |name: &str| {
+        let mut buf = String::new();
+        fs::File::open(temp_log_dir.path().join(name)).unwrap().read_to_string(&mut buf).unwrap();
+        buf
+    };
+    for name in &names {
+        assert_eq!(read(&format!("{}.day1", name)), "first day\n");
+    }
+    assert_eq!(read("app.log"), "second day\n");
+    assert_eq!(read("access.log"), "");
+    assert_eq!(read("error.log"), "");
+
+    drop(loggers);
+    assert_eq!(coordinator.file_count(), 0);
+}
+
+#[test]
+fn rotate_now_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let coordinator = fern::RotationCoordinator::new(Box::new(|| "day1".to_string()));
+    let logger = sync::Arc::new(coordinator.open(temp_log_dir.path().join("app.log"), "\n")
+                                           .unwrap());
+    let read = |name: &str| {
+        let mut buf = String::new();
+        fs::File::open(temp_log_dir.path().join(name)).unwrap().read_to_string(&mut buf).unwrap();
+        buf
+    };
+
+    let location = construct_fake_log_location();
+    fern::Logger::log(&*logger, "before backup", &log::LogLevel::Info, &location).unwrap();
+    let sealed = logger.rotate_now().unwrap().unwrap();
+    assert_eq!(sealed, temp_log_dir.path().join("app.log.day1"));
+    assert_eq!(read("app.log.day1"), "before backup\n");
+    assert_eq!(read("app.log"), "");
+
+    // Rotating while other threads log loses and splits nothing
+    let writers: Vec<_> = (0..4).map(|_| {
+        let logger = logger.clone();
+        thread::spawn(move || {
+            let location = construct_fake_log_location();
+            for _ in 0..100 {
+                fern::Logger::log(&*logger, "message", &log::LogLevel::Info, &location).unwrap();
+            }
+        })
+    }).collect();
+    for _ in 0..5 {
+        logger.rotate_now().unwrap();
+    }
+    for writer in writers {
+        writer.join().unwrap();
+    }
+    let mut contents = read("app.log");
+    for counter in 1..6 {
+        contents.push_str(&read(&format!("app.log.day1.{}", counter)));
+    }
+    assert_eq!(contents.lines().count(), 400);
+    assert!(contents.lines().all(|line| line == "message"));
+}
+
+#[test]
+fn rotate_now_factory_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let factory = |path: &::std::path::Path| -> io::Result<Box<io::Write + Send>> {
+        return Ok(Box::new(try!(fs::OpenOptions::new().append(true).create(true).open(path))));
+    };
+    let period = sync::Arc::new(sync::Mutex::new("day1".to_string()));
+    let schedule_period = period.clone();
+    let coordinator = fern::RotationCoordinator::new(Box::new(move || {
+        schedule_period.lock().unwrap().clone()
+    }));
+    let logger = coordinator.open_with_factory(temp_log_dir.path().join("app.log"), "\n",
+                                               Box::new(factory)).unwrap();
+    let read = |name: &str| {
+        let mut buf = String::new();
+        fs::File::open(temp_log_dir.path().join(name)).unwrap().read_to_string(&mut buf).unwrap();
+        buf
+    };
+
+    let location = construct_fake_log_location();
+    fern::Logger::log(&logger, "first", &log::LogLevel::Info, &location).unwrap();
+    // Each forced rotation starts a new file, rather than reopening the period's file
+    assert_eq!(logger.rotate_now().unwrap(), None);
+    assert!(temp_log_dir.path().join("app.log.day1.1").exists());
+    fern::Logger::log(&logger, "second", &log::LogLevel::Info, &location).unwrap();
+    logger.rotate_now().unwrap();
+    fern::Logger::log(&logger, "third", &log::LogLevel::Info, &location).unwrap();
+    // The counter starts again in the next period
+    *period.lock().unwrap() = "day2".to_string();
+    fern::Logger::log(&logger, "next day", &log::LogLevel::Info, &location).unwrap();
+    logger.rotate_now().unwrap();
+
+    assert_eq!(read("app.log.day1"), "first\n");
+    assert_eq!(read("app.log.day1.1"), "second\n");
+    assert_eq!(read("app.log.day1.2"), "third\n");
+    assert_eq!(read("app.log.day2"), "next day\n");
+    assert_eq!(read("app.log.day2.1"), "");
+}
+
+#[test]
+fn max_total_bytes_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let period = sync::Arc::new(sync::Mutex::new("day1".to_string()));
+    let schedule_period = period.clone();
+    let coordinator = fern::RotationCoordinator::new(Box::new(move || {
+        schedule_period.lock().unwrap().clone()
+    }));
+    let logger = coordinator.open(temp_log_dir.path().join("app.log"), "\n").unwrap()
+                            .max_total_bytes(25);
+    // Not a rotated file, so it's left alone
+    fs::File::create(temp_log_dir.path().join("other.log.day1")).unwrap()
+        .write_all(&[0; 100]).unwrap();
+
+    let location = construct_fake_log_location();
+    let rotated_bytes = || -> u64 {
+        fs::read_dir(temp_log_dir.path()).unwrap().map(|entry| entry.unwrap())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with("app.log."))
+            .map(|entry| entry.metadata().unwrap().len()).sum()
+    };
+    for day in 2..6 {
+        fern::Logger::log(&logger, "123456789", &log::LogLevel::Info, &location).unwrap();
+        *period.lock().unwrap() = format!("day{}", day);
+        fern::Logger::log(&logger, "rotate", &log::LogLevel::Info, &location).unwrap();
+        assert!(rotated_bytes() <= 25, "{} bytes after day {}", rotated_bytes(), day);
+    }
+
+    // The first day's file holds 10 bytes and the others 17, so only the latest one fits
+    let exists = |name: &str| temp_log_dir.path().join(name).exists();
+    assert!(!exists("app.log.day1") && !exists("app.log.day2") && !exists("app.log.day3"));
+    assert!(exists("app.log.day4") && exists("other.log.day1"));
+}
+
+#[test]
+fn concurrent_rotation_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let day = sync::Arc::new(sync::atomic::AtomicUsize::new(1));
+    let schedule_day = day.clone();
+    let calls = sync::atomic::AtomicUsize::new(0);
+    // A schedule which is sometimes slow, so that some threads check with a period which has
+    // already ended by the time they're done
+    let coordinator = fern::RotationCoordinator::new(Box::new(move || {
+        let day = schedule_day.load(sync::atomic::Ordering::SeqCst);
+        if calls.fetch_add(1, sync::atomic::Ordering::SeqCst) % 4 == 0 {
+            thread::sleep(Duration::from_millis(3));
+        }
+        format!("day{}", day)
+    }));
+    let logger = sync::Arc::new(coordinator.open(temp_log_dir.path().join("app.log"), "\n")
+                                           .unwrap());
+
+    let done = sync::Arc::new(sync::atomic::AtomicBool::new(false));
+    let writers: Vec<_> = (0..8).map(|_| {
+        let (logger, done) = (logger.clone(), done.clone());
+        thread::spawn(move || {
+            let location = construct_fake_log_location();
+            let mut count = 0;
+            while !done.load(sync::atomic::Ordering::SeqCst) {
+                fern::Logger::log(&*logger, "message", &log::LogLevel::Info, &location).unwrap();
+                count += 1;
+            }
+            count
+        })
+    }).collect();
+    for next_day in 2..6 {
+        thread::sleep(Duration::from_millis(20));
+        day.store(next_day, sync::atomic::Ordering::SeqCst);
+    }
+    thread::sleep(Duration::from_millis(20));
+    done.store(true, sync::atomic::Ordering::SeqCst);
+    let logged: usize = writers.into_iter().map(|writer| writer.join().unwrap()).sum();
+
+    // Each day was rotated exactly once
+    let mut names: Vec<String> = fs::read_dir(temp_log_dir.path()).unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned()).collect();
+    names.sort();
+    assert_eq!(names, vec!["app.log", "app.log.day1", "app.log.day2", "app.log.day3",
+                           "app.log.day4"]);
+    let mut lines = 0;
+    for name in &names {
+        let mut buf = String::new();
+        fs::File::open(temp_log_dir.path().join(name)).unwrap().read_to_string(&mut buf).unwrap();
+        lines += buf.lines().count();
+    }
+    assert_eq!(lines, logged);
+}
+
+/// Writer which appends to a buffer shared with the test, keyed by the name it was opened with.
+struct NamedBufferWriter {
+    name: String,
+    files: sync::Arc<sync::Mutex<Vec<(String, Vec<u8>)>>>,
+}
+
+impl io::Write for NamedBufferWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut files = self.files.lock().unwrap();
+        let file = files.iter_mut().find(|file| file.0 == self.name).unwrap();
+        file.1.extend_from_slice(buf);
+        return Ok(buf.len());
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return Ok(());
+    }
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn pipe_round_trip_test() {
+    let (reader, writer) = io::pipe().unwrap();
+    let location = log::LogLocation { __line: 42, .. construct_fake_log_location() };
+    {
+        let logger = fern::PipeLogger::new(writer);
+        fern::Logger::log(&logger, "from the worker", &log::LogLevel::Warn, &location).unwrap();
+        fern::Logger::log_with_target(&logger, "multi\nline ünïcode", &log::LogLevel::Trace,
+                                      &location, "worker::jobs").unwrap();
+    }
+
+    // Dropping the logger closed the pipe, ending the records
+    let mut reader = fern::PipeReader::new(reader);
+    let first = reader.read_record().unwrap().unwrap();
+    assert_eq!(first, fern::PipeRecord {
+        level: log::LogLevel::Warn,
+        target: "test".to_string(),
+        file: "tests.rs".to_string(),
+        line: 42,
+        message: "from the worker".to_string(),
+    });
+    let second = reader.read_record().unwrap().unwrap();
+    assert_eq!((second.level, &*second.target, &*second.message),
+               (log::LogLevel::Trace, "worker::jobs", "multi\nline ünïcode"));
+    assert!(reader.read_record().unwrap().is_none());
+
+    // The supervisor's directives see forwarded records as logged from `fern::pipe`
+    let memory = fern::MemoryLogger::new();
+    let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+        vec![fern::OutputConfig::custom(Box::new(memory.clone()))], log::LogLevelFilter::Trace,
+        vec![fern::LogDirective::new("fern::pipe".to_string(), log::LogLevelFilter::Info)])
+        .unwrap();
+    first.dispatch(&logger).unwrap();
+    second.dispatch(&logger).unwrap();
+    assert_eq!(memory.lines(), vec!["from the worker"]);
+
+    let mut garbage = fern::PipeReader::new(&[0, 0, 0, 5, 9, 0, 0, 0, 0][..]);
+    assert_eq!(garbage.read_record().unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn level_prefix_writer_test() {
+    assert_eq!(fern::parse_level_prefix("WARNING:  low on disk"),
+               (log::LogLevel::Warn, "low on disk"));
+    assert_eq!(fern::parse_level_prefix("[Trace] x"), (log::LogLevel::Trace, "x"));
+    assert_eq!(fern::parse_level_prefix("note: not a level"),
+               (log::LogLevel::Info, "note: not a level"));
+
+    let memory = fern::MemoryLogger::new();
+    {
+        let mut writer = fern::LevelPrefixWriter::new(sync::Arc::new(memory.clone()));
+        // Lines may be split across writes, and end with \r\n
+        writer.write_all(b"ERROR: boom\r\nDEBUG: par").unwrap();
+        writer.write_all(b"tial\nplain line\nINFO: unfinished").unwrap();
+    }
+    assert_eq!(memory.records(), vec![
+        (log::LogLevel::Error, "boom".to_string()),
+        (log::LogLevel::Debug, "partial".to_string()),
+        (log::LogLevel::Info, "plain line".to_string()),
+        (log::LogLevel::Info, "unfinished".to_string()),
+    ]);
+}
+
+#[test]
+fn rotation_writer_factory_test() {
+    let files = sync::Arc::new(sync::Mutex::new(Vec::new()));
+    let factory_files = files.clone();
+    let factory = move |path: &::std::path::Path| -> io::Result<Box<io::Write + Send>> {
+        let name = path.to_str().unwrap().to_string();
+        factory_files.lock().unwrap().push((name.clone(), Vec::new()));
+        return Ok(Box::new(NamedBufferWriter { name: name, files: factory_files.clone() }));
+    };
+
+    let period = sync::Arc::new(sync::Mutex::new("day1".to_string()));
+    let schedule_period = period.clone();
+    let coordinator = fern::RotationCoordinator::new(Box::new(move || {
+        schedule_period.lock().unwrap().clone()
+    }));
+    let logger = coordinator.open_with_factory("logs/app.log", "\n", Box::new(factory)).unwrap();
+
+    let location = construct_fake_log_location();
+    fern::Logger::log(&logger, "first day", &log::LogLevel::Info, &location).unwrap();
+    *period.lock().unwrap() = "day2".to_string();
+    fern::Logger::log(&logger, "second day", &log::LogLevel::Info, &location).unwrap();
+    *period.lock().unwrap() = "day3".to_string();
+    fern::Logger::log(&logger, "third day", &log::LogLevel::Info, &location).unwrap();
+
+    let files: Vec<(String, String)> = files.lock().unwrap().iter().map(|&(ref name, ref data)| {
+        (name.clone(), String::from_utf8(data.clone()).unwrap())
+    }).collect();
+    assert_eq!(files, vec![
+        ("logs/app.log.day1".to_string(), "first day\n".to_string()),
+        ("logs/app.log.day2".to_string(), "second day\n".to_string()),
+        ("logs/app.log.day3".to_string(), "third day\n".to_string()),
+    ]);
+}
+
+#[test]
+fn rotation_flush_test() {
+    let files = sync::Arc::new(sync::Mutex::new(Vec::new()));
+    let factory_files = files.clone();
+    let factory = move |path: &::std::path::Path| -> io::Result<Box<io::Write + Send>> {
+        let name = path.to_str().unwrap().to_string();
+        factory_files.lock().unwrap().push((name.clone(), Vec::new()));
+        let writer = NamedBufferWriter { name: name, files: factory_files.clone() };
+        return Ok(Box::new(io::BufWriter::new(writer)));
+    };
+    let coordinator = fern::RotationCoordinator::new(Box::new(|| "day1".to_string()));
+    let logger = coordinator.open_with_factory("logs/app.log", "\n", Box::new(factory)).unwrap();
+    assert!(fern::Logger::needs_flush(&logger));
+
+    let location = construct_fake_log_location();
+    fern::Logger::log(&logger, "buffered", &log::LogLevel::Info, &location).unwrap();
+    assert!(files.lock().unwrap()[0].1.is_empty());
+    fern::Logger::flush(&logger).unwrap();
+    assert_eq!(files.lock().unwrap()[0].1, b"buffered\n");
+
+    // Files fern opens itself aren't buffered
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let plain = coordinator.open(temp_log_dir.path().join("plain.log"), "\n").unwrap();
+    assert!(!fern::Logger::needs_flush(&plain));
+}
+
+#[test]
+fn utf8_bom_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let log_file = temp_log_dir.path().join("bom.log");
+    let location = construct_fake_log_location();
+
+    for message in &["created", "appended"] {
+        let logger = fern::WriterLogger::<fs::File>::with_file(&log_file, "\n").unwrap()
+                        .utf8_bom().unwrap();
+        fern::Logger::log(&logger, message, &log::LogLevel::Info, &location).unwrap();
+    }
+
+    // The mark is only written to the new file
+    let mut buf = Vec::new();
+    fs::File::open(&log_file).unwrap().read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, b"\xef\xbb\xbfcreated\nappended\n");
+}
+
+#[test]
+#[cfg(unix)]
+fn file_mode_test() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let mode = |name: &str| {
+        fs::metadata(temp_log_dir.path().join(name)).unwrap().permissions().mode() & 0o777
+    };
+    let location = construct_fake_log_location();
+
+    let logger = fern::WriterLogger::<fs::File>::with_file_mode(
+        &temp_log_dir.path().join("private.log"), "\n", 0o600).unwrap();
+    fern::Logger::log(&logger, "secret", &log::LogLevel::Info, &location).unwrap();
+    assert_eq!(mode("private.log"), 0o600);
+
+    let period = sync::Arc::new(sync::Mutex::new("day1".to_string()));
+    let schedule_period = period.clone();
+    let coordinator = fern::RotationCoordinator::new(Box::new(move || {
+        schedule_period.lock().unwrap().clone()
+    }));
+    let logger = coordinator.open_with_mode(temp_log_dir.path().join("app.log"), "\n", 0o600)
+        .unwrap();
+    fern::Logger::log(&logger, "first day", &log::LogLevel::Info, &location).unwrap();
+    *period.lock().unwrap() = "day2".to_string();
+    fern::Logger::log(&logger, "second day", &log::LogLevel::Info, &location).unwrap();
+    // The file started by rotating gets the same permissions
+    assert_eq!(mode("app.log.day1"), 0o600);
+    assert_eq!(mode("app.log"), 0o600);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn truncated_file_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let log_file = temp_log_dir.path().join("run.log");
+    let read = || {
+        let mut contents = String::new();
+        fs::File::open(&log_file).unwrap().read_to_string(&mut contents).unwrap();
+        contents
+    };
+    let location = construct_fake_log_location();
+
+    for run in &["first run", "second run"] {
+        let logger = fern::WriterLogger::<fs::File>::with_file(&log_file, "\n").unwrap();
+        fern::Logger::log(&logger, run, &log::LogLevel::Info, &location).unwrap();
+    }
+    // Appending keeps the earlier runs
+    assert_eq!(read(), "first run\nsecond run\n");
+
+    let logger = fern::WriterLogger::<fs::File>::with_truncated_file(&log_file, "\n").unwrap();
+    assert_eq!(read(), "");
+    fern::Logger::log(&logger, "third run", &log::LogLevel::Info, &location).unwrap();
+    assert_eq!(read(), "third run\n");
+
+    let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+        vec![fern::OutputConfig::truncated_file(&log_file)], log::LogLevelFilter::Info,
+        vec![]).unwrap();
+    fern::Logger::log(&logger, "fourth run", &log::LogLevel::Info, &location).unwrap();
+    assert_eq!(read(), "fourth run\n");
+}
+
+/// Logger whose configuration is always broken.
+struct MisconfiguredLogger;
+
+impl fern::Logger for MisconfiguredLogger {
+    fn log(&self, _msg: &str, _level: &log::LogLevel, _location: &log::LogLocation)
+            -> Result<(), fern::LogError> {
+        return Ok(());
+    }
+
+    fn describe(&self) -> String {
+        return "misconfigured".to_string();
+    }
+
+    fn validate(&self) -> Vec<String> {
+        return vec!["broken setting".to_string()];
+    }
+}
+
+#[test]
+fn validate_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let existing = temp_log_dir.path().join("existing.log");
+    fs::File::create(&existing).unwrap().write_all(b"kept\n").unwrap();
+    let new = temp_log_dir.path().join("new.log");
+    let not_a_dir = existing.join("app.log");
+    let missing_dir = temp_log_dir.path().join("missing").join("app.log");
+
+    let valid = fern::DispatchConfig {
+        format: Box::new(|msg, level, _location| format!("[{}] {}", level, msg)),
+        output: vec![fern::OutputConfig::file(&existing), fern::OutputConfig::truncated_file(&new),
+                     fern::OutputConfig::stderr()],
+        level: log::LogLevelFilter::Info,
+        directives: vec![fern::LogDirective::new("my_app::db".to_string(),
+                                                 log::LogLevelFilter::Debug)],
+    };
+    assert_eq!(fern::validate(&valid), Ok(()));
+    // Checking leaves the files as they were
+    let mut contents = String::new();
+    fs::File::open(&existing).unwrap().read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "kept\n");
+    assert!(!new.exists());
+
+    let invalid = fern::DispatchConfig {
+        format: Box::new(|msg, _level, _location| msg[..msg.find(';').unwrap()].to_string()),
+        output: vec![fern::OutputConfig::child(fern::DispatchConfig {
+            format: Box::new(|msg, _level, _location| msg.to_string()),
+            output: vec![fern::OutputConfig::null(), fern::OutputConfig::file(&not_a_dir),
+                         fern::OutputConfig::file(&missing_dir)],
+            level: log::LogLevelFilter::Info,
+            directives: vec![fern::LogDirective::new("".to_string(), log::LogLevelFilter::Debug)],
+        }), fern::OutputConfig::custom(Box::new(MisconfiguredLogger))],
+        level: log::LogLevelFilter::Info,
+        directives: vec![fern::LogDirective::new("my_app db=debug".to_string(),
+                                                 log::LogLevelFilter::Debug)],
+    };
+    let problems = fern::validate(&invalid).unwrap_err();
+    assert_eq!(problems.len(), 6, "{:?}", problems);
+    assert_eq!(problems[0], "directive #0 has a module name which can't be parsed: \
+                             \"my_app db=debug\"");
+    assert!(problems[1].starts_with("formatter panicked on a sample record: "), "{}", problems[1]);
+    assert_eq!(problems[2], "output #0 > directive #0 has an empty module name");
+    assert!(problems[3].starts_with(&format!("output #0 > output #1 (file ({})): can't open for \
+                                              writing: ", not_a_dir.display())), "{}", problems[3]);
+    let expected = format!("output #0 > output #2 (file ({})): can't open for writing: ",
+                           missing_dir.display());
+    assert!(problems[4].starts_with(&expected), "{}", problems[4]);
+    assert!(!missing_dir.parent().unwrap().exists());
+    assert_eq!(problems[5], "output #1 (misconfigured): broken setting");
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn rotating_file_overflow_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    // Writes to /dev/full always fail with ENOSPC. Rotating renames the link, not the device.
+    let main_path = temp_log_dir.path().join("app.log");
+    let overflow_path = temp_log_dir.path().join("overflow.log");
+    ::std::os::unix::fs::symlink("/dev/full", &main_path).unwrap();
+    let period = sync::Arc::new(sync::Mutex::new("day1".to_string()));
+    let schedule_period = period.clone();
+    let coordinator = fern::RotationCoordinator::new(Box::new(move || {
+        schedule_period.lock().unwrap().clone()
+    }));
+    let logger = coordinator.open(&main_path, "\n").unwrap().overflow(&overflow_path);
+
+    let location = construct_fake_log_location();
+    fern::Logger::log(&logger, "first", &log::LogLevel::Info, &location).unwrap();
+    fern::Logger::log(&logger, "second", &log::LogLevel::Info, &location).unwrap();
+    *period.lock().unwrap() = "day2".to_string();
+    fern::Logger::log(&logger, "after rotating", &log::LogLevel::Info, &location).unwrap();
+
+    let read = |path: &::std::path::Path| {
+        let mut buf = String::new();
+        fs::File::open(path).unwrap().read_to_string(&mut buf).unwrap();
+        buf
+    };
+    assert_eq!(read(&overflow_path), format!(
+        "fern: {} is full, logging here until it's rotated\nfirst\nsecond\n", main_path.display()));
+    assert_eq!(read(&main_path), "after rotating\n");
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn failing_output_context_test() {
+    // The backup message goes to stderr, so this test runs itself in a child process to capture
+    // it. The child sets the global logger, which only affects that process.
+    if env::var("FERN_OUTPUT_CONTEXT_TEST_CHILD").is_ok() {
+        let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+            vec![fern::OutputConfig::null(), fern::OutputConfig::custom(Box::new(FailingLogger))],
+            log::LogLevelFilter::Info, vec![]).unwrap();
+        fern::init_global_logger(logger, log::LogLevelFilter::Info).unwrap();
+        info!("message for a failing output");
+        return;
+    }
+
+    let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+        vec![fern::OutputConfig::null(), fern::OutputConfig::custom(Box::new(FailingLogger))],
+        log::LogLevelFilter::Info, vec![]).unwrap();
+    let location = construct_fake_log_location();
+    match fern::Logger::log(&logger, "message", &log::LogLevel::Info, &location) {
+        Err(fern::LogError::Output{index: 1, ref description, ..}) if description == "custom" => {},
+        other => panic!("Expected an error from output #1, found {:?}", other),
+    }
+
+    let output = process::Command::new(env::current_exe().unwrap())
+        .args(&["--exact", "failing_output_context_test", "--nocapture", "--test-threads=1"])
+        .env("FERN_OUTPUT_CONTEXT_TEST_CHILD", "1")
+        .output().unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("arguments: message for a failing output}: output #1 (custom) failed: \
+                             IO Error: failing output"), "{}", stderr);
+}
+
+#[test]
+fn log_error_encode_test() {
+    let errors = [
+        fern::LogError::Io(io::Error::new(io::ErrorKind::PermissionDenied, "access: denied")),
+        fern::LogError::Poison("poisoned lock: another task failed".to_string()),
+        fern::LogError::Output {
+            index: 2,
+            description: "file (a:b.log)".to_string(),
+            error: Box::new(fern::LogError::Output {
+                index: 0,
+                description: "udp (127.0.0.1:514)".to_string(),
+                error: Box::new(fern::LogError::Io(io::Error::new(io::ErrorKind::TimedOut,
+                                                                  "timed out"))),
+            }),
+        },
+    ];
+    let codes: Vec<&str> = errors.iter().map(|error| error.code()).collect();
+    assert_eq!(codes, vec!["io", "poison", "output"]);
+    assert_eq!(errors[0].encode(), "io:PermissionDenied:access: denied");
+
+    let encoded: Vec<String> = errors.iter().map(|error| error.encode()).collect();
+    for (error, encoded) in errors.iter().zip(&encoded) {
+        let decoded = fern::LogError::decode(encoded).unwrap();
+        assert_eq!(decoded.code(), error.code());
+        assert_eq!(decoded.to_string(), error.to_string());
+        assert_eq!(&decoded.encode(), encoded);
+    }
+    match fern::LogError::decode(&encoded[2]) {
+        Some(fern::LogError::Output{error, ..}) => match *error {
+            fern::LogError::Output{error, ..} => match *error {
+                fern::LogError::Io(ref e) => assert_eq!(e.kind(), io::ErrorKind::TimedOut),
+                other => panic!("Expected an IO error, found {:?}", other),
+            },
+            other => panic!("Expected an output error, found {:?}", other),
+        },
+        other => panic!("Expected an output error, found {:?}", other),
+    }
+
+    // A full disk keeps its kind as well
+    let full = fern::LogError::Io(io::Error::new(io::ErrorKind::StorageFull, "disk full"));
+    assert_eq!(full.encode(), "io:StorageFull:disk full");
+    match fern::LogError::decode(&full.encode()) {
+        Some(fern::LogError::Io(ref e)) => assert_eq!(e.kind(), io::ErrorKind::StorageFull),
+        other => panic!("Expected an IO error, found {:?}", other),
+    }
+
+    for invalid in &["", "io", "io:NoSuchKind:message", "output:1:50:short", "other:message"] {
+        assert!(fern::LogError::decode(invalid).is_none(), "{}", invalid);
+    }
+}
+
+/// Writer which panics when asked to write "panic", and otherwise discards everything.
+struct PanickingWriter;
+
+impl io::Write for PanickingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf == b"panic" {
+            panic!("writer panicked");
+        }
+        return Ok(buf.len());
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return Ok(());
+    }
+}
+
+/// Poisons the writer lock of `logger` by making its writer panic on another thread.
+fn poison_writer(logger: &sync::Arc<fern::WriterLogger<PanickingWriter>>) {
+    let thread_logger = logger.clone();
+    let result = thread::spawn(move || {
+        let location = construct_fake_log_location();
+        let _ = fern::Logger::log(&*thread_logger, "panic", &log::LogLevel::Info, &location);
+    }).join();
+    assert!(result.is_err());
+}
+
+#[test]
+fn recover_poisoned_test() {
+    // The warning goes to stderr, so this test runs itself in a child process to capture it
+    let location = construct_fake_log_location();
+    if env::var("FERN_RECOVER_POISONED_TEST_CHILD").is_ok() {
+        let logger = sync::Arc::new(fern::WriterLogger::new(PanickingWriter, "")
+                                        .label("panicky").recover_poisoned(true));
+        poison_writer(&logger);
+        for _ in 0..3 {
+            fern::Logger::log(&*logger, "after", &log::LogLevel::Info, &location).unwrap();
+        }
+        fern::Logger::flush(&*logger).unwrap();
+        return;
+    }
+
+    let logger = sync::Arc::new(fern::WriterLogger::new(PanickingWriter, ""));
+    poison_writer(&logger);
+    match fern::Logger::log(&*logger, "after", &log::LogLevel::Info, &location) {
+        Err(fern::LogError::Poison(..)) => {},
+        other => panic!("Expected a poison error, found {:?}", other),
+    }
+
+    let output = process::Command::new(env::current_exe().unwrap())
+        .args(&["--exact", "recover_poisoned_test", "--nocapture", "--test-threads=1"])
+        .env("FERN_RECOVER_POISONED_TEST_CHILD", "1")
+        .output().unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stderr.matches("Warning: a thread panicked while writing to fern output panicky")
+                     .count(), 1, "{}", stderr);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn stderr_backup_test() {
+    // The backup goes to stderr, so this test runs itself in a child process to capture it
+    if let Ok(backup) = env::var("FERN_STDERR_BACKUP_TEST_CHILD") {
+        let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+            vec![fern::OutputConfig::custom(Box::new(FailingLogger))], log::LogLevelFilter::Info,
+            vec![]).unwrap()
+            .stderr_backup(backup == "on");
+        fern::init_global_logger(logger, log::LogLevelFilter::Info).unwrap();
+        error!("lost message");
+        return;
+    }
+
+    let run_child = |backup: &str| {
+        let output = process::Command::new(env::current_exe().unwrap())
+            .args(&["--exact", "stderr_backup_test", "--nocapture", "--test-threads=1"])
+            .env("FERN_STDERR_BACKUP_TEST_CHILD", backup)
+            .output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+    let backup = run_child("on");
+    assert!(backup.contains("lost message"));
+    assert!(!run_child("off").contains("lost message"));
+
+    // The location is shown as the file name and line
+    let location = backup.split("location: ").nth(1).unwrap_or("");
+    let line = location.split(", ").next().unwrap();
+    assert!(line.starts_with("lib.rs:") && line["lib.rs:".len()..].parse::<u32>().is_ok(),
+            "{}", backup);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn console_line_sep_test() {
+    // Runs itself in a child process to see exactly what is written to stderr
+    if env::var("FERN_CONSOLE_LINE_SEP_TEST_CHILD").is_ok() {
+        let logger = fern::DispatchLogger::with_line_sep(
+            Box::new(|msg, _level, _location| msg.to_string()),
+            vec![fern::OutputConfig::stderr()], log::LogLevelFilter::Info, vec![], "\r\n").unwrap();
+        let location = construct_fake_log_location();
+        fern::Logger::log(&logger, "message", &log::LogLevel::Info, &location).unwrap();
+        return;
+    }
+
+    let output = process::Command::new(env::current_exe().unwrap())
+        .args(&["--exact", "console_line_sep_test", "--nocapture", "--test-threads=1"])
+        .env("FERN_CONSOLE_LINE_SEP_TEST_CHILD", "1")
+        .output().unwrap();
+    assert!(output.status.success());
+    let expected: &[u8] = match cfg!(windows) {
+        // Text mode readers turn this into \r\n, so a \r\n separator would be doubled
+        true => b"message\n",
+        false => b"message\r\n",
+    };
+    assert_eq!(output.stderr, expected);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn record_numbers_test() {
+    // Numbers are counted per process, so this test runs itself in child processes to start
+    // from 1
+    if env::var("FERN_RECORD_NUMBERS_TEST_CHILD").is_ok() {
+        let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+            vec![fern::OutputConfig::stderr()], log::LogLevelFilter::Info, vec![]).unwrap()
+            .record_numbers(6)
+            .instance_tag("v1");
+        let location = construct_fake_log_location();
+        for msg in &["first", "second", "third"] {
+            fern::Logger::log(&logger, msg, &log::LogLevel::Info, &location).unwrap();
+        }
+        let narrow = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+            vec![fern::OutputConfig::stderr()], log::LogLevelFilter::Info, vec![]).unwrap()
+            .record_numbers(0);
+        fern::Logger::log(&narrow, "fourth", &log::LogLevel::Info, &location).unwrap();
+        // An output filtering the context formats the message itself, with the same number
+        let filtered = fern::DispatchLogger::new(
+            Box::new(|msg, _level, _location| msg.to_string()),
+            vec![fern::OutputConfig::stderr(), fern::OutputConfig::stderr()],
+            log::LogLevelFilter::Info, vec![]).unwrap()
+            .record_numbers(3)
+            .output_context(1, fern::ContextFilter::Only(vec!["request_id".to_string()]));
+        for msg in &["fifth", "sixth"] {
+            fern::Logger::log(&filtered, msg, &log::LogLevel::Info, &location).unwrap();
+        }
+        return;
+    }
+
+    let run_child = || {
+        let output = process::Command::new(env::current_exe().unwrap())
+            .args(&["--exact", "record_numbers_test", "--nocapture", "--test-threads=1"])
+            .env("FERN_RECORD_NUMBERS_TEST_CHILD", "1")
+            .output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+    let expected = "#000001 [v1] first\n#000002 [v1] second\n#000003 [v1] third\n#4 fourth\n\
+                    #005 fifth\n#005 fifth\n#006 sixth\n#006 sixth\n";
+    assert_eq!(run_child(), expected);
+    assert_eq!(run_child(), expected);
+}
+
+#[test]
+#[cfg(not(feature = "static_max_level"))]
+fn heartbeat_test() {
+    let memory = fern::MemoryLogger::new();
+    let logger = sync::Arc::new(fern::DispatchLogger::new(
+        Box::new(|msg, level, location| format!("[{} {}] {}", level, location.module_path(), msg)),
+        vec![fern::OutputConfig::custom(Box::new(memory.clone()))], log::LogLevelFilter::Info,
+        vec![]).unwrap());
+    let heartbeat = fern::Heartbeat::start(logger.clone(), Duration::from_millis(50),
+                                           log::LogLevel::Info);
+    thread::sleep(Duration::from_millis(275));
+    drop(heartbeat);
+
+    // About 5 heartbeats, which is loosely checked as timing varies
+    let lines = memory.lines();
+    assert!(lines.len() >= 3 && lines.len() <= 6, "{:?}", lines);
+    assert!(lines.iter().all(|line| line == "[INFO fern::heartbeat] heartbeat"), "{:?}", lines);
+
+    // None are logged once it's stopped
+    thread::sleep(Duration::from_millis(150));
+    assert_eq!(memory.lines().len(), lines.len());
+}
+
+#[test]
+fn gelf_chunking_test() {
+    let receiver = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    receiver.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let logger = fern::UdpLogger::new(receiver.local_addr().unwrap()).unwrap()
+                    .gelf_chunking(112);
+    let location = construct_fake_log_location();
+    let mut buf = [0u8; 200];
+
+    // Messages which fit in one chunk are sent as they are
+    fern::Logger::log(&logger, "short", &log::LogLevel::Info, &location).unwrap();
+    let len = receiver.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..len], b"short");
+
+    // Each chunk holds at most 100 bytes after the 12 byte header
+    let message: String = (0..1050).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+    fern::Logger::log(&logger, &message, &log::LogLevel::Error, &location).unwrap();
+    let mut chunks = Vec::new();
+    for _ in 0..11 {
+        let len = receiver.recv(&mut buf).unwrap();
+        chunks.push(buf[..len].to_vec());
+    }
+    let id = chunks[0][2..10].to_vec();
+    let mut reassembled = Vec::new();
+    for (sequence, chunk) in chunks.iter().enumerate() {
+        assert_eq!(&chunk[..2], &[0x1e, 0x0f]);
+        assert_eq!(&chunk[2..10], &*id);
+        assert_eq!(chunk[10] as usize, sequence);
+        assert_eq!(chunk[11], 11);
+        assert_eq!(chunk.len(), if sequence < 10 { 112 } else { 62 });
+        reassembled.extend_from_slice(&chunk[12..]);
+    }
+    assert_eq!(reassembled, message.as_bytes());
+
+    // Graylog accepts at most 128 chunks
+    let huge = "x".repeat(100 * 128 + 1);
+    assert!(fern::Logger::log(&logger, &huge, &log::LogLevel::Error, &location).is_err());
+}
+
+/// Writer which blocks in every write until released.
+struct BlockingWriter {
+    started: sync::mpsc::Sender<()>,
+    release: sync::mpsc::Receiver<()>,
+}
+
+impl io::Write for BlockingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let _ = self.started.send(());
+        let _ = self.release.recv();
+        return Ok(buf.len());
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return Ok(());
+    }
+}
+
+#[test]
+fn non_blocking_writer_test() {
+    let (started_sender, started) = sync::mpsc::channel();
+    let (release, release_receiver) = sync::mpsc::channel();
+    let writer = BlockingWriter { started: started_sender, release: release_receiver };
+    let logger = sync::Arc::new(fern::WriterLogger::new(writer, "").non_blocking(true));
+
+    // The first message holds the writer until released
+    let thread_logger = logger.clone();
+    let thread = thread::spawn(move || {
+        let location = construct_fake_log_location();
+        fern::Logger::log(&thread_logger, "first", &log::LogLevel::Info, &location).unwrap();
+    });
+    started.recv().unwrap();
+
+    let location = construct_fake_log_location();
+    for _ in 0..3 {
+        fern::Logger::log(&logger, "contended", &log::LogLevel::Info, &location).unwrap();
+    }
+    let dropped_count = logger.dropped_count();
+
+    // Let the writing thread finish before checking, so a failure doesn't hang the test
+    drop(release);
+    thread.join().unwrap();
+    assert_eq!(dropped_count, 3);
+}
+
+#[test]
+fn write_in_background_test() {
+    let (started_sender, started) = sync::mpsc::channel();
+    let (release, release_receiver) = sync::mpsc::channel();
+    let writer = BlockingWriter { started: started_sender, release: release_receiver };
+    let logger = fern::WriterLogger::new(writer, "")
+        .write_in_background(2, fern::QueueFullMode::Drop);
+
+    // The background thread blocks on the first message, like on a full pipe
+    let location = construct_fake_log_location();
+    fern::Logger::log(&logger, "first", &log::LogLevel::Info, &location).unwrap();
+    started.recv().unwrap();
+
+    // Two messages fit in the queue, and the rest are dropped without waiting
+    for _ in 0..5 {
+        fern::Logger::log(&logger, "while blocked", &log::LogLevel::Info, &location).unwrap();
+    }
+    let queue_len = logger.queue_len();
+    let dropped_count = logger.dropped_count();
+
+    drop(release);
+    drop(logger);
+    assert_eq!((queue_len, dropped_count), (2, 3));
+}
+
+#[test]
+fn channel_logger_ordering_test() {
+    for &mode in &[fern::QueueFullMode::Block, fern::QueueFullMode::Drop] {
+        let memory = fern::MemoryLogger::new();
+        let logger = sync::Arc::new(fern::ChannelLogger::new(Box::new(memory.clone()), 16, mode));
+        let threads: Vec<_> = (0..4).map(|thread| {
+            let logger = logger.clone();
+            thread::spawn(move || {
+                let location = construct_fake_log_location();
+                for i in 0..500 {
+                    fern::Logger::log(&*logger, &format!("{} {}", thread, i),
+                                      &log::LogLevel::Info, &location).unwrap();
+                }
+            })
+        }).collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let dropped_count = logger.dropped_count();
+        drop(logger);
+
+        // Each thread's messages arrive in the order it logged them, with gaps only for dropped
+        // messages
+        let mut last = vec![None; 4];
+        let lines = memory.lines();
+        for line in &lines {
+            let mut parts = line.split(' ').map(|part| part.parse::<usize>().unwrap());
+            let (thread, i) = (parts.next().unwrap(), parts.next().unwrap());
+            assert!(last[thread].map_or(true, |last| i > last), "{:?}", lines);
+            last[thread] = Some(i);
+        }
+        assert_eq!(lines.len() + dropped_count, 2000);
+        if mode == fern::QueueFullMode::Block {
+            assert_eq!(last, vec![Some(499); 4]);
+        }
+    }
+}
+
 /// This may be a bad idea, but it seems necessary for using fern::Logger. Maybe this should be
 /// fixed. This may break with upstream changes from log, but since this is only in tests, not in
 /// the actual code, it should be fine.
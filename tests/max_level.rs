@@ -0,0 +1,38 @@
+#![cfg(all(feature = "max_level_info", not(any(feature = "max_level_off",
+                                             feature = "max_level_error",
+                                             feature = "max_level_warn"))))]
+extern crate log;
+extern crate fern;
+
+use std::sync;
+
+/// Logger which keeps every message it receives.
+struct CapturingLogger {
+    lines: sync::Arc<sync::Mutex<Vec<String>>>,
+}
+
+impl fern::Logger for CapturingLogger {
+    fn log(&self, msg: &str, _level: &log::LogLevel, _location: &log::LogLocation)
+            -> Result<(), fern::LogError> {
+        self.lines.lock().unwrap().push(msg.to_string());
+        return Ok(());
+    }
+}
+
+#[test]
+fn max_level_info_test() {
+    let lines = sync::Arc::new(sync::Mutex::new(Vec::new()));
+    let logger = fern::DispatchLogger::new(Box::new(|msg, _level, _location| msg.to_string()),
+        vec![fern::OutputConfig::custom(Box::new(CapturingLogger { lines: lines.clone() }))],
+        log::LogLevelFilter::Trace, vec![]).unwrap();
+    let location = log::LogLocation {
+        __module_path: "test",
+        __file: "tests/max_level.rs",
+        __line: 1,
+    };
+    for level in &[log::LogLevel::Trace, log::LogLevel::Debug, log::LogLevel::Info,
+                   log::LogLevel::Error] {
+        fern::Logger::log(&logger, &format!("{}", level), level, &location).unwrap();
+    }
+    assert_eq!(*lines.lock().unwrap(), vec!["INFO", "ERROR"]);
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "otlp")]
+extern crate log;
+extern crate fern;
+
+mod common;
+
+use std::net;
+use std::thread;
+
+#[test]
+fn otlp_severity_test() {
+    // From the OpenTelemetry log data model: the first number of each severity range
+    assert_eq!(fern::otlp_severity(&log::LogLevel::Trace), (1, "TRACE"));
+    assert_eq!(fern::otlp_severity(&log::LogLevel::Debug), (5, "DEBUG"));
+    assert_eq!(fern::otlp_severity(&log::LogLevel::Info), (9, "INFO"));
+    assert_eq!(fern::otlp_severity(&log::LogLevel::Warn), (13, "WARN"));
+    assert_eq!(fern::otlp_severity(&log::LogLevel::Error), (17, "ERROR"));
+}
+
+#[test]
+fn otlp_export_test() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}/v1/logs", listener.local_addr().unwrap());
+    let server = thread::spawn(move || common::receive_requests(listener, 1));
+
+    let logger = fern::OtlpLogger::new(&endpoint, "test-service").batch_size(2);
+    let location = log::LogLocation {
+        __module_path: "my_app::db",
+        __file: "src/db.rs",
+        __line: 42,
+    };
+    fern::Logger::log(&logger, "first \"quoted\"", &log::LogLevel::Error, &location).unwrap();
+    // The second record fills the batch, which sends it
+    fern::Logger::log(&logger, "second", &log::LogLevel::Info, &location).unwrap();
+
+    let (bodies, request_len) = server.join().unwrap();
+    let body = &bodies[0];
+    assert_eq!(logger.bytes_written(), request_len as u64);
+    assert!(body.starts_with("{\"resourceLogs\":[{\"resource\":{\"attributes\":[{\"key\":\
+                              \"service.name\",\"value\":{\"stringValue\":\"test-service\"}}]}"),
+            "{}", body);
+    assert!(body.contains("\"severityNumber\":17,\"severityText\":\"ERROR\",\
+                           \"body\":{\"stringValue\":\"first \\\"quoted\\\"\"}"), "{}", body);
+    assert!(body.contains("\"severityNumber\":9,\"severityText\":\"INFO\""), "{}", body);
+    assert!(body.contains("{\"key\":\"code.namespace\",\"value\":{\"stringValue\":\
+                           \"my_app::db\"}}"), "{}", body);
+    assert!(body.contains("{\"key\":\"code.lineno\",\"value\":{\"intValue\":\"42\"}}"), "{}", body);
+}
@@ -0,0 +1,150 @@
+#![cfg(feature = "redis")]
+extern crate log;
+extern crate fern;
+
+use std::io::prelude::*;
+use std::io;
+use std::net;
+use std::thread;
+
+/// Reads one command in the Redis protocol, returning its arguments.
+fn read_command<R: BufRead>(reader: &mut R) -> Vec<String> {
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert!(line.starts_with('*'), "{:?}", line);
+    let count: usize = line[1..].trim().parse().unwrap();
+    return (0..count).map(|_| {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert!(line.starts_with('$'), "{:?}", line);
+        let len: usize = line[1..].trim().parse().unwrap();
+        let mut arg = vec![0; len + 2];
+        reader.read_exact(&mut arg).unwrap();
+        arg.truncate(len);
+        String::from_utf8(arg).unwrap()
+    }).collect();
+}
+
+/// Accepts a connection for each of `batches`, reads that many commands on it, replying to
+/// each with a stream entry id, and then closes it. Returns the commands.
+fn serve_redis(listener: net::TcpListener, batches: Vec<usize>) -> Vec<Vec<String>> {
+    let mut commands = Vec::new();
+    let mut id = 0;
+    for count in batches {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = io::BufReader::new(stream);
+        for _ in 0..count {
+            commands.push(read_command(&mut reader));
+            id += 1;
+            let reply = format!("{}-0", id);
+            write!(reader.get_mut(), "${}\r\n{}\r\n", reply.len(), reply).unwrap();
+        }
+    }
+    return commands;
+}
+
+fn xadd(level: &str, message: &str) -> Vec<String> {
+    return vec!["XADD", "logs", "*", "level", level, "target", "my_app::db", "file", "src/db.rs",
+                "line", "42", "message", message].into_iter().map(String::from).collect();
+}
+
+#[test]
+fn redis_stream_test() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    // The first connection is closed after the first batch, so the logger has to reconnect
+    let server = thread::spawn(move || serve_redis(listener, vec![2, 1]));
+
+    let logger = fern::RedisLogger::stream(&address, "logs").batch_size(2);
+    let location = log::LogLocation {
+        __module_path: "my_app::db",
+        __file: "src/db.rs",
+        __line: 42,
+    };
+    fern::Logger::log(&logger, "first", &log::LogLevel::Info, &location).unwrap();
+    fern::Logger::log(&logger, "second", &log::LogLevel::Warn, &location).unwrap();
+    fern::Logger::log(&logger, "third", &log::LogLevel::Error, &location).unwrap();
+    fern::Logger::flush(&logger).unwrap();
+
+    assert_eq!(server.join().unwrap(),
+               vec![xadd("INFO", "first"), xadd("WARN", "second"), xadd("ERROR", "third")]);
+}
+
+#[test]
+fn redis_error_reply_test() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = io::BufReader::new(stream);
+        let command = read_command(&mut reader);
+        reader.get_mut().write_all(b"-WRONGTYPE Operation against a key holding the wrong kind \
+                                     of value\r\n").unwrap();
+        command
+    });
+
+    let logger = fern::RedisLogger::list(&address, "logs");
+    let location = log::LogLocation {
+        __module_path: "my_app::db",
+        __file: "src/db.rs",
+        __line: 42,
+    };
+    let error = fern::Logger::log(&logger, "pushed", &log::LogLevel::Info, &location)
+        .unwrap_err();
+    assert!(error.to_string().contains("WRONGTYPE"), "{}", error);
+    assert_eq!(server.join().unwrap(), vec!["RPUSH", "logs", "pushed"]);
+    // The command is counted even though Redis replied with an error
+    assert_eq!(logger.bytes_written(),
+               b"*3\r\n$5\r\nRPUSH\r\n$4\r\nlogs\r\n$6\r\npushed\r\n".len() as u64);
+}
+
+#[test]
+fn redis_nested_error_reply_test() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = io::BufReader::new(stream);
+        let first = read_command(&mut reader);
+        let second = read_command(&mut reader);
+        // An error inside an array, followed by a reply which still has to be read
+        reader.get_mut().write_all(b"*2\r\n$3\r\n1-0\r\n-ERR nested failure\r\n:2\r\n")
+            .unwrap();
+        vec![first, second]
+    });
+
+    let logger = fern::RedisLogger::list(&address, "logs").batch_size(2);
+    let location = log::LogLocation {
+        __module_path: "my_app::db",
+        __file: "src/db.rs",
+        __line: 42,
+    };
+    fern::Logger::log(&logger, "first", &log::LogLevel::Info, &location).unwrap();
+    let error = fern::Logger::log(&logger, "second", &log::LogLevel::Info, &location)
+        .unwrap_err();
+    assert!(error.to_string().contains("ERR nested failure"), "{}", error);
+    assert_eq!(server.join().unwrap().len(), 2);
+}
+
+#[test]
+fn redis_validate_test() {
+    let file = std::env::current_exe().unwrap();
+    let not_a_dir = file.join("app.log");
+    let config = fern::DispatchConfig {
+        format: Box::new(|msg, _level, _location| msg.to_string()),
+        output: vec![
+            fern::OutputConfig::file(&not_a_dir),
+            fern::OutputConfig::custom(Box::new(
+                fern::RedisLogger::stream("redis.invalid:6379", "logs"))),
+            fern::OutputConfig::custom(Box::new(fern::RedisLogger::list("127.0.0.1:6379", "logs"))),
+        ],
+        level: log::LogLevelFilter::Info,
+        directives: vec![],
+    };
+    let problems = fern::validate(&config).unwrap_err();
+    assert_eq!(problems.len(), 2, "{:?}", problems);
+    assert!(problems[0].starts_with("output #0 (file ("), "{}", problems[0]);
+    assert!(problems[0].contains("can't open for writing"), "{}", problems[0]);
+    assert!(problems[1].starts_with("output #1 (redis (redis.invalid:6379, stream logs)): can't \
+                                     resolve redis.invalid:6379: "), "{}", problems[1]);
+}
@@ -0,0 +1,49 @@
+#![cfg(not(feature = "static_max_level"))]
+extern crate log;
+extern crate fern;
+extern crate tempdir;
+
+use std::io::prelude::*;
+use std::fs;
+use std::thread;
+
+fn construct_fake_log_location() -> log::LogLocation {
+    return log::LogLocation {
+        __module_path: "test",
+        __file: "tests.rs",
+        __line: 0,
+    };
+}
+
+#[test]
+fn ring_buffer_test() {
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let dump_file = temp_log_dir.path().join("crash.log");
+
+    let ring = fern::RingBufferLogger::new(3);
+    let logger = fern::DispatchLogger::new(Box::new(|msg, level, _location| {
+        format!("[{}] {}", level, msg)
+    }), vec![fern::OutputConfig::custom(Box::new(ring.clone()))], log::LogLevelFilter::Trace,
+        vec![]).unwrap();
+    let location = construct_fake_log_location();
+    for i in 1..6 {
+        fern::Logger::log(&logger, &format!("message {}", i), &log::LogLevel::Trace, &location)
+            .unwrap();
+    }
+    // Only the last three are kept
+    assert_eq!(ring.records(), vec!["[TRACE] message 3", "[TRACE] message 4",
+                                    "[TRACE] message 5"]);
+    let mut dumped = Vec::new();
+    ring.dump_to(&mut dumped).unwrap();
+    assert_eq!(dumped, b"[TRACE] message 3\n[TRACE] message 4\n[TRACE] message 5\n");
+
+    // This installs a panic hook for the whole process, which is why this test has its own file
+    ring.dump_on_panic(&dump_file);
+    fern::Logger::log(&logger, "about to panic", &log::LogLevel::Error, &location).unwrap();
+    assert!(thread::spawn(|| panic!("crash")).join().is_err());
+
+    let mut buf = String::new();
+    fs::File::open(&dump_file).unwrap().read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "[TRACE] message 4\n[TRACE] message 5\n[ERROR] about to panic\n");
+}
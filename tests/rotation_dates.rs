@@ -0,0 +1,74 @@
+//! Date based rotation on the days most likely to trip up date arithmetic. This sets the time
+//! zone of the whole process, so it has its own test binary.
+#![cfg(unix)]
+extern crate log;
+extern crate fern;
+extern crate tempdir;
+extern crate time;
+
+use std::io::prelude::*;
+use std::env;
+use std::fs;
+use std::sync;
+use std::sync::atomic;
+
+#[test]
+fn daily_rotation_dates_test() {
+    // US Eastern time, which starts daylight saving time on 2016-03-13 and ends it on 2016-11-06
+    env::set_var("TZ", "EST5EDT,M3.2.0,M11.1.0");
+
+    let temp_log_dir = tempdir::TempDir::new("fern").ok()
+                        .expect("Failed to set up temporary directory");
+    let now = sync::Arc::new(atomic::AtomicI64::new(1456721999));
+    let clock_now = now.clone();
+    let coordinator = fern::RotationCoordinator::daily_with_clock(Box::new(move || {
+        time::Timespec::new(clock_now.load(atomic::Ordering::SeqCst), 0)
+    }));
+    let logger = coordinator.open(temp_log_dir.path().join("app.log"), "\n").unwrap();
+    let location = log::LogLocation {
+        __module_path: "test",
+        __file: "tests.rs",
+        __line: 0,
+    };
+
+    let times = [
+        (1456721999, "2016-02-28 23:59:59"),
+        (1456722000, "2016-02-29 00:00:00"),
+        (1456808399, "2016-02-29 23:59:59"),
+        (1456808400, "2016-03-01 00:00:00"),
+        // A 23 hour day
+        (1457845200, "2016-03-13 00:00:00"),
+        (1457927999, "2016-03-13 23:59:59"),
+        (1457928000, "2016-03-14 00:00:00"),
+        // A 25 hour day, which is still the same date 24 hours after midnight
+        (1478404800, "2016-11-06 00:00:00"),
+        (1478491200, "2016-11-06 23:00:00"),
+        (1478494799, "2016-11-06 23:59:59"),
+        (1478494800, "2016-11-07 00:00:00"),
+        (1483246799, "2016-12-31 23:59:59"),
+        (1483246800, "2017-01-01 00:00:00"),
+    ];
+    for &(secs, local_time) in &times {
+        now.store(secs, atomic::Ordering::SeqCst);
+        fern::Logger::log(&logger, local_time, &log::LogLevel::Info, &location).unwrap();
+    }
+
+    let read = |name: &str| {
+        let mut buf = String::new();
+        fs::File::open(temp_log_dir.path().join(name)).unwrap().read_to_string(&mut buf).unwrap();
+        buf
+    };
+    let mut names: Vec<String> = fs::read_dir(temp_log_dir.path()).unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned()).collect();
+    names.sort();
+    assert_eq!(names, vec!["app.log", "app.log.2016-02-28", "app.log.2016-02-29",
+                           "app.log.2016-03-01", "app.log.2016-03-13", "app.log.2016-03-14",
+                           "app.log.2016-11-06", "app.log.2016-11-07", "app.log.2016-12-31"]);
+    assert_eq!(read("app.log.2016-02-28"), "2016-02-28 23:59:59\n");
+    assert_eq!(read("app.log.2016-02-29"), "2016-02-29 00:00:00\n2016-02-29 23:59:59\n");
+    assert_eq!(read("app.log.2016-03-13"), "2016-03-13 00:00:00\n2016-03-13 23:59:59\n");
+    assert_eq!(read("app.log.2016-11-06"),
+               "2016-11-06 00:00:00\n2016-11-06 23:00:00\n2016-11-06 23:59:59\n");
+    assert_eq!(read("app.log.2016-12-31"), "2016-12-31 23:59:59\n");
+    assert_eq!(read("app.log"), "2017-01-01 00:00:00\n");
+}
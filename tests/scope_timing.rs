@@ -0,0 +1,45 @@
+#![cfg(not(feature = "static_max_level"))]
+#[macro_use]
+extern crate log;
+extern crate fern;
+
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn timed_scope_test() {
+    let memory = fern::MemoryLogger::new();
+    // Timed scopes log through the global logger, which is why this test has its own file
+    fern::init_global_logger(fern::DispatchConfig {
+        format: Box::new(|msg, level, location| {
+            format!("[{}] [{}] [{}] {}", level, location.module_path(), fern::scope::current(),
+                    msg)
+        }),
+        output: vec![fern::OutputConfig::custom(Box::new(memory.clone()))],
+        level: log::LogLevelFilter::Debug,
+        directives: vec![],
+    }, log::LogLevelFilter::Trace).unwrap();
+
+    {
+        let _request = fern::scope::enter("request");
+        {
+            let _query = fern::scope::timed("db_query");
+            info!("querying");
+            thread::sleep(Duration::from_millis(50));
+        }
+        info!("queried");
+    }
+
+    let lines = memory.lines();
+    assert_eq!(lines.len(), 3, "{:?}", lines);
+    assert!(lines[0].ends_with("[request > db_query] querying"), "{}", lines[0]);
+    // The time is logged once the scope has been left, and plain scopes aren't timed
+    let prefix = "[DEBUG] [fern::scope] [request] scope 'db_query' took ";
+    assert!(lines[1].starts_with(prefix), "{}", lines[1]);
+    assert!(lines[2].ends_with("[request] queried"), "{}", lines[2]);
+
+    let took = &lines[1][prefix.len()..];
+    assert!(took.ends_with("ms"), "{}", took);
+    let millis: f64 = took.trim_end_matches("ms").parse().unwrap();
+    assert!(millis >= 50.0 && millis < 500.0, "{}", took);
+}
@@ -0,0 +1,39 @@
+#![cfg(unix)]
+extern crate log;
+extern crate fern;
+
+use std::process;
+use std::sync;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Sends SIGUSR1 to this process, and waits for the level of `logger` to change from `from`.
+fn send_signal(logger: &fern::DispatchLogger, from: log::LogLevelFilter) -> log::LogLevelFilter {
+    let status = process::Command::new("kill").arg("-USR1").arg(process::id().to_string())
+                                              .status().unwrap();
+    assert!(status.success());
+    let started = Instant::now();
+    while logger.level() == from && started.elapsed() < Duration::from_secs(5) {
+        thread::sleep(Duration::from_millis(5));
+    }
+    return logger.level();
+}
+
+#[test]
+fn cycle_level_on_sigusr1_test() {
+    let memory = fern::MemoryLogger::new();
+    let logger = sync::Arc::new(fern::DispatchLogger::new(
+        Box::new(|msg, level, location| format!("[{} {}] {}", level, location.module_path(), msg)),
+        vec![fern::OutputConfig::custom(Box::new(memory.clone()))],
+        log::LogLevelFilter::Warn, vec![]).unwrap());
+    fern::cycle_level_on_sigusr1(logger.clone()).unwrap();
+
+    assert_eq!(send_signal(&logger, log::LogLevelFilter::Warn), log::LogLevelFilter::Info);
+    assert_eq!(send_signal(&logger, log::LogLevelFilter::Info), log::LogLevelFilter::Debug);
+    assert_eq!(send_signal(&logger, log::LogLevelFilter::Debug), log::LogLevelFilter::Trace);
+    assert_eq!(send_signal(&logger, log::LogLevelFilter::Trace), log::LogLevelFilter::Error);
+    assert_eq!(memory.lines(), vec!["[INFO fern::level] log level changed to INFO",
+                                    "[INFO fern::level] log level changed to DEBUG",
+                                    "[INFO fern::level] log level changed to TRACE",
+                                    "[INFO fern::level] log level changed to ERROR"]);
+}
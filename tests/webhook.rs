@@ -0,0 +1,43 @@
+#![cfg(feature = "webhook")]
+extern crate log;
+extern crate fern;
+
+mod common;
+
+use std::net;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn webhook_test() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hooks/alerts", listener.local_addr().unwrap());
+    let server = thread::spawn(move || common::receive_requests(listener, 2));
+
+    let logger = fern::WebhookLogger::new(&url, log::LogLevel::Error)
+        .min_interval(Duration::from_millis(300));
+    let location = log::LogLocation {
+        __module_path: "my_app::db",
+        __file: "src/db.rs",
+        __line: 42,
+    };
+    fern::Logger::log(&logger, "slow query", &log::LogLevel::Warn, &location).unwrap();
+    fern::Logger::log(&logger, "disk \"sda\" full", &log::LogLevel::Error, &location).unwrap();
+    // Give the first request time to be sent on its own
+    thread::sleep(Duration::from_millis(100));
+    fern::Logger::log(&logger, "write failed", &log::LogLevel::Error, &location).unwrap();
+    fern::Logger::log(&logger, "retrying", &log::LogLevel::Info, &location).unwrap();
+    fern::Logger::log(&logger, "write failed again", &log::LogLevel::Error, &location).unwrap();
+
+    // Messages logged within the interval are sent together, and only errors are sent
+    let (bodies, requests_len) = server.join().unwrap();
+    assert_eq!(bodies, vec!["{\"text\":\"disk \\\"sda\\\" full\"}",
+                            "{\"text\":\"write failed\\nwrite failed again\"}"]);
+    // The background thread counts each request once it has been written, which may be just
+    // after the server has read it
+    let start = Instant::now();
+    while logger.bytes_written() < requests_len as u64 && start.elapsed() < Duration::from_secs(5) {
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(logger.bytes_written(), requests_len as u64);
+}